@@ -0,0 +1,667 @@
+//! Central action map: systems ask whether an [`Action`] is pressed (through [`ActionInput`])
+//! instead of checking raw `KeyCode`s, so every binding lives here and can change at runtime.
+//!
+//! Each action keeps its [`InputBinding`]s for all devices (keyboard, mouse and gamepad
+//! buttons); the analog [`AxisAction`]s choose their stick / mouse source. Rebinding refuses
+//! inputs another action already uses, and the map is saved as RON whenever it changes: to
+//! `<config dir>/crab_feast/input.ron` on native and to `localStorage` on wasm.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::input::ActiveInputDevice;
+
+pub struct ActionsPlugin;
+
+impl Plugin for ActionsPlugin {
+    fn build(&self, app: &mut App) {
+        let map = storage::load().unwrap_or_default();
+        for conflict in map.conflicts() {
+            warn!("Input bindings: {conflict}");
+        }
+        app.insert_resource(map)
+            .add_systems(Last, save_action_map.run_if(resource_changed::<ActionMap>));
+    }
+}
+
+/// Digital actions.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Action {
+    MoveForward,
+    MoveBack,
+    MoveLeft,
+    MoveRight,
+    /// Run instead of walk with digital movement.
+    Sprint,
+    Jump,
+    /// Keep facing while moving (see `MovementFacingMode`).
+    Strafe,
+    /// Grabs and hides the cursor for mouse look.
+    GrabCursor,
+    /// Releases the grabbed cursor.
+    Pause,
+    /// Free-fly debug camera up / down.
+    FlyUp,
+    FlyDown,
+    NextCameraRig,
+    SwapShoulder,
+    ToggleFreeFly,
+    /// Shows / hides Rapier collider outlines.
+    ToggleColliderDebug,
+    /// Shows / hides the spatial index and query gizmos.
+    ToggleSpatialDebug,
+    /// Plays the character's n-th `debug_clips` entry.
+    DebugClip(u8),
+}
+
+/// Number of [`Action::DebugClip`] slots bound by default.
+pub const DEBUG_CLIP_ACTIONS: u8 = 5;
+
+const MOVE_ACTIONS: [(Action, Vec2); 4] = [
+    (Action::MoveForward, Vec2::NEG_Y),
+    (Action::MoveBack, Vec2::Y),
+    (Action::MoveLeft, Vec2::NEG_X),
+    (Action::MoveRight, Vec2::X),
+];
+
+/// Analog actions fed by a stick or the mouse.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum AxisAction {
+    Move,
+    Look,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum InputDevice {
+    Keyboard,
+    Mouse,
+    Gamepad,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum InputBinding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+}
+
+impl InputBinding {
+    pub fn device(&self) -> InputDevice {
+        match self {
+            Self::Key(_) => InputDevice::Keyboard,
+            Self::Mouse(_) => InputDevice::Mouse,
+            Self::Gamepad(_) => InputDevice::Gamepad,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AxisBinding {
+    MouseMotion,
+    LeftStick,
+    RightStick,
+}
+
+impl AxisBinding {
+    pub fn device(&self) -> InputDevice {
+        match self {
+            Self::MouseMotion => InputDevice::Mouse,
+            Self::LeftStick | Self::RightStick => InputDevice::Gamepad,
+        }
+    }
+}
+
+/// The same input bound to two actions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BindingConflict {
+    Button {
+        binding: InputBinding,
+        existing: Action,
+        requested: Action,
+    },
+    Axis {
+        binding: AxisBinding,
+        existing: AxisAction,
+        requested: AxisAction,
+    },
+}
+
+impl fmt::Display for BindingConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Button {
+                binding,
+                existing,
+                requested,
+            } => write!(
+                f,
+                "{binding:?} is already bound to {existing:?}, not {requested:?}"
+            ),
+            Self::Axis {
+                binding,
+                existing,
+                requested,
+            } => write!(
+                f,
+                "{binding:?} is already bound to {existing:?}, not {requested:?}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BindingConflict {}
+
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ActionMap {
+    #[serde(default)]
+    buttons: BTreeMap<Action, Vec<InputBinding>>,
+    #[serde(default)]
+    axes: BTreeMap<AxisAction, Vec<AxisBinding>>,
+}
+
+impl Default for ActionMap {
+    fn default() -> Self {
+        use GamepadButton as G;
+        use InputBinding::{Gamepad, Key, Mouse};
+
+        let mut buttons = BTreeMap::from([
+            (
+                Action::MoveForward,
+                vec![
+                    Key(KeyCode::KeyW),
+                    Key(KeyCode::ArrowUp),
+                    Gamepad(G::DPadUp),
+                ],
+            ),
+            (
+                Action::MoveBack,
+                vec![
+                    Key(KeyCode::KeyS),
+                    Key(KeyCode::ArrowDown),
+                    Gamepad(G::DPadDown),
+                ],
+            ),
+            (
+                Action::MoveLeft,
+                vec![
+                    Key(KeyCode::KeyA),
+                    Key(KeyCode::ArrowLeft),
+                    Gamepad(G::DPadLeft),
+                ],
+            ),
+            (
+                Action::MoveRight,
+                vec![
+                    Key(KeyCode::KeyD),
+                    Key(KeyCode::ArrowRight),
+                    Gamepad(G::DPadRight),
+                ],
+            ),
+            (
+                Action::Sprint,
+                vec![
+                    Key(KeyCode::ShiftLeft),
+                    Key(KeyCode::ShiftRight),
+                    Gamepad(G::LeftThumb),
+                ],
+            ),
+            (Action::Jump, vec![Key(KeyCode::Space), Gamepad(G::South)]),
+            (
+                Action::Strafe,
+                vec![Key(KeyCode::AltLeft), Gamepad(G::LeftTrigger2)],
+            ),
+            (Action::GrabCursor, vec![Mouse(MouseButton::Left)]),
+            (Action::Pause, vec![Key(KeyCode::Escape), Gamepad(G::Start)]),
+            (
+                Action::FlyUp,
+                vec![Key(KeyCode::KeyE), Gamepad(G::RightTrigger)],
+            ),
+            (
+                Action::FlyDown,
+                vec![Key(KeyCode::KeyQ), Gamepad(G::LeftTrigger)],
+            ),
+            (
+                Action::NextCameraRig,
+                vec![Key(KeyCode::KeyV), Gamepad(G::North)],
+            ),
+            (
+                Action::SwapShoulder,
+                vec![Key(KeyCode::KeyC), Gamepad(G::RightThumb)],
+            ),
+            (Action::ToggleFreeFly, vec![Key(KeyCode::F8)]),
+            (Action::ToggleColliderDebug, vec![Key(KeyCode::F9)]),
+            (Action::ToggleSpatialDebug, vec![Key(KeyCode::F10)]),
+        ]);
+        let digits = [
+            KeyCode::Digit1,
+            KeyCode::Digit2,
+            KeyCode::Digit3,
+            KeyCode::Digit4,
+            KeyCode::Digit5,
+        ];
+        for (slot, key) in (0..DEBUG_CLIP_ACTIONS).zip(digits) {
+            buttons.insert(Action::DebugClip(slot), vec![Key(key)]);
+        }
+
+        Self {
+            buttons,
+            axes: BTreeMap::from([
+                (AxisAction::Move, vec![AxisBinding::LeftStick]),
+                (
+                    AxisAction::Look,
+                    vec![AxisBinding::MouseMotion, AxisBinding::RightStick],
+                ),
+            ]),
+        }
+    }
+}
+
+impl ActionMap {
+    pub fn bindings(&self, action: Action) -> &[InputBinding] {
+        self.buttons.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn axis_bindings(&self, axis: AxisAction) -> &[AxisBinding] {
+        self.axes.get(&axis).map_or(&[], Vec::as_slice)
+    }
+
+    /// The action `binding` triggers, if any.
+    pub fn action_for(&self, binding: InputBinding) -> Option<Action> {
+        self.buttons
+            .iter()
+            .find(|(_, bindings)| bindings.contains(&binding))
+            .map(|(action, _)| *action)
+    }
+
+    /// Adds `binding` to `action`, keeping its other bindings.
+    pub fn bind(&mut self, action: Action, binding: InputBinding) -> Result<(), BindingConflict> {
+        self.check_button(action, binding)?;
+        let bindings = self.buttons.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+        Ok(())
+    }
+
+    /// Replaces `action`'s bindings on `binding`'s device with `binding`; bindings on other
+    /// devices are kept, so rebinding a key leaves the gamepad button alone.
+    pub fn rebind(&mut self, action: Action, binding: InputBinding) -> Result<(), BindingConflict> {
+        self.check_button(action, binding)?;
+        let bindings = self.buttons.entry(action).or_default();
+        bindings.retain(|b| b.device() != binding.device());
+        bindings.push(binding);
+        Ok(())
+    }
+
+    pub fn unbind(&mut self, action: Action, binding: InputBinding) {
+        if let Some(bindings) = self.buttons.get_mut(&action) {
+            bindings.retain(|b| *b != binding);
+        }
+    }
+
+    /// Replaces `axis`'s source on `binding`'s device (e.g. swaps sticks for left-handed play).
+    pub fn rebind_axis(
+        &mut self,
+        axis: AxisAction,
+        binding: AxisBinding,
+    ) -> Result<(), BindingConflict> {
+        if let Some((&existing, _)) = self
+            .axes
+            .iter()
+            .find(|(other, bindings)| **other != axis && bindings.contains(&binding))
+        {
+            return Err(BindingConflict::Axis {
+                binding,
+                existing,
+                requested: axis,
+            });
+        }
+        let bindings = self.axes.entry(axis).or_default();
+        bindings.retain(|b| b.device() != binding.device());
+        bindings.push(binding);
+        Ok(())
+    }
+
+    pub fn unbind_axis(&mut self, axis: AxisAction, binding: AxisBinding) {
+        if let Some(bindings) = self.axes.get_mut(&axis) {
+            bindings.retain(|b| *b != binding);
+        }
+    }
+
+    /// Every input bound to more than one action, e.g. in a hand-edited settings file.
+    pub fn conflicts(&self) -> Vec<BindingConflict> {
+        let mut conflicts = Vec::new();
+        for (i, (&existing, bindings)) in self.buttons.iter().enumerate() {
+            for (&requested, others) in self.buttons.iter().skip(i + 1) {
+                conflicts.extend(
+                    bindings
+                        .iter()
+                        .filter(|b| others.contains(b))
+                        .map(|&binding| BindingConflict::Button {
+                            binding,
+                            existing,
+                            requested,
+                        }),
+                );
+            }
+        }
+        for (i, (&existing, bindings)) in self.axes.iter().enumerate() {
+            for (&requested, others) in self.axes.iter().skip(i + 1) {
+                conflicts.extend(
+                    bindings
+                        .iter()
+                        .filter(|b| others.contains(b))
+                        .map(|&binding| BindingConflict::Axis {
+                            binding,
+                            existing,
+                            requested,
+                        }),
+                );
+            }
+        }
+        conflicts
+    }
+
+    /// Gives actions missing from a saved map (e.g. added in a newer build) their defaults.
+    fn with_defaults(mut self) -> Self {
+        let defaults = Self::default();
+        for (action, bindings) in defaults.buttons {
+            self.buttons.entry(action).or_insert(bindings);
+        }
+        for (axis, bindings) in defaults.axes {
+            self.axes.entry(axis).or_insert(bindings);
+        }
+        self
+    }
+
+    fn check_button(&self, action: Action, binding: InputBinding) -> Result<(), BindingConflict> {
+        match self.action_for(binding) {
+            Some(existing) if existing != action => Err(BindingConflict::Button {
+                binding,
+                existing,
+                requested: action,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Reads [`Action`]s from the keyboard, mouse and the [`ActiveInputDevice`] gamepad, so an
+/// idle gamepad cannot fire actions, like it cannot move the character.
+#[derive(SystemParam)]
+pub struct ActionInput<'w, 's> {
+    map: Res<'w, ActionMap>,
+    keyboard: Res<'w, ButtonInput<KeyCode>>,
+    mouse: Res<'w, ButtonInput<MouseButton>>,
+    active_device: Res<'w, ActiveInputDevice>,
+    gamepads: Query<'w, 's, &'static Gamepad>,
+}
+
+impl ActionInput<'_, '_> {
+    pub fn map(&self) -> &ActionMap {
+        &self.map
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.map
+            .bindings(action)
+            .iter()
+            .any(|binding| match *binding {
+                InputBinding::Key(key) => self.keyboard.pressed(key),
+                InputBinding::Mouse(button) => self.mouse.pressed(button),
+                InputBinding::Gamepad(button) => self.gamepad().is_some_and(|g| g.pressed(button)),
+            })
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.map
+            .bindings(action)
+            .iter()
+            .any(|binding| match *binding {
+                InputBinding::Key(key) => self.keyboard.just_pressed(key),
+                InputBinding::Mouse(button) => self.mouse.just_pressed(button),
+                InputBinding::Gamepad(button) => {
+                    self.gamepad().is_some_and(|g| g.just_pressed(button))
+                }
+            })
+    }
+
+    fn gamepad(&self) -> Option<&Gamepad> {
+        match *self.active_device {
+            ActiveInputDevice::Gamepad(entity) => self.gamepads.get(entity).ok(),
+            _ => None,
+        }
+    }
+
+    /// Whether any of the four move actions is held.
+    pub fn moving(&self) -> bool {
+        MOVE_ACTIONS.iter().any(|(action, _)| self.pressed(*action))
+    }
+
+    /// Direction of the held move actions, normalized; forward is `-y` like the on-screen
+    /// joystick.
+    pub fn move_direction(&self) -> Vec2 {
+        MOVE_ACTIONS
+            .iter()
+            .filter(|(action, _)| self.pressed(*action))
+            .map(|(_, offset)| *offset)
+            .sum::<Vec2>()
+            .normalize_or_zero()
+    }
+}
+
+fn save_action_map(map: Res<ActionMap>) {
+    // Inserted at startup from the saved file or the defaults; nothing new to write.
+    if map.is_added() {
+        return;
+    }
+    if let Err(e) = storage::save(&map) {
+        warn!("Could not save input bindings: {e}");
+    }
+}
+
+#[derive(Debug)]
+pub enum ActionMapStorageError {
+    Io(std::io::Error),
+    Ron(ron::Error),
+    /// No settings directory (native) or no `localStorage` (wasm).
+    Unavailable,
+}
+
+impl fmt::Display for ActionMapStorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "could not write settings file: {e}"),
+            Self::Ron(e) => write!(f, "could not serialize bindings: {e}"),
+            Self::Unavailable => write!(f, "no settings storage on this platform"),
+        }
+    }
+}
+
+impl std::error::Error for ActionMapStorageError {}
+
+impl From<std::io::Error> for ActionMapStorageError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<ron::Error> for ActionMapStorageError {
+    fn from(e: ron::Error) -> Self {
+        Self::Ron(e)
+    }
+}
+
+mod storage {
+    use bevy::log::warn;
+    use ron::ser::PrettyConfig;
+
+    use super::{ActionMap, ActionMapStorageError};
+
+    /// Saved map with defaults for actions it does not mention; `None` if there is no saved
+    /// map or it cannot be parsed.
+    pub fn load() -> Option<ActionMap> {
+        let text = read()?;
+        match ron::from_str::<ActionMap>(&text) {
+            Ok(map) => Some(map.with_defaults()),
+            Err(e) => {
+                warn!("Ignoring unreadable input bindings: {e}");
+                None
+            }
+        }
+    }
+
+    pub fn save(map: &ActionMap) -> Result<(), ActionMapStorageError> {
+        let text = ron::ser::to_string_pretty(map, PrettyConfig::default())?;
+        write(&text)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn settings_path() -> Option<std::path::PathBuf> {
+        use std::env::var_os;
+        use std::path::PathBuf;
+
+        let base = var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| var_os("APPDATA").map(PathBuf::from))
+            .or_else(|| var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(base.join("crab_feast").join("input.ron"))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn read() -> Option<String> {
+        std::fs::read_to_string(settings_path()?).ok()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn write(text: &str) -> Result<(), ActionMapStorageError> {
+        let path = settings_path().ok_or(ActionMapStorageError::Unavailable)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    const STORAGE_KEY: &str = "crab_feast.input";
+
+    #[cfg(target_arch = "wasm32")]
+    fn local_storage() -> Option<web_sys::Storage> {
+        web_sys::window()?.local_storage().ok()?
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn read() -> Option<String> {
+        local_storage()?.get_item(STORAGE_KEY).ok()?
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn write(text: &str) -> Result<(), ActionMapStorageError> {
+        local_storage()
+            .and_then(|storage| storage.set_item(STORAGE_KEY, text).ok())
+            .ok_or(ActionMapStorageError::Unavailable)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_have_no_conflicts() {
+        assert!(ActionMap::default().conflicts().is_empty());
+    }
+
+    #[test]
+    fn rebind_replaces_only_the_same_device() {
+        let mut map = ActionMap::default();
+        map.rebind(Action::Jump, InputBinding::Key(KeyCode::KeyJ))
+            .unwrap();
+        assert_eq!(
+            map.bindings(Action::Jump),
+            &[
+                InputBinding::Gamepad(GamepadButton::South),
+                InputBinding::Key(KeyCode::KeyJ),
+            ]
+        );
+        assert_eq!(map.action_for(InputBinding::Key(KeyCode::Space)), None);
+    }
+
+    #[test]
+    fn binding_a_used_input_is_a_conflict() {
+        let mut map = ActionMap::default();
+        let err = map
+            .bind(Action::Jump, InputBinding::Key(KeyCode::KeyW))
+            .unwrap_err();
+        assert_eq!(
+            err,
+            BindingConflict::Button {
+                binding: InputBinding::Key(KeyCode::KeyW),
+                existing: Action::MoveForward,
+                requested: Action::Jump,
+            }
+        );
+        assert_eq!(map, ActionMap::default());
+        // Rebinding an action to its own input is fine.
+        map.rebind(Action::Jump, InputBinding::Key(KeyCode::Space))
+            .unwrap();
+    }
+
+    #[test]
+    fn swapping_sticks_needs_the_other_axis_unbound_first() {
+        let mut map = ActionMap::default();
+        assert!(map
+            .rebind_axis(AxisAction::Move, AxisBinding::RightStick)
+            .is_err());
+        map.unbind_axis(AxisAction::Look, AxisBinding::RightStick);
+        map.rebind_axis(AxisAction::Move, AxisBinding::RightStick)
+            .unwrap();
+        map.rebind_axis(AxisAction::Look, AxisBinding::LeftStick)
+            .unwrap();
+        assert!(map.conflicts().is_empty());
+        assert_eq!(
+            map.axis_bindings(AxisAction::Look),
+            &[AxisBinding::MouseMotion, AxisBinding::LeftStick]
+        );
+    }
+
+    #[test]
+    fn conflicts_are_found_in_loaded_maps() {
+        let mut map = ActionMap::default();
+        map.buttons
+            .get_mut(&Action::Sprint)
+            .unwrap()
+            .push(InputBinding::Key(KeyCode::Space));
+        assert_eq!(map.conflicts().len(), 1);
+    }
+
+    #[test]
+    fn round_trips_through_ron_and_fills_missing_actions() {
+        let mut map = ActionMap::default();
+        map.rebind(Action::Sprint, InputBinding::Mouse(MouseButton::Right))
+            .unwrap();
+        let text = ron::ser::to_string_pretty(&map, Default::default()).unwrap();
+        let loaded: ActionMap = ron::from_str(&text).unwrap();
+        assert_eq!(loaded, map);
+
+        let partial: ActionMap = ron::from_str("(buttons: { Jump: [Key(KeyK)] })").unwrap();
+        let partial = partial.with_defaults();
+        assert_eq!(
+            partial.bindings(Action::Jump),
+            &[InputBinding::Key(KeyCode::KeyK)]
+        );
+        assert_eq!(
+            partial.bindings(Action::Pause),
+            ActionMap::default().bindings(Action::Pause)
+        );
+        assert_eq!(
+            partial.axis_bindings(AxisAction::Move),
+            &[AxisBinding::LeftStick]
+        );
+    }
+}
@@ -0,0 +1,246 @@
+//! Blend spaces: per-frame clip weights from a 1D (speed) or 2D (body-space velocity) point,
+//! plus the playback-rate / phase helpers that keep blended feet from sliding.
+//!
+//! Samples are plain data, so adding a clip (e.g. jogging) is one more [`BlendSample`].
+
+use bevy::math::Vec2;
+
+/// A clip placed in a blend space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlendSample<K> {
+    pub key: K,
+    /// 1D spaces only read `x` (speed); 2D spaces use body-space velocity (`x` right, `y` forward).
+    pub position: Vec2,
+    /// Ground speed (m/s) the clip was authored at; `0.0` for in-place clips such as idle.
+    pub ground_speed: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendSpaceKind {
+    Speed1d,
+    Direction2d,
+}
+
+#[derive(Clone, Debug)]
+pub struct BlendSpace<K> {
+    kind: BlendSpaceKind,
+    samples: Vec<BlendSample<K>>,
+}
+
+/// Rates outside this range look worse than a little foot sliding.
+const MIN_PLAYBACK_RATE: f32 = 0.5;
+const MAX_PLAYBACK_RATE: f32 = 2.0;
+/// Below this blended reference speed the pose is essentially in place; play at authored rate.
+const IN_PLACE_SPEED: f32 = 0.05;
+
+impl<K: Copy> BlendSpace<K> {
+    /// Samples are sorted by `position.x`.
+    pub fn speed_1d(mut samples: Vec<BlendSample<K>>) -> Self {
+        samples.sort_by(|a, b| a.position.x.total_cmp(&b.position.x));
+        Self {
+            kind: BlendSpaceKind::Speed1d,
+            samples,
+        }
+    }
+
+    pub fn direction_2d(samples: Vec<BlendSample<K>>) -> Self {
+        Self {
+            kind: BlendSpaceKind::Direction2d,
+            samples,
+        }
+    }
+
+    pub fn samples(&self) -> &[BlendSample<K>] {
+        &self.samples
+    }
+
+    /// Weights aligned with [`Self::samples`]; they sum to 1 unless the space is empty.
+    pub fn weights(&self, point: Vec2) -> Vec<f32> {
+        match self.kind {
+            BlendSpaceKind::Speed1d => self.weights_1d(point.x),
+            BlendSpaceKind::Direction2d => self.weights_2d(point),
+        }
+    }
+
+    /// Linear interpolation between the two samples bracketing `x`, clamped at the ends.
+    fn weights_1d(&self, x: f32) -> Vec<f32> {
+        let mut weights = vec![0.0; self.samples.len()];
+        let Some(last) = self.samples.len().checked_sub(1) else {
+            return weights;
+        };
+        if x <= self.samples[0].position.x {
+            weights[0] = 1.0;
+            return weights;
+        }
+        if x >= self.samples[last].position.x {
+            weights[last] = 1.0;
+            return weights;
+        }
+        for i in 0..last {
+            let a = self.samples[i].position.x;
+            let b = self.samples[i + 1].position.x;
+            if x >= a && x <= b {
+                let t = if b - a > f32::EPSILON {
+                    (x - a) / (b - a)
+                } else {
+                    0.0
+                };
+                weights[i] = 1.0 - t;
+                weights[i + 1] = t;
+                break;
+            }
+        }
+        weights
+    }
+
+    /// Cartesian gradient-band interpolation: every sample's influence falls off linearly
+    /// toward each other sample, so arbitrary layouts blend without a triangulation.
+    fn weights_2d(&self, p: Vec2) -> Vec<f32> {
+        let n = self.samples.len();
+        let mut weights = vec![0.0; n];
+        if n == 1 {
+            weights[0] = 1.0;
+            return weights;
+        }
+        let mut total = 0.0;
+        for (i, weight) in weights.iter_mut().enumerate() {
+            let pi = self.samples[i].position;
+            let mut w: f32 = 1.0;
+            for (j, other) in self.samples.iter().enumerate() {
+                if i == j {
+                    continue;
+                }
+                let pij = other.position - pi;
+                let len_sq = pij.length_squared();
+                if len_sq < f32::EPSILON {
+                    continue;
+                }
+                w = w.min((1.0 - (p - pi).dot(pij) / len_sq).clamp(0.0, 1.0));
+            }
+            *weight = w;
+            total += w;
+        }
+        if total > f32::EPSILON {
+            weights.iter_mut().for_each(|w| *w /= total);
+        } else if let Some(nearest) = (0..n).min_by(|&a, &b| {
+            let da = self.samples[a].position.distance_squared(p);
+            let db = self.samples[b].position.distance_squared(p);
+            da.total_cmp(&db)
+        }) {
+            weights[nearest] = 1.0;
+        }
+        weights
+    }
+
+    /// Playback rate that makes the blended clip's authored speed match `ground_speed`.
+    pub fn playback_rate(&self, weights: &[f32], ground_speed: f32) -> f32 {
+        let reference: f32 = self
+            .samples
+            .iter()
+            .zip(weights)
+            .map(|(s, w)| s.ground_speed * w)
+            .sum();
+        if reference < IN_PLACE_SPEED {
+            return 1.0;
+        }
+        (ground_speed / reference).clamp(MIN_PLAYBACK_RATE, MAX_PLAYBACK_RATE)
+    }
+}
+
+/// Advances a normalized gait phase in \[0, 1) shared by all blended clips; each clip then
+/// seeks to `phase * its_duration` so footfalls line up across clips of different lengths.
+pub fn advance_phase(phase: f32, dt: f32, rate: f32, blended_duration: f32) -> f32 {
+    if blended_duration <= f32::EPSILON {
+        return phase;
+    }
+    (phase + dt * rate / blended_duration).rem_euclid(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(key: u8, x: f32, y: f32, ground_speed: f32) -> BlendSample<u8> {
+        BlendSample {
+            key,
+            position: Vec2::new(x, y),
+            ground_speed,
+        }
+    }
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{a} != {b}");
+    }
+
+    #[test]
+    fn speed_1d_interpolates_between_neighbours() {
+        let space = BlendSpace::speed_1d(vec![
+            sample(2, 6.0, 0.0, 6.0),
+            sample(0, 0.0, 0.0, 0.0),
+            sample(1, 1.5, 0.0, 1.5),
+        ]);
+        assert_eq!(space.samples()[0].key, 0);
+        let w = space.weights(Vec2::new(3.75, 0.0));
+        assert_close(w[0], 0.0);
+        assert_close(w[1], 0.5);
+        assert_close(w[2], 0.5);
+    }
+
+    #[test]
+    fn speed_1d_clamps_outside_range() {
+        let space = BlendSpace::speed_1d(vec![sample(0, 0.0, 0.0, 0.0), sample(1, 2.0, 0.0, 2.0)]);
+        assert_eq!(space.weights(Vec2::new(-1.0, 0.0)), vec![1.0, 0.0]);
+        assert_eq!(space.weights(Vec2::new(9.0, 0.0)), vec![0.0, 1.0]);
+    }
+
+    #[test]
+    fn direction_2d_is_exact_on_samples() {
+        let space = BlendSpace::direction_2d(vec![
+            sample(0, 0.0, 0.0, 0.0),
+            sample(1, 0.0, 1.5, 1.5),
+            sample(2, -1.5, 0.0, 1.5),
+            sample(3, 1.5, 0.0, 1.5),
+        ]);
+        for (i, s) in space.samples().iter().enumerate() {
+            let w = space.weights(s.position);
+            for (j, wj) in w.iter().enumerate() {
+                assert_close(*wj, if i == j { 1.0 } else { 0.0 });
+            }
+        }
+    }
+
+    #[test]
+    fn direction_2d_weights_sum_to_one_and_follow_axis() {
+        let space = BlendSpace::direction_2d(vec![
+            sample(0, 0.0, 0.0, 0.0),
+            sample(1, 0.0, 1.5, 1.5),
+            sample(2, 0.0, 3.5, 3.5),
+            sample(3, 0.0, 6.0, 6.0),
+            sample(4, -1.5, 0.0, 1.5),
+            sample(5, 1.5, 0.0, 1.5),
+        ]);
+        // Straight ahead between walk and jog: only those two contribute, linearly.
+        let w = space.weights(Vec2::new(0.0, 3.0));
+        assert_close(w.iter().sum(), 1.0);
+        assert_close(w[1], 0.25);
+        assert_close(w[2], 0.75);
+
+        let w = space.weights(Vec2::new(0.7, 0.9));
+        assert_close(w.iter().sum(), 1.0);
+        assert!(w[5] > w[4]);
+    }
+
+    #[test]
+    fn playback_rate_matches_ground_speed() {
+        let space = BlendSpace::speed_1d(vec![sample(0, 0.0, 0.0, 0.0), sample(1, 1.5, 0.0, 1.5)]);
+        assert_close(space.playback_rate(&[0.0, 1.0], 1.8), 1.2);
+        assert_close(space.playback_rate(&[0.0, 1.0], 100.0), MAX_PLAYBACK_RATE);
+        assert_close(space.playback_rate(&[1.0, 0.0], 0.0), 1.0);
+    }
+
+    #[test]
+    fn phase_wraps() {
+        assert_close(advance_phase(0.9, 0.5, 1.0, 2.5), 0.1);
+        assert_close(advance_phase(0.3, 1.0, 1.0, 0.0), 0.3);
+    }
+}
@@ -1,4 +1,13 @@
+use std::f32::consts::FRAC_PI_2;
+
 use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::actions::{Action, ActionInput};
+use crate::character::{Character, CharacterManifest};
+use crate::input::{LocalControl, LocallyControlled, LookController, MovementInput, ZoomInput};
+use crate::utils::find_descendant_by_name;
+use crate::GameState;
 
 /// 集中管理所有相机的插件
 pub struct CameraPlugin;
@@ -6,10 +15,151 @@ pub struct CameraPlugin;
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, setup_ui_camera)
-            .add_systems(Startup, setup_game_camera);
+            .add_systems(Startup, setup_game_camera)
+            .add_systems(
+                Update,
+                (switch_camera_rig, fly_free_camera, attach_first_person_head)
+                    .run_if(in_state(GameState::Game)),
+            )
+            .add_systems(PostUpdate, fade_camera_occluders)
+            .add_observer(apply_zoom_input);
+    }
+}
+
+/// 游戏相机的工作模式，运行时可切换（默认 V 轮换跟随模式，C 切换肩侧，F8 自由飞行调试，
+/// 见 [`Action`]）。
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CameraRig {
+    /// 绕玩家旋转并始终注视玩家。
+    #[default]
+    Orbit,
+    /// 越肩视角：相机偏向一侧，朝视线方向看；`right` 为 `true` 时在右肩。
+    Shoulder { right: bool },
+    /// 第一人称：相机跟随角色头骨（见 [`FirstPersonHead`]）。
+    FirstPerson,
+    /// 脱离玩家的自由飞行调试相机；此时移动输入驱动相机而不是角色。
+    FreeFly,
+}
+
+/// 每种模式的视角限制。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LookProfile {
+    pub min_pitch: f32,
+    pub max_pitch: f32,
+    /// 乘在 [`LookSettings`](crate::look::LookSettings) 各输入源灵敏度上的倍率。
+    pub sensitivity: f32,
+}
+
+impl CameraRig {
+    pub fn look_profile(self) -> LookProfile {
+        let (pitch, sensitivity) = match self {
+            Self::Orbit => (FRAC_PI_2 - 0.1, 1.0),
+            Self::Shoulder { .. } => (1.0, 0.8),
+            Self::FirstPerson => (1.4, 0.6),
+            Self::FreeFly => (FRAC_PI_2 - 0.01, 0.8),
+        };
+        LookProfile {
+            min_pitch: -pitch,
+            max_pitch: pitch,
+            sensitivity,
+        }
+    }
+
+    /// 自由飞行时相机与角色脱钩，角色不响应移动输入。
+    pub fn is_detached(self) -> bool {
+        matches!(self, Self::FreeFly)
+    }
+
+    /// V 键轮换的下一个跟随模式。
+    fn next_follow(self) -> Self {
+        match self {
+            Self::Orbit => Self::Shoulder { right: true },
+            Self::Shoulder { .. } => Self::FirstPerson,
+            Self::FirstPerson | Self::FreeFly => Self::Orbit,
+        }
     }
 }
 
+/// 角色头骨实体，由 [`CharacterManifest::head_bone`] 查找后挂在角色刚体上。
+#[derive(Component, Debug, Clone, Copy)]
+pub struct FirstPersonHead(pub Entity);
+
+/// 模式切换时的过渡：从切换瞬间的相机位姿平滑插值到新模式的位姿。
+#[derive(Debug, Clone, Copy)]
+pub struct RigTransition {
+    pub from: Transform,
+    pub elapsed: f32,
+}
+
+/// 跟随相机参数（挂在游戏相机上）；[`sync_game_camera_rig`] 按此与 [`CameraRig`] 放置相机。
+#[derive(Component, Debug, Clone)]
+pub struct ThirdPersonCamera {
+    /// 相机相对玩家的轨道偏移（绕玩家按 yaw/pitch 旋转）。
+    pub offset: Vec3,
+    /// 越肩模式相对注视点的偏移（右肩；左肩时 `x` 取反）。
+    pub shoulder_offset: Vec3,
+    /// 第一人称模式相对头骨的偏移（视线坐标系）。
+    pub first_person_offset: Vec3,
+    /// 注视点相对玩家的偏移，也是碰撞检测的起点。
+    pub look_at_offset: Vec3,
+    /// 跟随距离倍率（滚轮 / 捏合缩放），限制在 `min_zoom..=max_zoom`。
+    pub zoom: f32,
+    pub min_zoom: f32,
+    pub max_zoom: f32,
+    /// 自由飞行速度，m/s。
+    pub free_fly_speed: f32,
+    /// 碰撞检测球半径：相机与墙面至少保持这个距离。
+    pub collision_radius: f32,
+    /// 障碍消失后相机退回期望距离的速度，m/s（被挡时立即拉近）。
+    pub ease_out_speed: f32,
+    /// 为 `true` 时带网格的碰撞体不拉近相机，改为半透明淡出；无网格的碰撞体（地面、墙）仍会拉近。
+    pub fade_occluders: bool,
+    /// 淡出后的不透明度。
+    pub occluder_alpha: f32,
+    /// 运行时状态：当前注视点到相机的距离。
+    pub current_distance: Option<f32>,
+    /// 运行时状态：本帧注视点（世界坐标）。
+    pub focus: Vec3,
+    /// 运行时状态：进行中的模式切换过渡。
+    pub transition: Option<RigTransition>,
+}
+
+impl Default for ThirdPersonCamera {
+    fn default() -> Self {
+        Self {
+            offset: Vec3::new(0.0, 1.3, 5.0),
+            shoulder_offset: Vec3::new(0.6, 0.3, 2.2),
+            first_person_offset: Vec3::new(0.0, 0.05, -0.12),
+            look_at_offset: Vec3::new(0.0, 0.6, 0.0),
+            zoom: 1.0,
+            min_zoom: 0.4,
+            max_zoom: 2.0,
+            free_fly_speed: 8.0,
+            collision_radius: 0.2,
+            ease_out_speed: 4.0,
+            fade_occluders: false,
+            occluder_alpha: 0.3,
+            current_distance: None,
+            focus: Vec3::ZERO,
+            transition: None,
+        }
+    }
+}
+
+/// 被淡出的遮挡物：换成一份可透明的材质副本，恢复不透明后换回原材质。
+#[derive(Component)]
+struct FadedOccluder {
+    original: Handle<StandardMaterial>,
+    faded: Handle<StandardMaterial>,
+    alpha: f32,
+    occluding: bool,
+}
+
+/// 遮挡物淡入/淡出所需时间（秒）。
+const OCCLUDER_FADE_SECS: f32 = 0.25;
+/// 模式切换过渡时长（秒）。
+const RIG_TRANSITION_SECS: f32 = 0.35;
+
 /// UI相机资源标记
 #[derive(Resource)]
 pub struct UiCamera(pub Entity);
@@ -43,3 +193,351 @@ fn setup_game_camera(mut commands: Commands) {
     
     commands.insert_resource(GameCamera(game_camera));
 }
+
+fn look_rotation(look: &LookController) -> Quat {
+    Quat::from_euler(
+        EulerRot::ZYX,
+        0.0,
+        look.accumulated_yaw,
+        look.accumulated_pitch,
+    )
+}
+
+fn switch_camera_rig(
+    actions: ActionInput,
+    game_camera: Res<GameCamera>,
+    mut camera: Query<(
+        &Transform,
+        &mut CameraRig,
+        &mut LookController,
+        &mut ThirdPersonCamera,
+    )>,
+) {
+    let Ok((cam_tf, mut rig, mut look, mut follow)) = camera.get_mut(game_camera.0) else {
+        return;
+    };
+    let next = if actions.just_pressed(Action::ToggleFreeFly) {
+        if rig.is_detached() {
+            CameraRig::Orbit
+        } else {
+            CameraRig::FreeFly
+        }
+    } else if actions.just_pressed(Action::NextCameraRig) {
+        rig.next_follow()
+    } else if actions.just_pressed(Action::SwapShoulder) {
+        match *rig {
+            CameraRig::Shoulder { right } => CameraRig::Shoulder { right: !right },
+            other => other,
+        }
+    } else {
+        return;
+    };
+    if next == *rig {
+        return;
+    }
+    *rig = next;
+    let profile = next.look_profile();
+    look.accumulated_pitch = look
+        .accumulated_pitch
+        .clamp(profile.min_pitch, profile.max_pitch);
+    look.target_pitch = look
+        .target_pitch
+        .clamp(profile.min_pitch, profile.max_pitch);
+    // 自由飞行从当前位姿直接开始，无需过渡。
+    follow.transition = (!next.is_detached()).then_some(RigTransition {
+        from: *cam_tf,
+        elapsed: 0.0,
+    });
+}
+
+fn apply_zoom_input(
+    trigger: On<ZoomInput>,
+    game_camera: Res<GameCamera>,
+    mut camera: Query<&mut ThirdPersonCamera>,
+) {
+    let Ok(mut follow) = camera.get_mut(game_camera.0) else {
+        return;
+    };
+    follow.zoom =
+        (follow.zoom * (-trigger.event().0).exp()).clamp(follow.min_zoom, follow.max_zoom);
+}
+
+/// 自由飞行：移动输入在视线平面内平移，[`Action::FlyUp`] / [`Action::FlyDown`]（默认 E / Q）
+/// 上升 / 下降。
+fn fly_free_camera(
+    time: Res<Time>,
+    game_camera: Res<GameCamera>,
+    control: LocalControl,
+    actions: ActionInput,
+    mut camera: Query<(
+        &mut Transform,
+        &LookController,
+        &CameraRig,
+        &ThirdPersonCamera,
+    )>,
+) {
+    let Ok((mut cam_tf, look, rig, follow)) = camera.get_mut(game_camera.0) else {
+        return;
+    };
+    if !rig.is_detached() {
+        return;
+    }
+    let rotation = look_rotation(look);
+    let mut velocity = match control.movement() {
+        MovementInput::Activated { direction, force } => {
+            (rotation * -Vec3::Z * -direction.y + rotation * Vec3::X * direction.x) * force
+        }
+        MovementInput::Idle => Vec3::ZERO,
+    };
+    if actions.pressed(Action::FlyUp) {
+        velocity += Vec3::Y;
+    }
+    if actions.pressed(Action::FlyDown) {
+        velocity -= Vec3::Y;
+    }
+    cam_tf.translation += velocity * follow.free_fly_speed * time.delta_secs();
+}
+
+/// 按角色清单里的 `head_bone` 找到头骨，供第一人称模式使用。
+fn attach_first_person_head(
+    mut commands: Commands,
+    manifests: Res<Assets<CharacterManifest>>,
+    characters: Query<(Entity, &Character), Without<FirstPersonHead>>,
+    children: Query<&Children>,
+    names: Query<&Name>,
+) {
+    for (body, character) in &characters {
+        let Some(head_bone) = manifests
+            .get(&character.0)
+            .and_then(|m| m.head_bone.as_deref())
+        else {
+            continue;
+        };
+        if let Some(head) = find_descendant_by_name(body, &children, &names, head_bone) {
+            commands.entity(body).insert(FirstPersonHead(head));
+        }
+    }
+}
+
+/// 从注视点向期望位置做球形 shape-cast，返回相机可到达的距离。
+fn follow_distance(
+    rapier_context: &ReadRapierContext,
+    player: Entity,
+    fadeable: &Query<(), With<MeshMaterial3d<StandardMaterial>>>,
+    follow: &ThirdPersonCamera,
+    look_at: Vec3,
+    dir: Dir3,
+    max_distance: f32,
+) -> f32 {
+    let fade_occluders = follow.fade_occluders;
+    let skip_fadeable = |e: Entity| !(fade_occluders && fadeable.contains(e));
+    rapier_context
+        .single()
+        .ok()
+        .and_then(|ctx| {
+            ctx.cast_shape(
+                look_at,
+                Quat::IDENTITY,
+                *dir,
+                &Collider::ball(follow.collision_radius),
+                ShapeCastOptions::with_max_time_of_impact(max_distance),
+                QueryFilter::default()
+                    .exclude_rigid_body(player)
+                    .exclude_sensors()
+                    .predicate(&skip_fadeable),
+            )
+        })
+        .map_or(max_distance, |(_, hit)| hit.time_of_impact)
+}
+
+/// 按 [`CameraRig`] 放置游戏相机。跟随模式从注视点向期望位置做球形 shape-cast，命中则立即
+/// 拉近，无遮挡时以 [`ThirdPersonCamera::ease_out_speed`] 退回；切换模式时在
+/// [`RIG_TRANSITION_SECS`] 内平滑过渡。
+pub(crate) fn sync_game_camera_rig(
+    time: Res<Time>,
+    game_camera: Res<GameCamera>,
+    rapier_context: ReadRapierContext,
+    player: Query<(Entity, &GlobalTransform, Option<&FirstPersonHead>), With<LocallyControlled>>,
+    bones: Query<&GlobalTransform>,
+    fadeable: Query<(), With<MeshMaterial3d<StandardMaterial>>>,
+    mut camera: Query<
+        (
+            &mut Transform,
+            &LookController,
+            &CameraRig,
+            &mut ThirdPersonCamera,
+        ),
+        With<Camera3d>,
+    >,
+) {
+    let Ok((player_entity, player_gt, head)) = player.single() else {
+        return;
+    };
+    let Ok((mut cam_tf, look, rig, mut follow)) = camera.get_mut(game_camera.0) else {
+        return;
+    };
+    let p = player_gt.translation();
+    let rotation = look_rotation(look);
+    let look_at = p + follow.look_at_offset;
+
+    let target = match *rig {
+        CameraRig::Orbit | CameraRig::Shoulder { .. } => {
+            let desired = match *rig {
+                CameraRig::Shoulder { right } => {
+                    let side = if right { 1.0 } else { -1.0 };
+                    let offset = follow.shoulder_offset * Vec3::new(side, 1.0, 1.0);
+                    look_at + rotation * offset * follow.zoom
+                }
+                _ => look_at + (p + rotation * follow.offset - look_at) * follow.zoom,
+            };
+            let Ok(dir) = Dir3::new(desired - look_at) else {
+                cam_tf.translation = desired;
+                return;
+            };
+            let max_distance = (desired - look_at).length();
+            let allowed = follow_distance(
+                &rapier_context,
+                player_entity,
+                &fadeable,
+                &follow,
+                look_at,
+                dir,
+                max_distance,
+            );
+            let eased = follow
+                .current_distance
+                .map_or(allowed, |d| d + follow.ease_out_speed * time.delta_secs());
+            let distance = eased.min(allowed);
+            follow.current_distance = Some(distance);
+            follow.focus = look_at;
+
+            let mut target = Transform::from_translation(look_at + dir * distance);
+            if *rig == CameraRig::Orbit {
+                target.look_at(look_at, Vec3::Y);
+            } else {
+                target.rotation = rotation;
+            }
+            target
+        }
+        CameraRig::FirstPerson => {
+            let eye = head
+                .and_then(|h| bones.get(h.0).ok())
+                .map_or(look_at, |gt| gt.translation());
+            follow.current_distance = None;
+            Transform::from_translation(eye + rotation * follow.first_person_offset)
+                .with_rotation(rotation)
+        }
+        CameraRig::FreeFly => {
+            follow.current_distance = None;
+            Transform {
+                rotation,
+                ..*cam_tf
+            }
+        }
+    };
+    if !matches!(*rig, CameraRig::Orbit | CameraRig::Shoulder { .. }) {
+        // 非跟随模式没有注视点，也不淡出遮挡物。
+        follow.focus = target.translation;
+    }
+
+    *cam_tf = match follow.transition.as_mut() {
+        Some(transition) => {
+            transition.elapsed += time.delta_secs();
+            let t = (transition.elapsed / RIG_TRANSITION_SECS).min(1.0);
+            let t = t * t * (3.0 - 2.0 * t);
+            let from = transition.from;
+            if t >= 1.0 {
+                follow.transition = None;
+            }
+            Transform {
+                translation: from.translation.lerp(target.translation, t),
+                rotation: from.rotation.slerp(target.rotation, t),
+                ..target
+            }
+        }
+        None => target,
+    };
+}
+
+/// 开启 [`ThirdPersonCamera::fade_occluders`] 时，把注视点与相机之间带网格的碰撞体淡出，
+/// 不再遮挡后淡回并换回原材质。
+fn fade_camera_occluders(
+    mut commands: Commands,
+    time: Res<Time>,
+    rapier_context: ReadRapierContext,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    cameras: Query<(&Transform, &ThirdPersonCamera)>,
+    player: Query<Entity, With<LocallyControlled>>,
+    mut meshes: Query<(
+        Entity,
+        &mut MeshMaterial3d<StandardMaterial>,
+        Option<&mut FadedOccluder>,
+    )>,
+) {
+    for (_, _, faded) in &mut meshes {
+        if let Some(mut faded) = faded {
+            faded.occluding = false;
+        }
+    }
+
+    if let Ok(ctx) = rapier_context.single() {
+        for (cam_tf, follow) in &cameras {
+            if !follow.fade_occluders {
+                continue;
+            }
+            let Ok(dir) = Dir3::new(cam_tf.translation - follow.focus) else {
+                continue;
+            };
+            let distance = (cam_tf.translation - follow.focus).length();
+            let mut filter = QueryFilter::default().exclude_sensors();
+            if let Ok(player) = player.single() {
+                filter = filter.exclude_rigid_body(player);
+            }
+            let mut hits = Vec::new();
+            ctx.intersections_with_ray(follow.focus, *dir, distance, true, filter, |e, _| {
+                hits.push(e);
+                true
+            });
+            for e in hits {
+                let Ok((_, mut material, faded)) = meshes.get_mut(e) else {
+                    continue;
+                };
+                if let Some(mut faded) = faded {
+                    faded.occluding = true;
+                    continue;
+                }
+                let Some(mut copy) = materials.get(&material.0).cloned() else {
+                    continue;
+                };
+                copy.alpha_mode = AlphaMode::Blend;
+                let faded = materials.add(copy);
+                commands.entity(e).insert(FadedOccluder {
+                    original: material.0.clone(),
+                    faded: faded.clone(),
+                    alpha: 1.0,
+                    occluding: true,
+                });
+                material.0 = faded;
+            }
+        }
+    }
+
+    let occluded_alpha = cameras.iter().next().map_or(1.0, |(_, c)| c.occluder_alpha);
+    let step = time.delta_secs() / OCCLUDER_FADE_SECS;
+    for (e, mut material, faded) in &mut meshes {
+        let Some(mut faded) = faded else {
+            continue;
+        };
+        faded.alpha = if faded.occluding {
+            (faded.alpha - step).max(occluded_alpha)
+        } else {
+            (faded.alpha + step).min(1.0)
+        };
+        if !faded.occluding && faded.alpha >= 1.0 {
+            material.0 = faded.original.clone();
+            commands.entity(e).remove::<FadedOccluder>();
+        } else if let Some(m) = materials.get_mut(&faded.faded) {
+            m.base_color.set_alpha(faded.alpha);
+        }
+    }
+}
@@ -0,0 +1,388 @@
+//! Data-driven characters: a RON manifest (`*.character.ron`) names the rig, hips bone,
+//! physics capsule and body kind, model offset, root-motion mode and animation clip slots,
+//! so adding a character or clip needs no Rust changes.
+//!
+//! Slots the locomotion code looks for: `jump`, `turn_left`, `turn_right` (all optional);
+//! ground locomotion comes from the manifest's `locomotion` blend-space rows (2D, by
+//! body-space velocity) and optional `locomotion_1d` rows (by speed). With both, the speed
+//! space plays while walking or running ahead and the 2D one while strafing.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use bevy::asset::{io::Reader, AssetLoader, LoadContext};
+use bevy::ecs::system::EntityCommands;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use crab_feast_library::utils::SpatialIndexed;
+use serde::Deserialize;
+
+use crate::blend_space::{BlendSample, BlendSpace};
+use crate::character_body::{insert_character_body, CharacterBodyMode};
+use crate::input::{
+    CharacterBodyYaw, ControlIntent, GroundState, JumpController, MovementController,
+    PlayerCharacterModelRoot,
+};
+use crate::locomotion::{ground_blend_point, LocomotionAnim};
+use crate::root_motion::RootMotionMode;
+
+pub struct CharacterPlugin;
+
+impl Plugin for CharacterPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<CharacterManifest>()
+            .init_asset_loader::<CharacterManifestLoader>();
+    }
+}
+
+/// Loaded form of a `*.character.ron` file; every path has been turned into a handle.
+#[derive(Asset, TypePath, Debug)]
+pub struct CharacterManifest {
+    #[dependency]
+    pub rig: Handle<Scene>,
+    pub hips_bone: String,
+    /// Bone the first-person camera rides on.
+    pub head_bone: Option<String>,
+    pub capsule: CapsuleDef,
+    pub body: CharacterBodyMode,
+    /// Offset of the skinned model under the capsule (feet at the capsule bottom).
+    pub model_offset: Vec3,
+    pub root_motion: RootMotionMode,
+    #[dependency]
+    pub clips: HashMap<String, Handle<AnimationClip>>,
+    pub locomotion: Vec<LocomotionClipDef>,
+    pub locomotion_1d: Vec<SpeedClipDef>,
+    /// Clip slots bound to the number-key debug hotkeys, in order.
+    pub debug_clips: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct CapsuleDef {
+    pub half_height: f32,
+    pub radius: f32,
+}
+
+/// One blend-space row: a clip slot and the body-space velocity (`x` right, `y` forward,
+/// m/s) it was authored at.
+#[derive(Deserialize, Debug, Clone)]
+pub struct LocomotionClipDef {
+    pub clip: String,
+    pub velocity: (f32, f32),
+}
+
+/// One speed blend-space row: a clip slot and the forward speed (m/s) it was authored at.
+#[derive(Deserialize, Debug, Clone)]
+pub struct SpeedClipDef {
+    pub clip: String,
+    pub speed: f32,
+}
+
+#[derive(Deserialize)]
+struct CharacterManifestFile {
+    rig: String,
+    hips_bone: String,
+    #[serde(default)]
+    head_bone: Option<String>,
+    capsule: CapsuleDef,
+    #[serde(default)]
+    body: CharacterBodyMode,
+    model_offset: (f32, f32, f32),
+    #[serde(default)]
+    root_motion: RootMotionMode,
+    clips: HashMap<String, String>,
+    #[serde(default)]
+    locomotion: Vec<LocomotionClipDef>,
+    #[serde(default)]
+    locomotion_1d: Vec<SpeedClipDef>,
+    #[serde(default)]
+    debug_clips: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum CharacterManifestError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+    /// `locomotion`, `locomotion_1d` or `debug_clips` refers to a slot missing from `clips`.
+    UnknownClip(String),
+}
+
+impl fmt::Display for CharacterManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "could not read character manifest: {e}"),
+            Self::Ron(e) => write!(f, "could not parse character manifest: {e}"),
+            Self::UnknownClip(slot) => write!(f, "unknown clip slot `{slot}`"),
+        }
+    }
+}
+
+impl std::error::Error for CharacterManifestError {}
+
+impl From<std::io::Error> for CharacterManifestError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<ron::error::SpannedError> for CharacterManifestError {
+    fn from(e: ron::error::SpannedError) -> Self {
+        Self::Ron(e)
+    }
+}
+
+#[derive(Default, TypePath)]
+pub struct CharacterManifestLoader;
+
+impl AssetLoader for CharacterManifestLoader {
+    type Asset = CharacterManifest;
+    type Settings = ();
+    type Error = CharacterManifestError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<CharacterManifest, CharacterManifestError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let file: CharacterManifestFile = ron::de::from_bytes(&bytes)?;
+
+        let referenced = file
+            .locomotion
+            .iter()
+            .map(|row| &row.clip)
+            .chain(file.locomotion_1d.iter().map(|row| &row.clip))
+            .chain(&file.debug_clips);
+        for slot in referenced {
+            if !file.clips.contains_key(slot) {
+                return Err(CharacterManifestError::UnknownClip(slot.clone()));
+            }
+        }
+
+        let clips = file
+            .clips
+            .into_iter()
+            .map(|(slot, path)| (slot, load_context.load(path)))
+            .collect();
+        let (x, y, z) = file.model_offset;
+        Ok(CharacterManifest {
+            rig: load_context.load(file.rig),
+            hips_bone: file.hips_bone,
+            head_bone: file.head_bone,
+            capsule: file.capsule,
+            body: file.body,
+            model_offset: Vec3::new(x, y, z),
+            root_motion: file.root_motion,
+            clips,
+            locomotion: file.locomotion,
+            locomotion_1d: file.locomotion_1d,
+            debug_clips: file.debug_clips,
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["character.ron"]
+    }
+}
+
+/// Blend-space key: the graph node to weight and the clip whose duration drives phase sync.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlendClip {
+    pub node: AnimationNodeIndex,
+    pub clip: AssetId<AnimationClip>,
+}
+
+/// Animation graph built from a [`CharacterManifest`], stored on the character body.
+#[derive(Component, Clone)]
+pub struct CharacterAnimations {
+    pub graph: Handle<AnimationGraph>,
+    /// Ground clips by body-space velocity.
+    pub locomotion: BlendSpace<BlendClip>,
+    /// Ground clips by speed, from [`CharacterManifest::locomotion_1d`]; `None` when the
+    /// manifest has no such rows.
+    pub speed_locomotion: Option<BlendSpace<BlendClip>>,
+    /// One standalone node per clip slot, used for clips layered over the blend space.
+    pub slots: HashMap<String, AnimationNodeIndex>,
+    /// Nodes for [`CharacterManifest::debug_clips`], in hotkey order.
+    pub debug: Vec<AnimationNodeIndex>,
+}
+
+impl CharacterAnimations {
+    pub fn from_manifest(
+        manifest: &CharacterManifest,
+        graphs: &mut Assets<AnimationGraph>,
+    ) -> Self {
+        let mut graph = AnimationGraph::new();
+
+        // Blend-space clips get their own nodes so the same clip can also play as an override.
+        let samples = manifest
+            .locomotion
+            .iter()
+            .filter_map(|row| {
+                let clip = manifest.clips.get(&row.clip)?;
+                let position = Vec2::new(row.velocity.0, row.velocity.1);
+                Some(BlendSample {
+                    key: BlendClip {
+                        node: graph.add_clip(clip.clone(), 1.0, graph.root),
+                        clip: clip.id(),
+                    },
+                    position,
+                    ground_speed: position.length(),
+                })
+            })
+            .collect();
+        let speed_samples: Vec<_> = manifest
+            .locomotion_1d
+            .iter()
+            .filter_map(|row| {
+                let clip = manifest.clips.get(&row.clip)?;
+                Some(BlendSample {
+                    key: BlendClip {
+                        node: graph.add_clip(clip.clone(), 1.0, graph.root),
+                        clip: clip.id(),
+                    },
+                    position: Vec2::new(row.speed, 0.0),
+                    ground_speed: row.speed,
+                })
+            })
+            .collect();
+
+        let slots: HashMap<String, AnimationNodeIndex> = manifest
+            .clips
+            .iter()
+            .map(|(slot, clip)| (slot.clone(), graph.add_clip(clip.clone(), 1.0, graph.root)))
+            .collect();
+        let debug = manifest
+            .debug_clips
+            .iter()
+            .filter_map(|slot| slots.get(slot).copied())
+            .collect();
+
+        Self {
+            graph: graphs.add(graph),
+            locomotion: BlendSpace::direction_2d(samples),
+            speed_locomotion: (!speed_samples.is_empty())
+                .then(|| BlendSpace::speed_1d(speed_samples)),
+            slots,
+            debug,
+        }
+    }
+
+    /// Blend spaces making up the ground pose, each with the point to sample it at and its
+    /// share of the pose. `strafe` is 0 walking or running ahead and 1 strafing; see
+    /// [`ground_blend_point`].
+    pub fn ground_layers(
+        &self,
+        state: LocomotionAnim,
+        velocity_body: Vec2,
+        strafe: f32,
+    ) -> Vec<(&BlendSpace<BlendClip>, Vec2, f32)> {
+        match &self.speed_locomotion {
+            Some(speed) => {
+                let ahead = ground_blend_point(state, velocity_body, 0.0).y;
+                vec![
+                    (speed, Vec2::new(ahead, 0.0), 1.0 - strafe),
+                    (
+                        &self.locomotion,
+                        ground_blend_point(state, velocity_body, 1.0),
+                        strafe,
+                    ),
+                ]
+            }
+            None => vec![(
+                &self.locomotion,
+                ground_blend_point(state, velocity_body, strafe),
+                1.0,
+            )],
+        }
+    }
+
+    pub fn slot(&self, name: &str) -> Option<AnimationNodeIndex> {
+        self.slots.get(name).copied()
+    }
+
+    /// The clip slot playing on `node`. Node indices depend on the slot order of the
+    /// manifest map, so other processes must be sent the slot name instead.
+    pub fn slot_name(&self, node: AnimationNodeIndex) -> Option<&str> {
+        self.slots
+            .iter()
+            .find(|(_, slot)| **slot == node)
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+/// Tracks the scene's [`AnimationPlayer`] and hips wiring once the rig has spawned.
+#[derive(Component)]
+pub struct CharacterAnimationBinding {
+    pub hips_bone: String,
+    pub anim_player: Option<Entity>,
+    pub hips_wired: bool,
+}
+
+/// The manifest a character body was spawned from.
+#[derive(Component, Clone)]
+pub struct Character(pub Handle<CharacterManifest>);
+
+/// Spawns a capsule body with the manifest's rig under a [`PlayerCharacterModelRoot`] and
+/// returns the body entity, or `None` if the manifest is not loaded yet. The body starts with
+/// an idle [`ControlIntent`]; add `Controlled` (or drive the intent from AI) to move it.
+pub fn spawn_character(
+    commands: &mut Commands,
+    manifests: &Assets<CharacterManifest>,
+    graphs: &mut Assets<AnimationGraph>,
+    manifest_handle: &Handle<CharacterManifest>,
+    transform: Transform,
+) -> Option<Entity> {
+    let manifest = manifests.get(manifest_handle)?;
+    let mut body = commands.spawn(transform);
+    insert_character(&mut body, manifest, manifest_handle, graphs);
+    Some(body.id())
+}
+
+/// [`spawn_character`] for an entity that already exists and keeps its [`Transform`], such
+/// as one a server replicated.
+pub fn insert_character(
+    body: &mut EntityCommands,
+    manifest: &CharacterManifest,
+    manifest_handle: &Handle<CharacterManifest>,
+    graphs: &mut Assets<AnimationGraph>,
+) {
+    let animations = CharacterAnimations::from_manifest(manifest, graphs);
+    body.insert((
+        Character(manifest_handle.clone()),
+        Collider::capsule_y(manifest.capsule.half_height, manifest.capsule.radius),
+        ColliderDebugColor(Hsla::WHITE),
+        Velocity::zero(),
+        MovementController::default(),
+        ControlIntent::default(),
+        JumpController::default(),
+        GroundState::default(),
+        SpatialIndexed::new(Vec2::splat(manifest.capsule.radius)),
+        InheritedVisibility::default(),
+        Visibility::Visible,
+    ));
+    // A replicated body already carries its yaw.
+    body.insert_if_new(CharacterBodyYaw::default());
+    insert_character_body(body, manifest.body);
+    body.insert((
+        animations,
+        manifest.root_motion,
+        CharacterAnimationBinding {
+            hips_bone: manifest.hips_bone.clone(),
+            anim_player: None,
+            hips_wired: false,
+        },
+    ))
+    .with_children(|parent| {
+        parent
+            .spawn((
+                Name::new("PlayerCharacterModelRoot"),
+                PlayerCharacterModelRoot,
+                Transform::from_translation(manifest.model_offset),
+            ))
+            .with_children(|p2| {
+                p2.spawn((SceneRoot(manifest.rig.clone()), Transform::default()));
+            });
+    });
+}
@@ -0,0 +1,151 @@
+//! Physics body behind a character: a Rapier dynamic rigid body, or a kinematic body moved
+//! by Rapier's [`KinematicCharacterController`] (slope limits, step-up, snap-to-ground,
+//! pushing dynamic bodies).
+//!
+//! Both kinds keep [`Velocity`] as the character's velocity so movement, jumping,
+//! root motion and animation read the same component. For kinematic bodies the velocity is
+//! integrated here (gravity included) and replaced afterwards by what the controller
+//! actually achieved, so walls and ceilings stop it like they would a dynamic body.
+
+use std::f32::consts::PI;
+
+use bevy::ecs::system::EntityCommands;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use serde::Deserialize;
+
+use crate::root_motion::{drive_bodies_from_root_motion, remove_root_motion_velocity};
+
+/// Chosen per character (see `body` in the character manifest).
+#[derive(Component, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CharacterBodyMode {
+    /// Rotation-locked dynamic rigid body; physics owns the vertical motion.
+    #[default]
+    Dynamic,
+    /// Position-based kinematic body driven by a [`KinematicCharacterController`].
+    Kinematic,
+}
+
+const MAX_SLOPE_CLIMB_ANGLE: f32 = 45.0 * PI / 180.0;
+const MIN_SLOPE_SLIDE_ANGLE: f32 = 30.0 * PI / 180.0;
+const STEP_HEIGHT: f32 = 0.3;
+const STEP_MIN_WIDTH: f32 = 0.15;
+const SNAP_TO_GROUND: f32 = 0.2;
+/// Mass used for the impulses a kinematic character gives dynamic bodies it walks into.
+const KINEMATIC_PUSH_MASS: f32 = 60.0;
+/// Used when no [`RapierConfiguration`] is found.
+const DEFAULT_GRAVITY: Vec3 = Vec3::new(0.0, -9.81, 0.0);
+
+pub struct CharacterBodyPlugin;
+
+impl Plugin for CharacterBodyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            (
+                // Root motion adds to the velocity, so move after it.
+                move_kinematic_characters
+                    .after(drive_bodies_from_root_motion)
+                    .before(PhysicsSet::SyncBackend),
+                sync_kinematic_velocity
+                    .after(PhysicsSet::Writeback)
+                    .before(remove_root_motion_velocity),
+            ),
+        );
+    }
+}
+
+/// Controller defaults for [`CharacterBodyMode::Kinematic`] bodies; tweak the component
+/// per entity after spawning to compare settings.
+pub fn kinematic_character_controller() -> KinematicCharacterController {
+    KinematicCharacterController {
+        max_slope_climb_angle: MAX_SLOPE_CLIMB_ANGLE,
+        min_slope_slide_angle: MIN_SLOPE_SLIDE_ANGLE,
+        autostep: Some(CharacterAutostep {
+            max_height: CharacterLength::Absolute(STEP_HEIGHT),
+            min_width: CharacterLength::Absolute(STEP_MIN_WIDTH),
+            include_dynamic_bodies: false,
+        }),
+        snap_to_ground: Some(CharacterLength::Absolute(SNAP_TO_GROUND)),
+        apply_impulse_to_dynamic_bodies: true,
+        custom_mass: Some(KINEMATIC_PUSH_MASS),
+        filter_flags: QueryFilterFlags::EXCLUDE_SENSORS,
+        ..default()
+    }
+}
+
+/// Adds the rigid-body components for `mode` to a character body that already has its
+/// collider and [`Velocity`].
+pub fn insert_character_body(body: &mut EntityCommands, mode: CharacterBodyMode) {
+    body.insert(mode);
+    match mode {
+        CharacterBodyMode::Dynamic => {
+            body.insert((
+                RigidBody::Dynamic,
+                Restitution::coefficient(0.3),
+                Damping {
+                    linear_damping: 0.3,
+                    angular_damping: 0.3,
+                },
+                LockedAxes::ROTATION_LOCKED,
+            ));
+        }
+        CharacterBodyMode::Kinematic => {
+            body.insert((
+                RigidBody::KinematicPositionBased,
+                kinematic_character_controller(),
+            ));
+        }
+    }
+}
+
+fn move_kinematic_characters(
+    time: Res<Time>,
+    configs: Query<&RapierConfiguration>,
+    mut q: Query<(
+        &CharacterBodyMode,
+        &mut Velocity,
+        &mut KinematicCharacterController,
+        Option<&KinematicCharacterControllerOutput>,
+    )>,
+) {
+    let dt = time.delta_secs();
+    if dt <= 0.0 {
+        return;
+    }
+    let gravity = configs.iter().next().map_or(DEFAULT_GRAVITY, |c| c.gravity);
+    for (mode, mut vel, mut controller, output) in &mut q {
+        if *mode != CharacterBodyMode::Kinematic {
+            continue;
+        }
+        let grounded = output.is_some_and(|o| o.grounded);
+        if grounded && vel.linvel.y <= 0.0 {
+            vel.linvel.y = 0.0;
+        } else {
+            vel.linvel += gravity * dt;
+        }
+        controller.translation = Some(vel.linvel * dt);
+    }
+}
+
+/// Replaces the requested velocity with the one the controller achieved this step.
+fn sync_kinematic_velocity(
+    time: Res<Time>,
+    mut q: Query<(
+        &CharacterBodyMode,
+        &mut Velocity,
+        &KinematicCharacterControllerOutput,
+    )>,
+) {
+    let dt = time.delta_secs();
+    if dt <= 0.0 {
+        return;
+    }
+    for (mode, mut vel, output) in &mut q {
+        if *mode != CharacterBodyMode::Kinematic {
+            continue;
+        }
+        vel.linvel = output.effective_translation / dt;
+        vel.angvel = Vec3::ZERO;
+    }
+}
@@ -0,0 +1,64 @@
+//! Runtime debug overlays: Rapier collider outlines and the spatial index / query gizmos.
+//!
+//! The [`DebugMenu`] resource says which overlays are drawn; edit it from any system or menu,
+//! or flip it with [`Action::ToggleColliderDebug`] / [`Action::ToggleSpatialDebug`].
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use crab_feast_library::utils::{SpatialDebugPlugin, SpatialGizmos};
+
+use crate::actions::{Action, ActionInput};
+
+pub struct DebugMenuPlugin;
+
+impl Plugin for DebugMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((RapierDebugRenderPlugin::default(), SpatialDebugPlugin))
+            .init_resource::<DebugMenu>()
+            .add_systems(
+                Update,
+                (
+                    toggle_debug_overlays,
+                    apply_debug_menu.run_if(resource_changed::<DebugMenu>),
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Which debug overlays are drawn.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DebugMenu {
+    /// Rapier collider outlines.
+    pub colliders: bool,
+    /// Spatial index quadtree nodes and footprints on the ground, with the regions and hits
+    /// of any `SpatialQuery` calls.
+    pub spatial_index: bool,
+}
+
+impl Default for DebugMenu {
+    fn default() -> Self {
+        Self {
+            colliders: true,
+            spatial_index: false,
+        }
+    }
+}
+
+fn toggle_debug_overlays(actions: ActionInput, mut menu: ResMut<DebugMenu>) {
+    if actions.just_pressed(Action::ToggleColliderDebug) {
+        menu.colliders = !menu.colliders;
+    }
+    if actions.just_pressed(Action::ToggleSpatialDebug) {
+        menu.spatial_index = !menu.spatial_index;
+    }
+}
+
+fn apply_debug_menu(
+    menu: Res<DebugMenu>,
+    mut rapier: ResMut<DebugRenderContext>,
+    mut gizmos: ResMut<GizmoConfigStore>,
+) {
+    rapier.enabled = menu.colliders;
+    gizmos.config_mut::<SpatialGizmos>().0.enabled = menu.spatial_index;
+}
@@ -1,9 +1,16 @@
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use crate::camera::GameCamera;
+use crate::actions::{Action, ActionInput};
+use crate::camera::{sync_game_camera_rig, CameraRig, GameCamera};
+use crate::locomotion::GroundPhase;
+use crate::look::{smoothing_factor, LookSettings, LookSource};
+use crate::utils::is_mobile;
 
-#[derive(Resource, Debug, Clone, Copy, Reflect, Default)]
+/// 移动输入：`direction` 为摇杆坐标（`x` 右、`-y` 前），`force` 为 0..1 的推杆幅度。
+#[derive(Debug, Clone, Copy, PartialEq, Reflect, Default, Serialize, Deserialize)]
 pub enum MovementInput {
     #[default]
     Idle,
@@ -13,111 +20,322 @@ pub enum MovementInput {
     },
 }
 
+/// 视角输入：`delta` 的单位由 `source` 决定（见 [`LookSettings::look_radians`]）。
 #[derive(Event)]
-pub struct LookInput(pub Vec2);
+pub struct LookInput {
+    pub delta: Vec2,
+    pub source: LookSource,
+}
+
+/// 缩放输入（滚轮 / 双指捏合）：正值拉近，按对数比例累计到 [`ThirdPersonCamera::zoom`]。
+///
+/// [`ThirdPersonCamera::zoom`]: crate::camera::ThirdPersonCamera::zoom
+#[derive(Event)]
+pub struct ZoomInput(pub f32);
 
-#[derive(Resource, Debug, Clone, Copy, Reflect, Default)]
+/// 跳跃按下的**边沿**：键盘 Space / 移动端跳跃按钮写入 `Activated`，
+/// [`jump_system`] 消费后复位为 `Idle`（按下时刻进入跳跃缓冲）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect, Default, Serialize, Deserialize)]
 pub enum JumpInput {
     #[default]
     Idle,
     Activated,
 }
 
-/// 按住 Left Alt 时为 [`StrafeKeepFacing`](MovementFacingMode::StrafeKeepFacing)：
+/// 最近一次产生输入的设备（键鼠 / 触摸 / 某个手柄）；手柄活跃时隐藏屏幕摇杆，
+/// 且只有活跃手柄的摇杆驱动移动。
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActiveInputDevice {
+    KeyboardMouse,
+    Touch,
+    Gamepad(Entity),
+}
+
+impl Default for ActiveInputDevice {
+    fn default() -> Self {
+        if is_mobile() {
+            Self::Touch
+        } else {
+            Self::KeyboardMouse
+        }
+    }
+}
+
+/// 按住 [`Action::Strafe`]（默认 Left Alt / 手柄 L2）时为
+/// [`StrafeKeepFacing`](MovementFacingMode::StrafeKeepFacing)：
 /// 身体偏航不随移动转向；否则朝移动方向转向。
-#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect, Serialize, Deserialize)]
 pub enum MovementFacingMode {
     #[default]
     FaceMoveDirection,
     StrafeKeepFacing,
 }
 
+/// 玩家编号：本地分屏玩家、网络远端玩家或 AI 控制者各用一个。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Reflect)]
+pub struct PlayerId(pub u8);
+
+/// 本机输入设备（键鼠 / 触摸 / 手柄）所属的玩家。
+#[derive(Resource, Debug, Clone, Copy, Default)]
+pub struct LocalPlayer(pub PlayerId);
+
+/// 实体由哪个玩家控制；`by` 为 [`LocalPlayer`] 时本机输入写入它的 [`ControlIntent`]，
+/// 相机也跟随它。
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Controlled {
+    pub by: PlayerId,
+}
+
+/// 由 [`Controlled`] 与 [`LocalPlayer`] 维护的标记：实体归本机玩家控制。
+#[derive(Component, Debug, Default)]
+pub struct LocallyControlled;
+
+/// 单个角色的控制意图：本机输入设备、网络或 AI 写入，移动 / 朝向 / 跳跃 / 动画只读它，
+/// 因此多个角色可以各自独立驱动。
+#[derive(Component, Debug, Clone, Copy, PartialEq, Default, Reflect, Serialize, Deserialize)]
+pub struct ControlIntent {
+    pub movement: MovementInput,
+    pub jump: JumpInput,
+    pub facing: MovementFacingMode,
+    /// `movement.direction` 的参考偏航：本机玩家为相机轨道偏航，AI 用 `0.0` 即世界坐标
+    /// （`x` 为 +X、`-y` 为 -Z）；`None` 时不移动（如自由飞行调试相机）。
+    pub reference_yaw: Option<f32>,
+}
+
+impl ControlIntent {
+    /// 世界 XZ 平面上的移动意图：单位方向 × 力度；无输入或无参考偏航时为 `None`。
+    pub fn world_movement(&self) -> Option<Vec3> {
+        let MovementInput::Activated { direction, force } = self.movement else {
+            return None;
+        };
+        if force < 0.01 || direction.length() < 0.01 {
+            return None;
+        }
+        intent_horizontal_xz_on_ground(direction, self.reference_yaw?).map(|w| w * force)
+    }
+}
+
+/// 本机输入设备写入 [`LocallyControlled`] 实体的 [`ControlIntent`]。
+#[derive(SystemParam)]
+pub struct LocalControl<'w, 's> {
+    intents: Query<'w, 's, &'static mut ControlIntent, With<LocallyControlled>>,
+}
+
+impl LocalControl<'_, '_> {
+    pub fn update(&mut self, mut f: impl FnMut(&mut ControlIntent)) {
+        for mut intent in &mut self.intents {
+            f(&mut intent);
+        }
+    }
+
+    /// 本机玩家当前的移动输入；没有受控实体时为 `Idle`。
+    pub fn movement(&self) -> MovementInput {
+        self.intents
+            .iter()
+            .next()
+            .map_or(MovementInput::Idle, |intent| intent.movement)
+    }
+}
+
 /// 仅用于移动方向与蒙皮前向；刚体根保持 **identity** 旋转，相机在世界里独立轨道。
-#[derive(Component, Clone, Copy, Default)]
+#[derive(Component, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
 pub struct CharacterBodyYaw(pub f32);
 
 /// 蒙皮/场景根父节点（胶囊子级），只同步 [`CharacterBodyYaw`] 的 yaw。
 #[derive(Component)]
 pub struct PlayerCharacterModelRoot;
 
-#[derive(Component)]
+/// 水平移动参数：目标速度由输入决定，实际速度按加/减速度逼近，空中乘以 `air_control`。
+#[derive(Component, Debug, Clone, Copy)]
 pub struct MovementController {
     pub speed: f32,
+    /// 加速（朝目标速度增加）时的速度变化率，m/s²。
+    pub acceleration: f32,
+    /// 松开输入或反向时的速度变化率，m/s²。
+    pub deceleration: f32,
+    /// 空中时加/减速度的比例，0 = 空中无法改变水平速度，1 = 与地面相同。
+    pub air_control: f32,
 }
 
-#[allow(dead_code)]
-pub enum LookAxis {
-    Yaw,
-    Pitch,
-    YawAndPitch,
-}
-
+/// 视角累计角度；俯仰范围与灵敏度倍率由同一实体上的 [`CameraRig`] 决定。
+/// 输入写入 `target_*`，`accumulated_*` 按 [`LookSettings::smoothing_secs`] 指数逼近。
 #[derive(Component)]
 pub struct LookController {
-    pub axis: LookAxis,
     pub accumulated_yaw: f32,
     pub accumulated_pitch: f32,
+    pub target_yaw: f32,
+    pub target_pitch: f32,
+}
+
+/// 跳跃参数：起跳竖直速度、coyote time（离地后仍可起跳的宽限）与跳跃缓冲（落地前提前按下）。
+#[derive(Component, Debug, Clone, Copy)]
+pub struct JumpController {
+    pub jump_speed: f32,
+    pub coyote_time: f32,
+    pub buffer_time: f32,
+    /// 胶囊底部向下探测地面的距离。
+    pub ground_probe_distance: f32,
+}
+
+impl Default for JumpController {
+    fn default() -> Self {
+        Self {
+            jump_speed: 5.0,
+            coyote_time: 0.12,
+            buffer_time: 0.15,
+            ground_probe_distance: 0.08,
+        }
+    }
+}
+
+/// 地面检测结果与跳跃计时，由 [`ground_detection_system`] / [`jump_system`] 维护。
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct GroundState {
+    pub phase: GroundPhase,
+    /// 最近一次接地时刻（`Time::elapsed_secs`），用于 coyote time。
+    pub last_grounded_secs: f32,
+    /// 最近一次起跳时刻；起跳后短时间内忽略地面命中，避免刚离地又被判定接地。
+    pub last_jump_secs: Option<f32>,
+    /// 缓冲中的跳跃按下时刻。
+    pub buffered_jump_secs: Option<f32>,
+    /// 进入 [`GroundPhase::Landing`] 的时刻。
+    pub landed_secs: f32,
 }
 
 impl Default for MovementController {
     fn default() -> Self {
-        Self { speed: 10.0 }
+        Self {
+            speed: 10.0,
+            acceleration: 40.0,
+            deceleration: 60.0,
+            air_control: 0.3,
+        }
     }
 }
 
 impl Default for LookController {
     fn default() -> Self {
         Self {
-            axis: LookAxis::YawAndPitch,
             accumulated_yaw: 0.0,
             accumulated_pitch: 0.0,
+            target_yaw: 0.0,
+            target_pitch: 0.0,
         }
     }
 }
 
+/// [`ControlInputPlugin`] 在 `Update` 中的两段：先由本机输入补全 [`ControlIntent`]，
+/// 再按意图驱动角色。网络等需要读取完整意图的系统排在两者之间。
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ControlSystems {
+    /// 视角平滑、朝向模式与参考偏航写入本机意图。
+    Intent,
+    /// 转身、移动、地面检测、跳跃（消费跳跃边沿）、模型朝向与相机。
+    Motion,
+}
+
 pub struct ControlInputPlugin;
 
 impl Plugin for ControlInputPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<MovementInput>()
-            .init_resource::<JumpInput>()
-            .init_resource::<MovementFacingMode>()
+        app.init_resource::<LocalPlayer>()
+            .init_resource::<ActiveInputDevice>()
+            .init_resource::<LookSettings>()
+            .add_systems(PreUpdate, sync_locally_controlled)
+            .configure_sets(
+                Update,
+                (ControlSystems::Intent, ControlSystems::Motion).chain(),
+            )
             .add_systems(
                 Update,
                 (
-                    sync_movement_facing_mode_from_keyboard,
-                    face_body_toward_local_movement,
-                    // 每帧运行：无输入时清零水平速度，避免仅靠阻尼滑行导致与切 idle/根骨 存在长时间错位感
-                    movement_system,
-                    sync_player_character_model_rotation,
-                    sync_third_person_game_camera,
+                    smooth_look_system,
+                    sync_movement_facing_mode,
+                    sync_control_reference_yaw,
                 )
-                    .chain(),
+                    .chain()
+                    .in_set(ControlSystems::Intent),
             )
             .add_systems(
                 Update,
-                jump_system.run_if(resource_changed::<MovementInput>),
+                (
+                    face_body_toward_local_movement,
+                    // 每帧运行：无输入时按减速度收敛到零，避免仅靠阻尼滑行导致与切 idle/根骨 存在长时间错位感
+                    movement_system,
+                    ground_detection_system,
+                    jump_system,
+                    sync_player_character_model_rotation,
+                    sync_game_camera_rig,
+                )
+                    .chain()
+                    .in_set(ControlSystems::Motion),
             )
-            .add_observer(look_system);
+            .add_observer(look_system)
+            .add_observer(remove_locally_controlled);
     }
 }
 
 const FACE_TURN_RADIANS_PER_SEC: f32 = 10.0;
 /// 与目标角差小于此则直接对齐，避免围绕「移动目标角」做无尽闭环修正
 const FACE_ARRIVAL_RAD: f32 = 0.05;
-const STRAFE_MODIFIER_KEY: KeyCode = KeyCode::AltLeft;
-const CAM_OFFSET: Vec3 = Vec3::new(0.0, 1.3, 5.0);
-const CAM_LOOK_AT_OFFSET: Vec3 = Vec3::new(0.0, 0.6, 0.0);
+/// 起跳后忽略地面命中的时长，防止离地第一帧仍被探测为接地。
+const JUMP_GROUND_IGNORE_SECS: f32 = 0.15;
+/// 落地后保持 [`GroundPhase::Landing`] 的时长。
+const LANDING_SECS: f32 = 0.25;
+/// 探测用球半径相对胶囊半径的比例，略小于 1 以免擦到墙面被当作地面。
+const GROUND_PROBE_RADIUS_SCALE: f32 = 0.9;
 
-fn sync_movement_facing_mode_from_keyboard(
-    keyboard: Res<ButtonInput<KeyCode>>,
-    mut mode: ResMut<MovementFacingMode>,
+fn sync_locally_controlled(
+    mut commands: Commands,
+    local: Res<LocalPlayer>,
+    controlled: Query<(Entity, Ref<Controlled>, Has<LocallyControlled>)>,
 ) {
-    *mode = if keyboard.pressed(STRAFE_MODIFIER_KEY) {
+    for (entity, controlled, marked) in &controlled {
+        if !local.is_changed() && !controlled.is_changed() {
+            continue;
+        }
+        match (controlled.by == local.0, marked) {
+            (true, false) => {
+                commands.entity(entity).insert(LocallyControlled);
+            }
+            (false, true) => {
+                commands.entity(entity).remove::<LocallyControlled>();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn remove_locally_controlled(trigger: On<Remove, Controlled>, mut commands: Commands) {
+    if let Ok(mut entity) = commands.get_entity(trigger.event_target()) {
+        // 实体可能正在被销毁
+        entity.try_remove::<LocallyControlled>();
+    }
+}
+
+fn sync_movement_facing_mode(actions: ActionInput, mut control: LocalControl) {
+    let facing = if actions.pressed(Action::Strafe) {
         MovementFacingMode::StrafeKeepFacing
     } else {
         MovementFacingMode::FaceMoveDirection
     };
+    control.update(|intent| intent.facing = facing);
+}
+
+/// 本机玩家的移动以游戏相机轨道偏航为参考；自由飞行调试相机时为 `None`，角色不动。
+fn sync_control_reference_yaw(
+    game: Res<GameCamera>,
+    camera: Query<(&LookController, &CameraRig), With<Camera3d>>,
+    mut intents: Query<&mut ControlIntent, With<LocallyControlled>>,
+) {
+    let yaw = camera
+        .get(game.0)
+        .ok()
+        .filter(|(_, rig)| !rig.is_detached())
+        .map(|(look, _)| look.accumulated_yaw);
+    for mut intent in &mut intents {
+        intent.reference_yaw = yaw;
+    }
 }
 
 fn body_rotation(yaw: f32) -> Quat {
@@ -157,92 +375,104 @@ fn intent_horizontal_xz_on_ground(
     Some(w / len)
 }
 
+/// 身体偏航朝意图的移动方向转动一帧后的值；侧移保持朝向或无移动时不变。
+pub(crate) fn turn_toward_movement(yaw: f32, intent: &ControlIntent, dt: f32) -> f32 {
+    if intent.facing == MovementFacingMode::StrafeKeepFacing {
+        return yaw;
+    }
+    let Some(w) = intent.world_movement() else {
+        return yaw;
+    };
+    let target_yaw = w.x.atan2(w.z);
+    let mut d = target_yaw - yaw;
+    d = (d + std::f32::consts::PI).rem_euclid(2.0 * std::f32::consts::PI) - std::f32::consts::PI;
+    if d.abs() <= FACE_ARRIVAL_RAD {
+        return target_yaw;
+    }
+    let max_step = FACE_TURN_RADIANS_PER_SEC * dt;
+    yaw + d.clamp(-max_step, max_step)
+}
+
 fn face_body_toward_local_movement(
     time: Res<Time>,
-    facing: Res<MovementFacingMode>,
-    input: Res<MovementInput>,
-    game: Res<GameCamera>,
-    camera: Query<&LookController, With<Camera3d>>,
-    mut q: Query<&mut CharacterBodyYaw, With<MovementController>>,
+    mut q: Query<(&mut CharacterBodyYaw, &ControlIntent), With<MovementController>>,
 ) {
-    if matches!(*facing, MovementFacingMode::StrafeKeepFacing) {
-        return;
+    let dt = time.delta_secs();
+    for (mut yaw, intent) in &mut q {
+        yaw.0 = turn_toward_movement(yaw.0, intent, dt);
     }
-    if let MovementInput::Activated { direction, force } = input.as_ref() {
-        if *force < 0.01 || direction.length() < 0.01 {
-            return;
-        }
-        let Ok(cam) = camera.get(game.0) else {
-            return;
-        };
-        let Some(w) = intent_horizontal_xz_on_ground(*direction, cam.accumulated_yaw) else {
-            return;
-        };
-        let target_yaw = w.x.atan2(w.z);
-        let dt = time.delta_secs();
-        for mut yaw in &mut q {
-            let from = yaw.0;
-            let mut d = target_yaw - from;
-            d = (d + std::f32::consts::PI).rem_euclid(2.0 * std::f32::consts::PI)
-                - std::f32::consts::PI;
-            if d.abs() <= FACE_ARRIVAL_RAD {
-                yaw.0 = target_yaw;
-                continue;
-            }
-            let max_step = FACE_TURN_RADIANS_PER_SEC * dt;
-            yaw.0 = from + d.clamp(-max_step, max_step);
-        }
+}
+
+/// 水平速度向 `target` 逼近一帧：目标非零且不反向时用加速度，否则用减速度。
+fn approach_horizontal_velocity(
+    current: Vec2,
+    target: Vec2,
+    controller: &MovementController,
+    airborne: bool,
+    dt: f32,
+) -> Vec2 {
+    let speeding_up = target.length_squared() > 1e-6 && current.dot(target) >= 0.0;
+    let rate = if speeding_up {
+        controller.acceleration
+    } else {
+        controller.deceleration
+    };
+    let control = if airborne {
+        controller.air_control
+    } else {
+        1.0
+    };
+    let max_step = rate * control * dt;
+    let delta = target - current;
+    if delta.length() <= max_step {
+        target
+    } else {
+        current + delta.normalize() * max_step
     }
 }
 
-fn clear_horizontal_move_velocity(vel: &mut Velocity) {
-    vel.linvel.x = 0.0;
-    vel.linvel.z = 0.0;
+/// 一帧后的水平速度（世界 XZ）：目标方向 × 输入力度；无输入 / 无参考偏航时目标为零，
+/// 由减速度收敛。
+pub(crate) fn step_horizontal_velocity(
+    current: Vec2,
+    intent: &ControlIntent,
+    controller: &MovementController,
+    airborne: bool,
+    dt: f32,
+) -> Vec2 {
+    let target = intent
+        .world_movement()
+        .map_or(Vec2::ZERO, |w| Vec2::new(w.x, w.z) * controller.speed);
+    approach_horizontal_velocity(current, target, controller, airborne, dt)
 }
 
 fn movement_system(
-    game: Res<GameCamera>,
-    camera: Query<&LookController, With<Camera3d>>,
-    mut movement_controllers: Query<(&mut Velocity, &MovementController)>,
-    input: Res<MovementInput>,
+    time: Res<Time>,
+    mut movement_controllers: Query<(
+        &mut Velocity,
+        &MovementController,
+        &ControlIntent,
+        Option<&GroundState>,
+    )>,
 ) {
-    match input.as_ref() {
-        MovementInput::Idle => {
-            for (mut vel, _) in &mut movement_controllers {
-                clear_horizontal_move_velocity(&mut vel);
-            }
-        }
-        MovementInput::Activated { direction, force } => {
-            if *force < 0.01 || direction.length() < 0.01 {
-                for (mut vel, _) in &mut movement_controllers {
-                    clear_horizontal_move_velocity(&mut vel);
-                }
-                return;
-            }
-            let Ok(cam) = camera.get(game.0) else {
-                for (mut vel, _) in &mut movement_controllers {
-                    clear_horizontal_move_velocity(&mut vel);
-                }
-                return;
-            };
-            let Some(w) = intent_horizontal_xz_on_ground(*direction, cam.accumulated_yaw) else {
-                for (mut vel, _) in &mut movement_controllers {
-                    clear_horizontal_move_velocity(&mut vel);
-                }
-                return;
-            };
-            for (mut vel, movement_controller) in &mut movement_controllers {
-                let v = w * *force * movement_controller.speed;
-                vel.linvel.x = v.x;
-                vel.linvel.z = v.z;
-            }
-        }
+    let dt = time.delta_secs();
+    for (mut vel, movement_controller, intent, ground) in &mut movement_controllers {
+        let airborne = ground.is_some_and(|g| g.phase == GroundPhase::Airborne);
+        let v = step_horizontal_velocity(
+            Vec2::new(vel.linvel.x, vel.linvel.z),
+            intent,
+            movement_controller,
+            airborne,
+            dt,
+        );
+        vel.linvel.x = v.x;
+        vel.linvel.z = v.y;
     }
 }
 
 fn sync_player_character_model_rotation(
     mut model: Query<(&mut Transform, &ChildOf), With<PlayerCharacterModelRoot>>,
-    parents: Query<&CharacterBodyYaw, With<MovementController>>,
+    parents: Query<&CharacterBodyYaw>,
 ) {
     for (mut t, child_of) in &mut model {
         if let Ok(yaw) = parents.get(child_of.0) {
@@ -251,49 +481,137 @@ fn sync_player_character_model_rotation(
     }
 }
 
-fn sync_third_person_game_camera(
-    game_camera: Res<GameCamera>,
-    player: Query<&GlobalTransform, (With<MovementController>, With<CharacterBodyYaw>)>,
-    mut camera: Query<(&mut Transform, &LookController), With<Camera3d>>,
+fn look_system(
+    trigger: On<LookInput>,
+    time: Res<Time>,
+    settings: Res<LookSettings>,
+    mut look_controllers: Query<(&mut LookController, &CameraRig), With<Camera3d>>,
 ) {
-    let Ok(player_gt) = player.single() else {
-        return;
-    };
-    let Ok((mut cam_tf, look)) = camera.get_mut(game_camera.0) else {
-        return;
-    };
-    let p = player_gt.translation();
-    let orbit = Quat::from_euler(
-        EulerRot::ZYX,
-        0.0,
-        look.accumulated_yaw,
-        look.accumulated_pitch,
-    );
-    cam_tf.translation = p + orbit * CAM_OFFSET;
-    let look_at = p + CAM_LOOK_AT_OFFSET;
-    cam_tf.look_at(look_at, Vec3::Y);
+    let event = trigger.event();
+    let delta = settings.look_radians(event.source, event.delta, time.delta_secs());
+    for (mut look_controller, rig) in &mut look_controllers {
+        let profile = rig.look_profile();
+        look_controller.target_yaw -= delta.x * profile.sensitivity;
+        look_controller.target_pitch = (look_controller.target_pitch
+            - delta.y * profile.sensitivity)
+            .clamp(profile.min_pitch, profile.max_pitch);
+    }
 }
 
-fn look_system(
-    trigger: On<LookInput>,
-    mut look_controllers: Query<&mut LookController, With<Camera3d>>,
+fn smooth_look_system(
+    time: Res<Time>,
+    settings: Res<LookSettings>,
+    mut look_controllers: Query<&mut LookController>,
 ) {
-    let rotate_speed = 10.0;
-    let max_pitch = std::f32::consts::FRAC_PI_2 - 0.1;
+    let k = smoothing_factor(time.delta_secs(), settings.smoothing_secs);
+    for mut look in &mut look_controllers {
+        look.accumulated_yaw += (look.target_yaw - look.accumulated_yaw) * k;
+        look.accumulated_pitch += (look.target_pitch - look.accumulated_pitch) * k;
+    }
+}
 
-    for mut look_controller in &mut look_controllers {
-        if !matches!(look_controller.axis, LookAxis::YawAndPitch) {
+/// 从胶囊下半球球心向下做球形 shape-cast；命中且未在上升则视为接地。
+fn ground_detection_system(
+    time: Res<Time>,
+    rapier_context: ReadRapierContext,
+    mut q: Query<(
+        Entity,
+        &GlobalTransform,
+        &Collider,
+        &Velocity,
+        &JumpController,
+        &mut GroundState,
+    )>,
+) {
+    let Ok(rapier_context) = rapier_context.single() else {
+        return;
+    };
+    let now = time.elapsed_secs();
+    for (entity, gt, collider, vel, jump, mut ground) in &mut q {
+        let Some(capsule) = collider.as_capsule() else {
             continue;
+        };
+        let half_height = capsule.half_height();
+        let radius = capsule.radius();
+        let probe_radius = radius * GROUND_PROBE_RADIUS_SCALE;
+        let origin = gt.translation() - Vec3::Y * half_height;
+        let max_toi = radius - probe_radius + jump.ground_probe_distance;
+
+        let just_jumped = ground
+            .last_jump_secs
+            .is_some_and(|t| now - t < JUMP_GROUND_IGNORE_SECS);
+        let hit = !just_jumped
+            && vel.linvel.y <= 0.5
+            && rapier_context
+                .cast_shape(
+                    origin,
+                    Quat::IDENTITY,
+                    -Vec3::Y,
+                    &Collider::ball(probe_radius),
+                    ShapeCastOptions::with_max_time_of_impact(max_toi),
+                    QueryFilter::default()
+                        .exclude_rigid_body(entity)
+                        .exclude_sensors(),
+                )
+                .is_some();
+
+        ground.phase = match (ground.phase, hit) {
+            (GroundPhase::Airborne, true) => {
+                ground.landed_secs = now;
+                GroundPhase::Landing
+            }
+            (GroundPhase::Landing, true) if now - ground.landed_secs < LANDING_SECS => {
+                GroundPhase::Landing
+            }
+            (_, true) => GroundPhase::Grounded,
+            (_, false) => GroundPhase::Airborne,
+        };
+        if hit {
+            ground.last_grounded_secs = now;
         }
-        look_controller.accumulated_yaw -= trigger.event().0.x * rotate_speed;
-        look_controller.accumulated_pitch = (look_controller.accumulated_pitch
-            - trigger.event().0.y * rotate_speed)
-        .clamp(-max_pitch, max_pitch);
     }
 }
 
-fn jump_system(input: Res<JumpInput>) {
-    if let JumpInput::Activated = input.as_ref() {
-        // 跳跃逻辑
+/// 消费各实体 [`ControlIntent::jump`] 的边沿进入缓冲；
+/// 在接地或 coyote time 内且缓冲未过期时起跳。
+fn jump_system(
+    time: Res<Time>,
+    mut q: Query<
+        (
+            &mut Velocity,
+            &JumpController,
+            &mut GroundState,
+            &mut ControlIntent,
+        ),
+        With<MovementController>,
+    >,
+) {
+    let now = time.elapsed_secs();
+    for (mut vel, jump, mut ground, mut intent) in &mut q {
+        if intent.jump == JumpInput::Activated {
+            intent.jump = JumpInput::Idle;
+            ground.buffered_jump_secs = Some(now);
+        }
+        let Some(pressed_at) = ground.buffered_jump_secs else {
+            continue;
+        };
+        if now - pressed_at > jump.buffer_time {
+            ground.buffered_jump_secs = None;
+            continue;
+        }
+        let can_jump = match ground.phase {
+            GroundPhase::Grounded | GroundPhase::Landing => true,
+            GroundPhase::Airborne => {
+                now - ground.last_grounded_secs <= jump.coyote_time
+                    && ground.last_jump_secs.is_none_or(|t| t < ground.last_grounded_secs)
+            }
+        };
+        if !can_jump {
+            continue;
+        }
+        vel.linvel.y = jump.jump_speed;
+        ground.phase = GroundPhase::Airborne;
+        ground.last_jump_secs = Some(now);
+        ground.buffered_jump_secs = None;
     }
 }
@@ -5,17 +5,32 @@ use bevy::{
 };
 
 
+mod actions;
 mod assets;
+mod blend_space;
 mod camera;
+mod character;
+mod character_body;
+mod debug;
 mod input;
 mod locomotion;
+mod look;
+mod multiplayer;
+mod navigation;
+mod npc;
+mod prediction;
 mod scene;
 mod state;
+mod steering;
 mod ui;
 mod utils;
 mod root_motion;
 
+pub use actions::{Action, ActionMap, AxisAction, AxisBinding, BindingConflict, InputBinding};
 pub use assets::GameAssets;
+pub use character::CharacterManifest;
+pub use crab_feast_library::net::{NetMode, NetOptions};
+pub use debug::DebugMenu;
 pub use state::GameState;
 
 pub fn build_app(app: &mut App) {
@@ -45,8 +60,11 @@ pub fn build_app(app: &mut App) {
                 ..Default::default()
             },
         },
+        actions::ActionsPlugin,
+        character::CharacterPlugin,
         assets::AssetLoadingPlugin,
         ui::UiPlugin,
         scene::ScenePlugin,
+        multiplayer::MultiplayerPlugin,
     ));
 }
\ No newline at end of file
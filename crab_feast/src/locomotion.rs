@@ -1,4 +1,13 @@
-//! Speed / input → locomotion clip selection (tests cover the pure mapping).
+//! Speed / input / ground phase → locomotion clip selection (tests cover the pure mapping).
+//!
+//! [`LocomotionStateMachine`] layers hysteresis, strafe and turn-in-place on top of the
+//! stateless [`locomotion_anim_from_speed_and_force`] mapping; [`ground_blend_point`] turns
+//! its state into the point the ground blend space is sampled at.
+
+use bevy::math::Vec2;
+use serde::{Deserialize, Serialize};
+
+use crate::input::MovementFacingMode;
 
 /// Horizontal speed (m/s) and optional movement input force in \[0, 1\].
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -14,6 +23,27 @@ pub enum LocomotionAnim {
     Idle,
     Walk,
     Run,
+    StrafeWalkLeft,
+    StrafeWalkRight,
+    StrafeRunLeft,
+    StrafeRunRight,
+    /// Standing still while the body yaw is turning.
+    TurnLeft,
+    TurnRight,
+    /// Mid-air (jumping or falling).
+    Jump,
+    /// Short recovery after touching down without movement input.
+    Land,
+}
+
+/// Ground contact phase of a character body.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GroundPhase {
+    #[default]
+    Grounded,
+    Airborne,
+    /// Just touched down; lasts a short, fixed time before returning to `Grounded`.
+    Landing,
 }
 
 /// Idle if almost no motion; run if speed is high or player is pushing hard on the stick.
@@ -38,6 +68,187 @@ pub fn locomotion_anim_from_speed_and_force(
     }
 }
 
+/// Airborne always plays the jump clip; landing only overrides the ground clip while idle,
+/// so moving landings go straight back to walk/run.
+pub fn locomotion_anim_for_ground_phase(phase: GroundPhase, on_ground: LocomotionAnim) -> LocomotionAnim {
+    match phase {
+        GroundPhase::Airborne => LocomotionAnim::Jump,
+        GroundPhase::Landing if !on_ground.is_moving() => LocomotionAnim::Land,
+        _ => on_ground,
+    }
+}
+
+impl LocomotionAnim {
+    fn is_moving(self) -> bool {
+        !matches!(
+            self,
+            LocomotionAnim::Idle
+                | LocomotionAnim::TurnLeft
+                | LocomotionAnim::TurnRight
+                | LocomotionAnim::Jump
+                | LocomotionAnim::Land
+        )
+    }
+
+    fn is_running(self) -> bool {
+        matches!(
+            self,
+            LocomotionAnim::Run | LocomotionAnim::StrafeRunLeft | LocomotionAnim::StrafeRunRight
+        )
+    }
+
+    pub fn is_strafing(self) -> bool {
+        matches!(
+            self,
+            LocomotionAnim::StrafeWalkLeft
+                | LocomotionAnim::StrafeWalkRight
+                | LocomotionAnim::StrafeRunLeft
+                | LocomotionAnim::StrafeRunRight
+        )
+    }
+
+    fn is_turning(self) -> bool {
+        matches!(self, LocomotionAnim::TurnLeft | LocomotionAnim::TurnRight)
+    }
+}
+
+/// One frame of input for [`LocomotionStateMachine::update`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LocomotionSample {
+    /// Horizontal velocity in body space: `x` right, `y` forward (m/s).
+    pub velocity_body: Vec2,
+    /// Body yaw rate (rad/s); positive turns left (counter-clockwise seen from above).
+    pub yaw_rate: f32,
+    pub ground: GroundPhase,
+    pub facing: MovementFacingMode,
+    /// `None` when not moving (idle input).
+    pub move_force: Option<f32>,
+}
+
+/// Enter / exit thresholds; each pair is a hysteresis band so values hovering
+/// around one threshold do not flip the state every frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LocomotionParams {
+    pub walk_enter_speed: f32,
+    pub walk_exit_speed: f32,
+    pub run_enter_speed: f32,
+    pub run_exit_speed: f32,
+    pub run_force: f32,
+    /// Lateral share of the speed (`|x| / |v|`) needed to start / keep strafing.
+    pub strafe_enter_ratio: f32,
+    pub strafe_exit_ratio: f32,
+    pub turn_enter_rate: f32,
+    pub turn_exit_rate: f32,
+}
+
+impl Default for LocomotionParams {
+    fn default() -> Self {
+        Self {
+            walk_enter_speed: 0.2,
+            walk_exit_speed: 0.1,
+            run_enter_speed: 4.0,
+            run_exit_speed: 3.4,
+            run_force: 0.9,
+            strafe_enter_ratio: 0.7,
+            strafe_exit_ratio: 0.5,
+            turn_enter_rate: 1.5,
+            turn_exit_rate: 0.5,
+        }
+    }
+}
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LocomotionStateMachine {
+    state: LocomotionAnim,
+}
+
+impl LocomotionStateMachine {
+    pub fn state(&self) -> LocomotionAnim {
+        self.state
+    }
+
+    pub fn update(&mut self, sample: &LocomotionSample, params: &LocomotionParams) -> LocomotionAnim {
+        let current = self.state;
+        let speed = sample.velocity_body.length();
+
+        // Hysteresis: once in a band, leaving it needs the (lower) exit threshold.
+        let idle_max = if current.is_moving() {
+            params.walk_exit_speed
+        } else {
+            params.walk_enter_speed
+        };
+        let run_min = if current.is_running() {
+            params.run_exit_speed
+        } else {
+            params.run_enter_speed
+        };
+        let base = locomotion_anim_from_speed_and_force(
+            LocomotionInput {
+                speed_horizontal: speed,
+                move_force: sample.move_force,
+            },
+            idle_max,
+            run_min,
+            params.run_force,
+        );
+
+        let on_ground = match base {
+            LocomotionAnim::Idle => {
+                let turn_min = if current.is_turning() {
+                    params.turn_exit_rate
+                } else {
+                    params.turn_enter_rate
+                };
+                if sample.yaw_rate >= turn_min {
+                    LocomotionAnim::TurnLeft
+                } else if sample.yaw_rate <= -turn_min {
+                    LocomotionAnim::TurnRight
+                } else {
+                    LocomotionAnim::Idle
+                }
+            }
+            walk_or_run if sample.facing == MovementFacingMode::StrafeKeepFacing => {
+                let strafe_min = if current.is_strafing() {
+                    params.strafe_exit_ratio
+                } else {
+                    params.strafe_enter_ratio
+                };
+                let lateral = if speed > 1e-4 {
+                    sample.velocity_body.x.abs() / speed
+                } else {
+                    0.0
+                };
+                if lateral < strafe_min {
+                    walk_or_run
+                } else {
+                    let right = sample.velocity_body.x > 0.0;
+                    match (walk_or_run == LocomotionAnim::Run, right) {
+                        (false, false) => LocomotionAnim::StrafeWalkLeft,
+                        (false, true) => LocomotionAnim::StrafeWalkRight,
+                        (true, false) => LocomotionAnim::StrafeRunLeft,
+                        (true, true) => LocomotionAnim::StrafeRunRight,
+                    }
+                }
+            }
+            walk_or_run => walk_or_run,
+        };
+
+        self.state = locomotion_anim_for_ground_phase(sample.ground, on_ground);
+        self.state
+    }
+}
+
+/// Where ground locomotion samples its blend space in `state`. Standing states sample the
+/// origin, so the idle hysteresis also holds the idle pose. Moving states slide from straight
+/// ahead at the body's speed (`strafe` 0) to the full body-space velocity (`strafe` 1), so
+/// strafe clips only blend in while the machine is strafing.
+pub fn ground_blend_point(state: LocomotionAnim, velocity_body: Vec2, strafe: f32) -> Vec2 {
+    if !state.is_moving() {
+        return Vec2::ZERO;
+    }
+    Vec2::new(0.0, velocity_body.length()).lerp(velocity_body, strafe.clamp(0.0, 1.0))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -97,4 +308,113 @@ mod tests {
         );
         assert_eq!(s, LocomotionAnim::Run);
     }
+
+    #[test]
+    fn airborne_overrides_run() {
+        let s = locomotion_anim_for_ground_phase(GroundPhase::Airborne, LocomotionAnim::Run);
+        assert_eq!(s, LocomotionAnim::Jump);
+    }
+
+    #[test]
+    fn landing_while_idle_is_land() {
+        let s = locomotion_anim_for_ground_phase(GroundPhase::Landing, LocomotionAnim::Idle);
+        assert_eq!(s, LocomotionAnim::Land);
+    }
+
+    #[test]
+    fn landing_while_moving_keeps_ground_anim() {
+        let s = locomotion_anim_for_ground_phase(GroundPhase::Landing, LocomotionAnim::Walk);
+        assert_eq!(s, LocomotionAnim::Walk);
+    }
+
+    fn sample(velocity_body: Vec2, move_force: Option<f32>) -> LocomotionSample {
+        LocomotionSample {
+            velocity_body,
+            yaw_rate: 0.0,
+            ground: GroundPhase::Grounded,
+            facing: MovementFacingMode::FaceMoveDirection,
+            move_force,
+        }
+    }
+
+    #[test]
+    fn machine_run_has_hysteresis() {
+        let params = LocomotionParams::default();
+        let mut m = LocomotionStateMachine::default();
+        assert_eq!(m.update(&sample(Vec2::new(0.0, 4.1), Some(0.5)), &params), LocomotionAnim::Run);
+        // Dipping just under the enter threshold keeps running...
+        assert_eq!(m.update(&sample(Vec2::new(0.0, 3.8), Some(0.5)), &params), LocomotionAnim::Run);
+        // ...until the exit threshold is crossed.
+        assert_eq!(m.update(&sample(Vec2::new(0.0, 3.0), Some(0.5)), &params), LocomotionAnim::Walk);
+        assert_eq!(m.update(&sample(Vec2::new(0.0, 3.8), Some(0.5)), &params), LocomotionAnim::Walk);
+    }
+
+    #[test]
+    fn machine_idle_has_hysteresis() {
+        let params = LocomotionParams::default();
+        let mut m = LocomotionStateMachine::default();
+        assert_eq!(m.update(&sample(Vec2::new(0.0, 0.15), None), &params), LocomotionAnim::Idle);
+        assert_eq!(m.update(&sample(Vec2::new(0.0, 0.3), None), &params), LocomotionAnim::Walk);
+        assert_eq!(m.update(&sample(Vec2::new(0.0, 0.15), None), &params), LocomotionAnim::Walk);
+        assert_eq!(m.update(&sample(Vec2::new(0.0, 0.05), None), &params), LocomotionAnim::Idle);
+    }
+
+    #[test]
+    fn machine_strafes_only_when_keeping_facing() {
+        let params = LocomotionParams::default();
+        let mut m = LocomotionStateMachine::default();
+        let mut s = sample(Vec2::new(-2.0, 0.2), Some(0.6));
+        assert_eq!(m.update(&s, &params), LocomotionAnim::Walk);
+        s.facing = MovementFacingMode::StrafeKeepFacing;
+        assert_eq!(m.update(&s, &params), LocomotionAnim::StrafeWalkLeft);
+        s.velocity_body = Vec2::new(5.0, 0.0);
+        assert_eq!(m.update(&s, &params), LocomotionAnim::StrafeRunRight);
+        // Mostly forward: ratio ~0.56 stays strafing (exit band), ~0.3 leaves it.
+        s.velocity_body = Vec2::new(3.0, 4.5);
+        assert_eq!(m.update(&s, &params), LocomotionAnim::StrafeRunRight);
+        s.velocity_body = Vec2::new(1.5, 4.5);
+        assert_eq!(m.update(&s, &params), LocomotionAnim::Run);
+    }
+
+    #[test]
+    fn machine_turns_in_place() {
+        let params = LocomotionParams::default();
+        let mut m = LocomotionStateMachine::default();
+        let mut s = sample(Vec2::ZERO, None);
+        s.yaw_rate = 2.0;
+        assert_eq!(m.update(&s, &params), LocomotionAnim::TurnLeft);
+        s.yaw_rate = 1.0;
+        assert_eq!(m.update(&s, &params), LocomotionAnim::TurnLeft);
+        s.yaw_rate = 0.2;
+        assert_eq!(m.update(&s, &params), LocomotionAnim::Idle);
+        s.yaw_rate = -2.0;
+        assert_eq!(m.update(&s, &params), LocomotionAnim::TurnRight);
+    }
+
+    #[test]
+    fn machine_airborne_and_landing() {
+        let params = LocomotionParams::default();
+        let mut m = LocomotionStateMachine::default();
+        let mut s = sample(Vec2::new(0.0, 5.0), Some(1.0));
+        s.ground = GroundPhase::Airborne;
+        assert_eq!(m.update(&s, &params), LocomotionAnim::Jump);
+        s.ground = GroundPhase::Landing;
+        assert_eq!(m.update(&s, &params), LocomotionAnim::Run);
+        s.velocity_body = Vec2::ZERO;
+        s.move_force = None;
+        assert_eq!(m.update(&s, &params), LocomotionAnim::Land);
+    }
+
+    #[test]
+    fn blend_point_follows_state() {
+        let velocity = Vec2::new(3.0, 4.0);
+        assert_eq!(ground_blend_point(LocomotionAnim::Idle, velocity, 1.0), Vec2::ZERO);
+        assert_eq!(ground_blend_point(LocomotionAnim::TurnLeft, velocity, 0.0), Vec2::ZERO);
+        // Walking and running only move along the forward axis...
+        assert_eq!(ground_blend_point(LocomotionAnim::Run, velocity, 0.0), Vec2::new(0.0, 5.0));
+        // ...and strafing sweeps over to the sideways velocity.
+        assert_eq!(ground_blend_point(LocomotionAnim::StrafeRunRight, velocity, 1.0), velocity);
+        let halfway = ground_blend_point(LocomotionAnim::StrafeRunRight, velocity, 0.5);
+        assert!((halfway - Vec2::new(1.5, 4.5)).length() < 1e-5);
+    }
 }
@@ -0,0 +1,208 @@
+//! Look input → camera rotation: per-source sensitivity and response curves, inversion and
+//! exponential smoothing (tests cover the pure conversion).
+//!
+//! Each [`LookSource`] reports in its own unit, so one setting never has to fit all devices:
+//! mouse in pixels of motion, touch in fractions of the viewport width (independent of screen
+//! size and DPI) and gamepad as stick deflection in \[-1, 1\] (a rate, scaled by frame time).
+
+use bevy::math::Vec2;
+use bevy::prelude::Resource;
+
+use crate::utils::is_non_mobile;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LookSource {
+    Mouse,
+    Touch,
+    Gamepad,
+}
+
+/// Response curve applied to one source before inversion.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LookCurve {
+    Linear,
+    /// `magnitude^exponent` of the raw input; meant for sticks, where `exponent > 1` gives
+    /// fine aim near the centre and full speed at the rim.
+    Power {
+        exponent: f32,
+    },
+    /// Fast motion turns further: the linear result is multiplied by
+    /// `1 + gain * speed` (rad/s), capped at `max_multiplier`.
+    Accelerated {
+        gain: f32,
+        max_multiplier: f32,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LookSourceSettings {
+    /// Radians per raw unit (per unit per second for [`LookSource::Gamepad`]).
+    pub sensitivity: f32,
+    pub curve: LookCurve,
+}
+
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct LookSettings {
+    pub mouse: LookSourceSettings,
+    pub touch: LookSourceSettings,
+    pub gamepad: LookSourceSettings,
+    pub invert_x: bool,
+    pub invert_y: bool,
+    /// Time constant (seconds) of the exponential smoothing toward the look target;
+    /// `0.0` applies input immediately.
+    pub smoothing_secs: f32,
+    /// Lock and hide the cursor on click and look with raw mouse motion (desktop only).
+    pub grab_cursor: bool,
+}
+
+impl Default for LookSettings {
+    fn default() -> Self {
+        Self {
+            mouse: LookSourceSettings {
+                sensitivity: 0.0025,
+                curve: LookCurve::Linear,
+            },
+            touch: LookSourceSettings {
+                sensitivity: 5.0,
+                curve: LookCurve::Linear,
+            },
+            gamepad: LookSourceSettings {
+                sensitivity: 3.0,
+                curve: LookCurve::Power { exponent: 2.0 },
+            },
+            invert_x: false,
+            invert_y: false,
+            smoothing_secs: 0.03,
+            grab_cursor: is_non_mobile(),
+        }
+    }
+}
+
+impl LookSettings {
+    pub fn source(&self, source: LookSource) -> &LookSourceSettings {
+        match source {
+            LookSource::Mouse => &self.mouse,
+            LookSource::Touch => &self.touch,
+            LookSource::Gamepad => &self.gamepad,
+        }
+    }
+
+    /// Yaw (`x`) / pitch (`y`) change in radians for one input sample taken over `dt`
+    /// seconds; signs follow the raw input (right / down positive) unless inverted.
+    pub fn look_radians(&self, source: LookSource, raw: Vec2, dt: f32) -> Vec2 {
+        let settings = self.source(source);
+        let linear = raw * settings.sensitivity;
+        let mut delta = match settings.curve {
+            LookCurve::Linear => linear,
+            LookCurve::Power { exponent } => {
+                let magnitude = raw.length();
+                if magnitude <= f32::EPSILON {
+                    Vec2::ZERO
+                } else {
+                    raw / magnitude * magnitude.powf(exponent) * settings.sensitivity
+                }
+            }
+            LookCurve::Accelerated {
+                gain,
+                max_multiplier,
+            } => {
+                let speed = match source {
+                    LookSource::Gamepad => linear.length(),
+                    _ if dt > 0.0 => linear.length() / dt,
+                    _ => 0.0,
+                };
+                linear * (1.0 + gain * speed).min(max_multiplier)
+            }
+        };
+        if source == LookSource::Gamepad {
+            delta *= dt;
+        }
+        if self.invert_x {
+            delta.x = -delta.x;
+        }
+        if self.invert_y {
+            delta.y = -delta.y;
+        }
+        delta
+    }
+}
+
+/// Fraction of the remaining distance to cover this frame for exponential smoothing with
+/// time constant `smoothing_secs`; frame-rate independent and `1.0` when smoothing is off.
+pub fn smoothing_factor(dt: f32, smoothing_secs: f32) -> f32 {
+    if smoothing_secs <= 0.0 {
+        return 1.0;
+    }
+    1.0 - (-dt / smoothing_secs).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-5, "{a} != {b}");
+    }
+
+    fn settings() -> LookSettings {
+        LookSettings {
+            smoothing_secs: 0.0,
+            grab_cursor: false,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn sources_use_their_own_sensitivity() {
+        let s = settings();
+        let mouse = s.look_radians(LookSource::Mouse, Vec2::new(100.0, 0.0), 0.016);
+        assert_close(mouse.x, 100.0 * s.mouse.sensitivity);
+        let touch = s.look_radians(LookSource::Touch, Vec2::new(0.0, 0.1), 0.016);
+        assert_close(touch.y, 0.1 * s.touch.sensitivity);
+    }
+
+    #[test]
+    fn gamepad_is_a_rate_with_response_curve() {
+        let s = settings();
+        // Half deflection with exponent 2 → quarter speed, over half a second.
+        let d = s.look_radians(LookSource::Gamepad, Vec2::new(0.5, 0.0), 0.5);
+        assert_close(d.x, 0.25 * s.gamepad.sensitivity * 0.5);
+        assert_eq!(
+            s.look_radians(LookSource::Gamepad, Vec2::ZERO, 0.5),
+            Vec2::ZERO
+        );
+    }
+
+    #[test]
+    fn inversion_flips_each_axis() {
+        let s = LookSettings {
+            invert_y: true,
+            ..settings()
+        };
+        let d = s.look_radians(LookSource::Mouse, Vec2::new(10.0, 10.0), 0.016);
+        assert!(d.x > 0.0);
+        assert!(d.y < 0.0);
+    }
+
+    #[test]
+    fn acceleration_boosts_fast_motion_up_to_cap() {
+        let mut s = settings();
+        s.mouse.curve = LookCurve::Accelerated {
+            gain: 0.5,
+            max_multiplier: 2.0,
+        };
+        let slow = s.look_radians(LookSource::Mouse, Vec2::new(1.0, 0.0), 0.1);
+        assert_close(slow.x, 0.0025 * (1.0 + 0.5 * 0.025));
+        let fast = s.look_radians(LookSource::Mouse, Vec2::new(1000.0, 0.0), 0.01);
+        assert_close(fast.x, 2.5 * 2.0);
+    }
+
+    #[test]
+    fn smoothing_is_frame_rate_independent() {
+        assert_eq!(smoothing_factor(0.016, 0.0), 1.0);
+        let one = smoothing_factor(0.02, 0.05);
+        let two_halves = 1.0 - (1.0 - smoothing_factor(0.01, 0.05)).powi(2);
+        assert_close(one, two_halves);
+        assert!(one > 0.0 && one < 1.0);
+    }
+}
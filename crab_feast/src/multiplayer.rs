@@ -0,0 +1,564 @@
+//! Networked play on top of the library's `NetPlugin`: the server simulates every character
+//! and replicates it, clients send their [`ControlIntent`] and display what comes back.
+//!
+//! The server plays too, as [`NetId::HOST`], and spawns one character per connected client.
+//! Each character replicates its `Transform`, [`CharacterBodyYaw`], owner ([`NetCharacter`])
+//! and animation inputs ([`NetLocomotion`]). Clients attach the rig to every replicated
+//! character as a [`Replica`] and take control of the one they own, so camera and input work
+//! as offline. The server's simulation is authoritative; clients predict their own character
+//! (see [`crate::prediction`]) and show the others [`INTERPOLATION_DELAY_SECS`] in the past,
+//! blended between snapshots.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use bevy_replicon::prelude::*;
+use crab_feast_library::net::{
+    Interpolate, InterpolationPlugin, LocalNetId, NetId, NetPlugin, SnapshotBuffer,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::character::{insert_character, spawn_character, Character, CharacterManifest};
+use crate::input::{
+    CharacterBodyYaw, ControlIntent, ControlSystems, Controlled, JumpController, JumpInput,
+    LocalPlayer, LocallyControlled, MovementController, MovementFacingMode,
+};
+use crate::locomotion::GroundPhase;
+use crate::prediction::{apply_prediction, ControlInput, NetMotion, Prediction, PredictionPlugin};
+use crate::root_motion::RootMotionMode;
+use crate::{GameAssets, GameState};
+
+/// Where the server drops the characters of newly connected clients.
+const CLIENT_SPAWN: Vec3 = Vec3::new(2.0, 2.0, 2.0);
+/// How far behind the server other characters are shown: about three ticks at the default
+/// replication rate, so one late or lost update does not starve the buffer.
+pub const INTERPOLATION_DELAY_SECS: f32 = 0.1;
+/// How many of the newest unacknowledged inputs every [`ControlMessage`] carries, so a few
+/// messages in a row can be lost on the unreliable channel without losing input.
+const REDUNDANT_INPUTS: usize = 4;
+/// Inputs the server keeps per client, about half a second at 60 Hz; a client running
+/// further ahead loses its oldest ones.
+const MAX_QUEUED_INPUTS: usize = 32;
+/// Most server time a client's inputs can fall behind and still be made up.
+const MAX_INPUT_DEBT_SECS: f32 = 0.1;
+
+pub struct MultiplayerPlugin;
+
+impl Plugin for MultiplayerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            NetPlugin,
+            PredictionPlugin,
+            InterpolationPlugin::<CharacterBodyYaw>::default(),
+        ));
+        app.replicate::<Transform>()
+            .replicate::<CharacterBodyYaw>()
+            .replicate::<NetCharacter>()
+            .replicate::<NetLocomotion>()
+            .replicate::<NetMotion>()
+            .add_client_message::<ControlMessage>(Channel::Unreliable)
+            .add_observer(despawn_client_character)
+            .add_systems(
+                Update,
+                (
+                    (
+                        replicate_server_characters,
+                        spawn_client_characters,
+                        apply_client_controls.before(ControlSystems::Motion),
+                    )
+                        .run_if(in_state(ServerState::Running)),
+                    (
+                        attach_replicas,
+                        send_control
+                            .after(ControlSystems::Intent)
+                            .before(ControlSystems::Motion),
+                    )
+                        .run_if(in_state(ClientState::Connected)),
+                )
+                    .run_if(in_state(GameState::Game)),
+            )
+            .add_systems(
+                PostUpdate,
+                record_net_motion
+                    .after(PhysicsSet::Writeback)
+                    .run_if(in_state(ServerState::Running)),
+            );
+    }
+}
+
+/// A character the server replicates; `owner` is the player controlling it, `None` for NPCs.
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct NetCharacter {
+    pub owner: Option<NetId>,
+}
+
+/// What the server's locomotion animation was driven by this frame besides velocity, so
+/// clients animate replicas without simulating them. Written by the animation system on the
+/// server; clients take the velocity from interpolation or prediction.
+#[derive(Component, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct NetLocomotion {
+    pub ground: GroundPhase,
+    pub facing: MovementFacingMode,
+    pub move_force: Option<f32>,
+    /// Clip slot layered over the blend space (jump, turn, debug clip).
+    pub override_clip: Option<String>,
+}
+
+/// A client's view of a server character: moved by replication only, animated from
+/// [`NetLocomotion`].
+#[derive(Component, Debug, Default)]
+pub struct Replica;
+
+/// Turns the short way round, like the body does.
+impl Interpolate for CharacterBodyYaw {
+    fn interpolate(from: &Self, to: &Self, t: f32) -> Self {
+        let turn = (to.0 - from.0 + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU)
+            - std::f32::consts::PI;
+        Self(from.0 + turn * t)
+    }
+}
+
+/// The locally controlled character's latest inputs, sent by clients every frame.
+#[derive(Message, Serialize, Deserialize, Clone, Debug)]
+struct ControlMessage {
+    /// This frame's input and up to [`REDUNDANT_INPUTS`] `- 1` before it, oldest first.
+    inputs: Vec<ControlInput>,
+}
+
+/// On a server's connected-client entity: the character that client controls.
+#[derive(Component)]
+struct ClientCharacter(Entity);
+
+/// On a server's client character: inputs received but not simulated yet.
+#[derive(Component, Debug, Default)]
+struct InputQueue {
+    inputs: VecDeque<ControlInput>,
+    /// Newest sequence number received; repeated and late inputs are dropped.
+    received: u32,
+    /// Server time the applied inputs have not covered yet, in seconds.
+    budget: f32,
+}
+
+impl InputQueue {
+    fn receive(&mut self, inputs: &[ControlInput]) {
+        for input in inputs {
+            if input.seq <= self.received {
+                continue;
+            }
+            self.received = input.seq;
+            if self.inputs.len() == MAX_QUEUED_INPUTS {
+                self.inputs.pop_front();
+            }
+            self.inputs.push_back(*input);
+        }
+    }
+
+    /// The input to simulate for a server step of `dt`, `None` when nothing is queued.
+    ///
+    /// Takes one input per step, and more while their client frame times fit in the step,
+    /// so a client running at a higher rate than the server does not fall behind. Merged
+    /// inputs keep the last one's intent and sequence number, and any jump among them.
+    fn take(&mut self, dt: f32) -> Option<ControlInput> {
+        // Steps without input are made up once inputs arrive again, so a late message
+        // does not leave its client a step behind for good.
+        self.budget = (self.budget + dt).min(MAX_INPUT_DEBT_SECS);
+        if self.inputs.is_empty() {
+            return None;
+        }
+        let mut taken: Option<ControlInput> = None;
+        while let Some(mut input) = self.inputs.front().copied() {
+            // Frame times rarely add up exactly.
+            if taken.is_some() && input.dt > self.budget + f32::EPSILON {
+                break;
+            }
+            self.inputs.pop_front();
+            self.budget -= input.dt;
+            if taken.is_some_and(|taken| taken.intent.jump == JumpInput::Activated) {
+                input.intent.jump = JumpInput::Activated;
+            }
+            taken = Some(input);
+        }
+        taken
+    }
+}
+
+/// Replicates the characters the server spawned itself: the host's and the NPCs.
+fn replicate_server_characters(
+    mut commands: Commands,
+    characters: Query<(Entity, Option<&Controlled>), (With<Character>, Without<NetCharacter>)>,
+    local_player: Res<LocalPlayer>,
+) {
+    for (entity, controlled) in &characters {
+        let owner = controlled
+            .filter(|controlled| controlled.by == local_player.0)
+            .map(|_| NetId::HOST);
+        commands.entity(entity).insert((
+            Replicated,
+            NetCharacter { owner },
+            NetLocomotion::default(),
+        ));
+    }
+}
+
+/// All networked characters use the `amy` manifest for now.
+fn spawn_client_characters(
+    mut commands: Commands,
+    game_assets: Res<GameAssets>,
+    manifests: Res<Assets<CharacterManifest>>,
+    mut graphs: ResMut<Assets<AnimationGraph>>,
+    clients: Query<(Entity, &NetworkId), (With<AuthorizedClient>, Without<ClientCharacter>)>,
+) {
+    for (client, network_id) in &clients {
+        let Some(character) = spawn_character(
+            &mut commands,
+            &manifests,
+            &mut graphs,
+            &game_assets.amy,
+            Transform::from_translation(CLIENT_SPAWN),
+        ) else {
+            return;
+        };
+        commands.entity(character).insert((
+            Replicated,
+            NetCharacter {
+                owner: Some(NetId(network_id.get())),
+            },
+            NetLocomotion::default(),
+            NetMotion::default(),
+            InputQueue::default(),
+        ));
+        commands.entity(client).insert(ClientCharacter(character));
+        info!("Spawned {character} for client {}", network_id.get());
+    }
+}
+
+/// Removing the client entity on disconnect takes its character with it.
+fn despawn_client_character(
+    trigger: On<Remove, ClientCharacter>,
+    mut commands: Commands,
+    clients: Query<&ClientCharacter>,
+) {
+    if let Ok(character) = clients.get(trigger.event_target()) {
+        commands.entity(character.0).try_despawn();
+    }
+}
+
+/// Queues the inputs clients sent and applies the next one of each client's character,
+/// acknowledging only what is simulated this frame. A character whose queue ran dry keeps
+/// its last intent.
+fn apply_client_controls(
+    time: Res<Time>,
+    mut messages: MessageReader<FromClient<ControlMessage>>,
+    clients: Query<&ClientCharacter>,
+    mut characters: Query<(&mut ControlIntent, &mut NetMotion, &mut InputQueue)>,
+) {
+    for FromClient { client_id, message } in messages.read() {
+        // The host's own character reads its input directly.
+        let ClientId::Client(client) = *client_id else {
+            continue;
+        };
+        if let Some((_, _, mut queue)) = clients
+            .get(client)
+            .ok()
+            .and_then(|character| characters.get_mut(character.0).ok())
+        {
+            queue.receive(&message.inputs);
+        }
+    }
+    let dt = time.delta_secs();
+    for (mut intent, mut motion, mut queue) in &mut characters {
+        let Some(input) = queue.take(dt) else {
+            continue;
+        };
+        // A jump edge not yet consumed by `jump_system` survives later inputs.
+        let jump = intent.jump;
+        *intent = input.intent;
+        if jump == JumpInput::Activated {
+            intent.jump = JumpInput::Activated;
+        }
+        motion.ack = input.seq;
+    }
+}
+
+/// The state client characters reached after physics, for their owners to reconcile with.
+fn record_net_motion(
+    mut characters: Query<(&Transform, &Velocity, &CharacterBodyYaw, &mut NetMotion)>,
+) {
+    for (transform, velocity, yaw, mut motion) in &mut characters {
+        let ack = motion.ack;
+        motion.set_if_neq(NetMotion {
+            ack,
+            position: transform.translation,
+            velocity: velocity.linvel,
+            yaw: yaw.0,
+        });
+    }
+}
+
+/// Gives replicated characters their rig and collider, and local control of our own.
+fn attach_replicas(
+    mut commands: Commands,
+    game_assets: Res<GameAssets>,
+    manifests: Res<Assets<CharacterManifest>>,
+    mut graphs: ResMut<Assets<AnimationGraph>>,
+    local_id: Res<LocalNetId>,
+    local_player: Res<LocalPlayer>,
+    replicas: Query<
+        (Entity, &NetCharacter, &Transform, Option<&CharacterBodyYaw>),
+        Without<Character>,
+    >,
+) {
+    let Some(manifest) = manifests.get(&game_assets.amy) else {
+        return;
+    };
+    for (entity, character, transform, yaw) in &replicas {
+        let mut body = commands.entity(entity);
+        insert_character(&mut body, manifest, &game_assets.amy, &mut graphs);
+        // The server owns the simulation: no movement, jumping or root-motion drive here.
+        body.insert((
+            Replica,
+            RigidBody::KinematicPositionBased,
+            RootMotionMode::Compensate,
+        ))
+        .remove::<(
+            MovementController,
+            JumpController,
+            KinematicCharacterController,
+        )>();
+        if character.owner == Some(local_id.0) {
+            let yaw = yaw.map_or(0.0, |yaw| yaw.0);
+            body.insert((
+                Controlled { by: local_player.0 },
+                Prediction::new(transform.translation, yaw),
+            ));
+        } else {
+            body.insert((
+                SnapshotBuffer::<Transform>::new(INTERPOLATION_DELAY_SECS),
+                SnapshotBuffer::<CharacterBodyYaw>::new(INTERPOLATION_DELAY_SECS),
+            ));
+        }
+    }
+}
+
+/// Predicts and sends this frame's intent with the few unacknowledged ones before it, then
+/// consumes its jump edge, which no `jump_system` reads on a replica.
+fn send_control(
+    time: Res<Time>,
+    mut messages: MessageWriter<ControlMessage>,
+    mut characters: Query<
+        (
+            &mut ControlIntent,
+            &mut Prediction,
+            &mut Transform,
+            &mut CharacterBodyYaw,
+            &NetLocomotion,
+        ),
+        (With<LocallyControlled>, With<Replica>),
+    >,
+) {
+    let Ok((mut intent, mut prediction, mut transform, mut yaw, locomotion)) =
+        characters.single_mut()
+    else {
+        return;
+    };
+    let dt = time.delta_secs();
+    let airborne = locomotion.ground == GroundPhase::Airborne;
+    prediction.predict(*intent, dt, airborne);
+    messages.write(ControlMessage {
+        inputs: prediction.latest_inputs(REDUNDANT_INPUTS).collect(),
+    });
+    prediction.smooth(dt);
+    apply_prediction(&prediction, &mut transform, &mut yaw);
+    if intent.jump == JumpInput::Activated {
+        intent.jump = JumpInput::Idle;
+    }
+}
+
+#[cfg(test)]
+mod multiplayer_tests {
+    use std::time::Duration;
+
+    use bevy::ecs::message::Messages;
+
+    use crate::input::MovementInput;
+    use crate::prediction::step_motion;
+
+    use super::*;
+
+    const CLIENT_DT: f32 = 1.0 / 60.0;
+    const SERVER_DT: f32 = 1.0 / 30.0;
+    const LATENCY: f32 = 0.1;
+    /// One in this many control messages is lost.
+    const LOST_EVERY: usize = 5;
+
+    fn intent_at(t: f32) -> ControlIntent {
+        let direction = match t {
+            t if t < 1.0 => Vec2::NEG_Y,
+            t if t < 1.5 => Vec2::X,
+            _ => return ControlIntent::default(),
+        };
+        ControlIntent {
+            movement: MovementInput::Activated {
+                direction,
+                force: 1.0,
+            },
+            reference_yaw: Some(0.0),
+            ..default()
+        }
+    }
+
+    fn input(seq: u32) -> ControlInput {
+        ControlInput {
+            seq,
+            intent: ControlIntent::default(),
+            dt: CLIENT_DT,
+        }
+    }
+
+    /// Stands in for the server's physics: moves characters like `movement_system` and
+    /// consumes the jump edge like `jump_system`.
+    fn simulate_motion(
+        time: Res<Time>,
+        mut characters: Query<(&mut ControlIntent, &mut NetMotion)>,
+    ) {
+        let controller = MovementController::default();
+        for (mut intent, mut motion) in &mut characters {
+            step_motion(&mut motion, &intent, time.delta_secs(), false, &controller);
+            intent.jump = JumpInput::Idle;
+        }
+    }
+
+    #[test]
+    fn test_input_queue_skips_repeats_and_keeps_pace() {
+        let mut queue = InputQueue::default();
+        queue.receive(&[input(1), input(2)]);
+        queue.receive(&[input(1), input(2), input(3)]);
+        // A server step two client frames long takes two inputs.
+        assert_eq!(queue.take(SERVER_DT).map(|input| input.seq), Some(2));
+        assert_eq!(queue.take(SERVER_DT).map(|input| input.seq), Some(3));
+        assert_eq!(queue.take(SERVER_DT), None);
+
+        let mut jump = input(4);
+        jump.intent.jump = JumpInput::Activated;
+        queue.receive(&[jump, input(5)]);
+        let merged = queue.take(SERVER_DT).unwrap();
+        assert_eq!(merged.seq, 5);
+        assert_eq!(merged.intent.jump, JumpInput::Activated);
+    }
+
+    /// A server world with one client character, running the real `apply_client_controls`.
+    struct TestServer {
+        world: World,
+        schedule: Schedule,
+        client: Entity,
+        character: Entity,
+    }
+
+    impl TestServer {
+        fn new() -> Self {
+            let mut world = World::new();
+            world.init_resource::<Messages<FromClient<ControlMessage>>>();
+            world.init_resource::<Time>();
+            let character = world
+                .spawn((
+                    ControlIntent::default(),
+                    NetMotion::default(),
+                    InputQueue::default(),
+                ))
+                .id();
+            let client = world.spawn(ClientCharacter(character)).id();
+            let mut schedule = Schedule::default();
+            schedule.add_systems((apply_client_controls, simulate_motion).chain());
+            Self {
+                world,
+                schedule,
+                client,
+                character,
+            }
+        }
+
+        /// Delivers `messages`, runs one frame and returns what the client would be sent.
+        fn step(&mut self, messages: impl IntoIterator<Item = ControlMessage>) -> NetMotion {
+            for message in messages {
+                self.world.write_message(FromClient {
+                    client_id: ClientId::Client(self.client),
+                    message,
+                });
+            }
+            self.world
+                .resource_mut::<Time>()
+                .advance_by(Duration::from_secs_f32(SERVER_DT));
+            self.schedule.run(&mut self.world);
+            self.world
+                .resource_mut::<Messages<FromClient<ControlMessage>>>()
+                .update();
+            *self.world.get::<NetMotion>(self.character).unwrap()
+        }
+    }
+
+    /// Client at 60 Hz, server at 30 Hz, 100 ms each way and one message in five lost:
+    /// `apply_client_controls` acknowledges every input in order, corrections stay within a
+    /// normal frame's movement and the prediction ends on the server's position.
+    #[test]
+    fn test_prediction_converges_with_the_server() {
+        let mut server = TestServer::new();
+        let mut prediction = Prediction::new(Vec3::ZERO, 0.0);
+        let mut to_server: VecDeque<(f32, ControlMessage)> = VecDeque::new();
+        let mut to_client: VecDeque<(f32, NetMotion)> = VecDeque::new();
+        let mut next_server_frame = 0.0;
+        let mut shown = Vec3::ZERO;
+        let mut largest_step: f32 = 0.0;
+        let mut motion = NetMotion::default();
+
+        for frame in 0..240 {
+            let now = frame as f32 * CLIENT_DT;
+            while to_client.front().is_some_and(|(at, _)| *at <= now) {
+                let (_, authoritative) = to_client.pop_front().unwrap();
+                prediction.reconcile(&authoritative);
+            }
+            let seq = prediction.predict(intent_at(now), CLIENT_DT, false);
+            if frame % LOST_EVERY != 2 {
+                let message = ControlMessage {
+                    inputs: prediction.latest_inputs(REDUNDANT_INPUTS).collect(),
+                };
+                to_server.push_back((now + LATENCY, message));
+            }
+            prediction.smooth(CLIENT_DT);
+
+            let (position, _) = prediction.visual();
+            largest_step = largest_step.max(position.distance(shown));
+            shown = position;
+
+            while next_server_frame <= now {
+                let arrived = to_server
+                    .iter()
+                    .take_while(|(at, _)| *at <= next_server_frame)
+                    .count();
+                let stepped = server.step(to_server.drain(..arrived).map(|(_, message)| message));
+                assert!(stepped.ack >= motion.ack && stepped.ack <= seq);
+                motion = stepped;
+                to_client.push_back((next_server_frame + LATENCY, motion));
+                next_server_frame += SERVER_DT;
+            }
+        }
+
+        let normal_step = MovementController::default().speed * CLIENT_DT;
+        assert!(
+            largest_step < normal_step * 1.5,
+            "visible snap of {largest_step} m"
+        );
+        // Only the inputs still in flight, about one round trip's worth.
+        let round_trip = ((2.0 * LATENCY + SERVER_DT) / CLIENT_DT).ceil() as usize;
+        assert!(prediction.pending_len() <= round_trip);
+        assert!(prediction.visual().0.distance(motion.position) < 0.01);
+
+        // Once the messages in flight arrive, every input gets applied.
+        let last_seq = prediction.latest_inputs(1).next().unwrap().seq;
+        server.step(to_server.drain(..).map(|(_, message)| message));
+        for _ in 0..20 {
+            motion = server.step([]);
+        }
+        assert_eq!(motion.ack, last_seq);
+    }
+}
@@ -0,0 +1,110 @@
+//! Feeds the library's [`NavPlugin`] a [`NavGrid`] rasterized from static Rapier colliders.
+//!
+//! The level is treated as flat: a fixed collider blocks walking when its world AABB
+//! overlaps the band between [`STEP_HEIGHT`] and [`AGENT_HEIGHT`], and its XZ footprint is
+//! grown by [`AGENT_CLEARANCE`]. Map bounds are the XZ union of all static colliders, so the
+//! floor defines the navigable area.
+
+use std::sync::Arc;
+
+use bevy::math::bounding::{Aabb2d, BoundingVolume};
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use crab_feast_library::nav::{NavGrid, NavPlugin, WalkabilityMap};
+
+use crate::GameState;
+
+const CELL_SIZE: f32 = 0.5;
+/// Slightly more than the character capsule radius.
+const AGENT_CLEARANCE: f32 = 0.3;
+/// Obstacles lower than this are stepped over (and floors ignored).
+const STEP_HEIGHT: f32 = 0.3;
+/// Obstacles starting above this are walked under.
+const AGENT_HEIGHT: f32 = 1.8;
+
+pub struct NavigationPlugin;
+
+impl Plugin for NavigationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(NavPlugin).add_systems(
+            PostUpdate,
+            rebuild_nav_grid
+                .after(PhysicsSet::Writeback)
+                .run_if(in_state(GameState::Game)),
+        );
+    }
+}
+
+/// Rebuilds the grid whenever a static collider is added or moved. Rapier registers new
+/// colliders during the physics step, so a rebuild waits until every one is known to it.
+fn rebuild_nav_grid(
+    mut commands: Commands,
+    rapier_context: ReadRapierContext,
+    colliders: Query<
+        (
+            Entity,
+            Option<&RigidBody>,
+            Ref<Collider>,
+            Ref<GlobalTransform>,
+        ),
+        Without<Sensor>,
+    >,
+    mut dirty: Local<bool>,
+) {
+    let statics = || {
+        colliders
+            .iter()
+            .filter(|(_, body, ..)| body.is_none_or(|body| *body == RigidBody::Fixed))
+    };
+    if statics().any(|(_, _, collider, transform)| collider.is_changed() || transform.is_changed())
+    {
+        *dirty = true;
+    }
+    if !*dirty {
+        return;
+    }
+    let Ok(ctx) = rapier_context.single() else {
+        return;
+    };
+
+    let mut bounds: Option<Aabb2d> = None;
+    let mut footprints = Vec::new();
+    for (entity, ..) in statics() {
+        let Some(collider) = ctx
+            .colliders
+            .entity2collider()
+            .get(&entity)
+            .and_then(|handle| ctx.colliders.colliders.get(*handle))
+        else {
+            // Not in the physics world yet; try again next frame.
+            return;
+        };
+        let aabb = collider.compute_aabb();
+        let footprint = Aabb2d {
+            min: Vec2::new(aabb.mins.x, aabb.mins.z),
+            max: Vec2::new(aabb.maxs.x, aabb.maxs.z),
+        };
+        bounds = Some(bounds.map_or(footprint, |bounds| bounds.merge(&footprint)));
+        if aabb.maxs.y > STEP_HEIGHT && aabb.mins.y < AGENT_HEIGHT {
+            footprints.push(footprint);
+        }
+    }
+    *dirty = false;
+    let Some(bounds) = bounds else {
+        return;
+    };
+
+    let map = match WalkabilityMap::from_footprints(bounds, CELL_SIZE, AGENT_CLEARANCE, footprints)
+    {
+        Ok(map) => map,
+        Err(error) => {
+            warn!("Navigation grid not rebuilt: {error}");
+            return;
+        }
+    };
+    debug!(
+        "Navigation grid rebuilt: {} cells",
+        map.size().x * map.size().y
+    );
+    commands.insert_resource(NavGrid(Arc::new(map)));
+}
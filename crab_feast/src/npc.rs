@@ -0,0 +1,317 @@
+//! AI-driven characters: an [`Npc`] turns its [`SteeringBehavior`] into the same
+//! [`ControlIntent`] a player's input produces (world direction + force), so NPCs move through
+//! `MovementController`, turn via `CharacterBodyYaw` and get locomotion animation and
+//! root-motion compensation exactly like the player.
+//!
+//! Spawn a body with `spawn_character` and insert an [`Npc`] instead of `Controlled`.
+//! Obstacle avoidance probes Rapier colliders with three horizontal feeler rays, and
+//! separation keeps NPCs clear of nearby characters;
+//! [`SteeringBehavior::GoTo`] plans a route on the navigation grid first.
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use rand::Rng;
+
+use crab_feast_library::nav::{PathRequest, PathResult};
+
+use crate::input::{CharacterBodyYaw, ControlIntent, MovementFacingMode, MovementInput};
+use crate::steering::{
+    arrive, avoid_obstacles, flee, follow_path, leash, seek, separate, wander, FeelerHit,
+};
+
+/// Maximum change of the wander angle, in radians per second.
+const WANDER_JITTER: f32 = 3.0;
+/// Side feelers are shorter than the centre one so narrow gaps stay passable.
+const SIDE_FEELER_SCALE: f32 = 0.7;
+
+pub struct NpcPlugin;
+
+impl Plugin for NpcPlugin {
+    fn build(&self, app: &mut App) {
+        // Written in PreUpdate like device input, before ControlInputPlugin consumes intents.
+        app.add_systems(
+            PreUpdate,
+            (request_npc_paths, apply_npc_paths, steer_npcs).chain(),
+        );
+    }
+}
+
+/// Where a behavior points: a fixed spot or another entity's current position.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SteerTarget {
+    Point(Vec3),
+    Entity(Entity),
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum SteeringBehavior {
+    #[default]
+    Idle,
+    /// Meander, pulled back toward `home` beyond `leash` metres.
+    Wander {
+        home: Vec3,
+        leash: f32,
+    },
+    Seek(SteerTarget),
+    /// Run from `threat` while it is closer than `panic_distance`.
+    Flee {
+        threat: SteerTarget,
+        panic_distance: f32,
+    },
+    /// Seek, slowing down inside `slowing_radius` and stopping at the target.
+    Arrive {
+        target: SteerTarget,
+        slowing_radius: f32,
+    },
+    /// Walk the waypoints in order; open paths stop at the last one.
+    FollowPath {
+        waypoints: Vec<Vec3>,
+        looped: bool,
+    },
+    /// Plan a path around static obstacles, then follow it. Stands still while planning
+    /// and goes [`Idle`](Self::Idle) when there is no route.
+    GoTo(Vec3),
+}
+
+/// Feeler-ray obstacle avoidance and neighbour separation settings.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ObstacleAvoidance {
+    /// Length of the centre feeler in metres; `0.0` disables avoidance.
+    pub lookahead: f32,
+    /// Angle of the two side feelers from the movement direction, in radians.
+    pub feeler_angle: f32,
+    /// How strongly a touching obstacle outweighs the desired direction.
+    pub strength: f32,
+    /// Other characters closer than this, in metres, push the NPC aside; `0.0` disables
+    /// separation.
+    pub separation_radius: f32,
+}
+
+impl Default for ObstacleAvoidance {
+    fn default() -> Self {
+        Self {
+            lookahead: 2.0,
+            feeler_angle: 35f32.to_radians(),
+            strength: 1.5,
+            separation_radius: 1.2,
+        }
+    }
+}
+
+/// Marks a character body as AI-driven; its [`ControlIntent`] is rewritten every frame.
+#[derive(Component, Clone, Debug)]
+#[require(ControlIntent)]
+pub struct Npc {
+    behavior: SteeringBehavior,
+    /// Intent force at full push, in \[0, 1\] like stick deflection
+    /// (scaled by `MovementController::speed`).
+    pub max_force: f32,
+    pub avoidance: ObstacleAvoidance,
+    wander_angle: f32,
+    next_waypoint: usize,
+    route_requested: bool,
+}
+
+impl Npc {
+    pub fn new(behavior: SteeringBehavior) -> Self {
+        Self {
+            behavior,
+            max_force: 0.4,
+            avoidance: ObstacleAvoidance::default(),
+            wander_angle: 0.0,
+            next_waypoint: 0,
+            route_requested: false,
+        }
+    }
+
+    pub fn with_max_force(mut self, max_force: f32) -> Self {
+        self.max_force = max_force;
+        self
+    }
+
+    pub fn behavior(&self) -> &SteeringBehavior {
+        &self.behavior
+    }
+
+    /// Switches behavior, restarting paths from their first waypoint.
+    pub fn set_behavior(&mut self, behavior: SteeringBehavior) {
+        self.behavior = behavior;
+        self.wander_angle = 0.0;
+        self.next_waypoint = 0;
+        self.route_requested = false;
+    }
+}
+
+fn xz(v: Vec3) -> Vec2 {
+    Vec2::new(v.x, v.z)
+}
+
+fn request_npc_paths(
+    mut commands: Commands,
+    mut npcs: Query<(Entity, &mut Npc, &GlobalTransform)>,
+) {
+    for (entity, mut npc, transform) in &mut npcs {
+        let SteeringBehavior::GoTo(goal) = npc.behavior else {
+            continue;
+        };
+        if npc.route_requested {
+            continue;
+        }
+        npc.route_requested = true;
+        commands
+            .entity(entity)
+            .remove::<PathResult>()
+            .insert(PathRequest::new(xz(transform.translation()), xz(goal)));
+    }
+}
+
+/// Turns a finished route into [`SteeringBehavior::FollowPath`].
+fn apply_npc_paths(
+    mut commands: Commands,
+    mut npcs: Query<(Entity, &mut Npc, &PathResult), Without<PathRequest>>,
+) {
+    for (entity, mut npc, result) in &mut npcs {
+        let SteeringBehavior::GoTo(goal) = npc.behavior else {
+            continue;
+        };
+        if !npc.route_requested {
+            continue;
+        }
+        commands.entity(entity).remove::<PathResult>();
+        match result {
+            PathResult::Found(points) => npc.set_behavior(SteeringBehavior::FollowPath {
+                waypoints: points.iter().map(|p| Vec3::new(p.x, goal.y, p.y)).collect(),
+                looped: false,
+            }),
+            PathResult::NotFound => {
+                warn!("No route for {entity} to {goal}");
+                npc.set_behavior(SteeringBehavior::Idle);
+            }
+        }
+    }
+}
+
+fn steer_npcs(
+    time: Res<Time>,
+    rapier_context: ReadRapierContext,
+    positions: Query<&GlobalTransform>,
+    characters: Query<(Entity, &GlobalTransform), With<CharacterBodyYaw>>,
+    mut npcs: Query<(Entity, &mut Npc, &mut ControlIntent, &CharacterBodyYaw)>,
+) {
+    let dt = time.delta_secs();
+    let ctx = rapier_context.single().ok();
+    let mut rng = rand::thread_rng();
+    let resolve = |target: SteerTarget| match target {
+        SteerTarget::Point(point) => Some(xz(point)),
+        SteerTarget::Entity(entity) => positions.get(entity).ok().map(|t| xz(t.translation())),
+    };
+
+    for (entity, mut npc, mut intent, yaw) in &mut npcs {
+        let Ok(transform) = positions.get(entity) else {
+            continue;
+        };
+        let origin = transform.translation();
+        let position = xz(origin);
+        let Npc {
+            behavior,
+            max_force,
+            avoidance,
+            wander_angle,
+            next_waypoint,
+            ..
+        } = &mut *npc;
+
+        let desired = match behavior {
+            SteeringBehavior::Idle | SteeringBehavior::GoTo(_) => Vec2::ZERO,
+            SteeringBehavior::Wander {
+                home,
+                leash: radius,
+            } => {
+                *wander_angle += rng.gen_range(-1.0..=1.0) * WANDER_JITTER * dt;
+                let heading = Vec2::new(yaw.0.sin(), yaw.0.cos());
+                leash(wander(heading, *wander_angle), position, xz(*home), *radius)
+            }
+            SteeringBehavior::Seek(target) => {
+                resolve(*target).map_or(Vec2::ZERO, |target| seek(position, target))
+            }
+            SteeringBehavior::Flee {
+                threat,
+                panic_distance,
+            } => resolve(*threat)
+                .map_or(Vec2::ZERO, |threat| flee(position, threat, *panic_distance)),
+            SteeringBehavior::Arrive {
+                target,
+                slowing_radius,
+            } => resolve(*target).map_or(Vec2::ZERO, |target| {
+                arrive(position, target, *slowing_radius)
+            }),
+            SteeringBehavior::FollowPath { waypoints, looped } => {
+                let waypoints: Vec<Vec2> = waypoints.iter().copied().map(xz).collect();
+                follow_path(position, &waypoints, next_waypoint, *looped)
+            }
+        } * *max_force;
+
+        let desired = match &ctx {
+            Some(ctx) if avoidance.lookahead > 0.0 && desired != Vec2::ZERO => {
+                let direction = desired.normalize();
+                let filter = QueryFilter::default()
+                    .exclude_sensors()
+                    .exclude_rigid_body(entity);
+                let hits: Vec<FeelerHit> = [0.0, avoidance.feeler_angle, -avoidance.feeler_angle]
+                    .into_iter()
+                    .filter_map(|angle| {
+                        let feeler = Vec2::from_angle(angle).rotate(direction);
+                        let length = if angle == 0.0 {
+                            avoidance.lookahead
+                        } else {
+                            avoidance.lookahead * SIDE_FEELER_SCALE
+                        };
+                        let (_, hit) = ctx.cast_ray_and_get_normal(
+                            origin,
+                            Vec3::new(feeler.x, 0.0, feeler.y),
+                            length,
+                            true,
+                            filter,
+                        )?;
+                        Some(FeelerHit {
+                            normal: xz(hit.normal).normalize_or_zero(),
+                            proximity: 1.0 - hit.time_of_impact / length,
+                        })
+                    })
+                    .collect();
+                avoid_obstacles(desired, &hits, avoidance.strength)
+            }
+            _ => desired,
+        };
+
+        let desired = if avoidance.separation_radius > 0.0 && desired != Vec2::ZERO {
+            let neighbours: Vec<Vec2> = characters
+                .iter()
+                .filter(|(other, _)| *other != entity)
+                .map(|(_, other)| xz(other.translation()))
+                .collect();
+            separate(
+                desired,
+                position,
+                &neighbours,
+                avoidance.separation_radius,
+                avoidance.strength,
+            )
+        } else {
+            desired
+        };
+
+        // Steering is in world space, so the intent uses world axes as its reference.
+        let movement = if desired.length() < 0.01 {
+            MovementInput::Idle
+        } else {
+            MovementInput::Activated {
+                direction: desired.normalize(),
+                force: desired.length(),
+            }
+        };
+        intent.movement = movement;
+        intent.facing = MovementFacingMode::FaceMoveDirection;
+        intent.reference_yaw = Some(0.0);
+    }
+}
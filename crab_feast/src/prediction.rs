@@ -0,0 +1,255 @@
+//! Client-side prediction of the locally controlled character.
+//!
+//! Every frame the client numbers its [`ControlIntent`] as a [`ControlInput`], sends it and
+//! simulates the horizontal movement and turning itself, keeping the inputs the server has
+//! not acknowledged yet. When an authoritative [`NetMotion`] arrives the prediction restarts
+//! from it and replays the remaining inputs; the difference to what was on screen becomes a
+//! visual offset that decays over [`Prediction::smoothing_secs`] instead of snapping.
+//!
+//! Vertical motion is not predicted: height comes from the server.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy_replicon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::input::{
+    step_horizontal_velocity, turn_toward_movement, CharacterBodyYaw, ControlIntent,
+    MovementController,
+};
+use crate::look::smoothing_factor;
+
+/// Inputs kept for replay at most; older ones are dropped if the server stops acknowledging.
+const MAX_PENDING_INPUTS: usize = 256;
+
+pub struct PredictionPlugin;
+
+impl Plugin for PredictionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PreUpdate,
+            reconcile_prediction
+                .after(ClientSystems::Receive)
+                .run_if(in_state(ClientState::Connected)),
+        );
+    }
+}
+
+/// Kinematic state of a character, as simulated by the server or predicted by a client.
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct NetMotion {
+    /// Sequence number of the last client input the server applied.
+    pub ack: u32,
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub yaw: f32,
+}
+
+/// One frame of the locally controlled character's intent, numbered so the server can
+/// acknowledge it through [`NetMotion::ack`].
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct ControlInput {
+    pub seq: u32,
+    pub intent: ControlIntent,
+    /// Client frame time the input was predicted over.
+    pub dt: f32,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct PendingInput {
+    input: ControlInput,
+    airborne: bool,
+}
+
+/// Predicted motion of the locally controlled character on a client.
+#[derive(Component, Clone, Debug)]
+pub struct Prediction {
+    pub controller: MovementController,
+    /// Time constant of the correction smoothing; `0.0` snaps.
+    pub smoothing_secs: f32,
+    /// Corrections larger than this (metres) are teleports and snap.
+    pub snap_distance: f32,
+    state: NetMotion,
+    next_seq: u32,
+    pending: VecDeque<PendingInput>,
+    /// What was on screen minus the prediction; decays to zero.
+    position_error: Vec3,
+    yaw_error: f32,
+}
+
+impl Prediction {
+    pub fn new(position: Vec3, yaw: f32) -> Self {
+        Self {
+            controller: MovementController::default(),
+            smoothing_secs: 0.1,
+            snap_distance: 3.0,
+            state: NetMotion {
+                position,
+                yaw,
+                ..default()
+            },
+            next_seq: 1,
+            pending: VecDeque::new(),
+            position_error: Vec3::ZERO,
+            yaw_error: 0.0,
+        }
+    }
+
+    /// Simulates one frame of `intent` and returns the sequence number to send it with.
+    pub fn predict(&mut self, intent: ControlIntent, dt: f32, airborne: bool) -> u32 {
+        let input = ControlInput {
+            seq: self.next_seq,
+            intent,
+            dt,
+        };
+        self.next_seq += 1;
+        step_motion(&mut self.state, &intent, dt, airborne, &self.controller);
+        if self.pending.len() == MAX_PENDING_INPUTS {
+            self.pending.pop_front();
+        }
+        self.pending.push_back(PendingInput { input, airborne });
+        input.seq
+    }
+
+    /// The newest `count` inputs the server has not acknowledged, oldest first.
+    pub fn latest_inputs(&self, count: usize) -> impl Iterator<Item = ControlInput> + '_ {
+        self.pending
+            .iter()
+            .skip(self.pending.len().saturating_sub(count))
+            .map(|pending| pending.input)
+    }
+
+    /// Restarts from the server's state and replays the inputs it has not applied yet.
+    pub fn reconcile(&mut self, authoritative: &NetMotion) {
+        while self
+            .pending
+            .front()
+            .is_some_and(|pending| pending.input.seq <= authoritative.ack)
+        {
+            self.pending.pop_front();
+        }
+        let (shown_position, shown_yaw) = self.visual();
+        self.state = *authoritative;
+        for PendingInput { input, airborne } in &self.pending {
+            step_motion(
+                &mut self.state,
+                &input.intent,
+                input.dt,
+                *airborne,
+                &self.controller,
+            );
+        }
+        self.position_error = shown_position - self.state.position;
+        self.yaw_error = wrap_angle(shown_yaw - self.state.yaw);
+        if self.position_error.length() > self.snap_distance {
+            self.position_error = Vec3::ZERO;
+            self.yaw_error = 0.0;
+        }
+    }
+
+    /// Decays the correction offset by one frame.
+    pub fn smooth(&mut self, dt: f32) {
+        let remaining = 1.0 - smoothing_factor(dt, self.smoothing_secs);
+        self.position_error *= remaining;
+        self.yaw_error *= remaining;
+    }
+
+    /// Position and yaw to display: the prediction plus what is left of the last correction.
+    pub fn visual(&self) -> (Vec3, f32) {
+        (
+            self.state.position + self.position_error,
+            self.state.yaw + self.yaw_error,
+        )
+    }
+
+    pub fn velocity(&self) -> Vec3 {
+        self.state.velocity
+    }
+
+    /// Inputs sent but not yet acknowledged by the server.
+    pub fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+/// The same horizontal movement and turning the server's `movement_system` and
+/// `face_body_toward_local_movement` apply; collisions are left to the server.
+pub(crate) fn step_motion(
+    state: &mut NetMotion,
+    intent: &ControlIntent,
+    dt: f32,
+    airborne: bool,
+    controller: &MovementController,
+) {
+    state.yaw = turn_toward_movement(state.yaw, intent, dt);
+    let velocity = step_horizontal_velocity(
+        Vec2::new(state.velocity.x, state.velocity.z),
+        intent,
+        controller,
+        airborne,
+        dt,
+    );
+    state.velocity.x = velocity.x;
+    state.velocity.z = velocity.y;
+    state.position += Vec3::new(velocity.x, 0.0, velocity.y) * dt;
+}
+
+fn wrap_angle(angle: f32) -> f32 {
+    (angle + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU) - std::f32::consts::PI
+}
+
+/// Shows the predicted motion on the body, over whatever replication wrote.
+pub(crate) fn apply_prediction(
+    prediction: &Prediction,
+    transform: &mut Transform,
+    yaw: &mut CharacterBodyYaw,
+) {
+    let (position, body_yaw) = prediction.visual();
+    transform.translation = position;
+    yaw.0 = body_yaw;
+}
+
+fn reconcile_prediction(
+    mut characters: Query<(
+        &mut Prediction,
+        Ref<NetMotion>,
+        &mut Transform,
+        &mut CharacterBodyYaw,
+    )>,
+) {
+    for (mut prediction, motion, mut transform, mut yaw) in &mut characters {
+        if motion.is_changed() {
+            prediction.reconcile(&motion);
+        }
+        apply_prediction(&prediction, &mut transform, &mut yaw);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::input::MovementInput;
+
+    use super::*;
+
+    #[test]
+    fn test_large_corrections_snap() {
+        let mut prediction = Prediction::new(Vec3::ZERO, 0.0);
+        let forward = ControlIntent {
+            movement: MovementInput::Activated {
+                direction: Vec2::NEG_Y,
+                force: 1.0,
+            },
+            reference_yaw: Some(0.0),
+            ..default()
+        };
+        prediction.predict(forward, 1.0 / 60.0, false);
+        let teleported = NetMotion {
+            ack: 1,
+            position: Vec3::new(50.0, 0.0, 0.0),
+            ..default()
+        };
+        prediction.reconcile(&teleported);
+        assert_eq!(prediction.visual().0, teleported.position);
+    }
+}
@@ -1,15 +1,27 @@
+use std::f32::consts::{FRAC_PI_4, PI, TAU};
+
 use bevy::app::AnimationSystems;
 use bevy::prelude::*;
 use bevy::transform::TransformSystems;
-use bevy_rapier3d::prelude::PhysicsSet;
+use bevy_rapier3d::prelude::{PhysicsSet, Velocity};
+use serde::Deserialize;
+
+use crate::input::CharacterBodyYaw;
 
-/// Visual-only root offset for the character bone: parent applies `lock * inverse(current)`.
+/// Root offset for the character bone: parent applies `lock * inverse(current)`.
+/// With [`RootMotionMode::Drive`] the cancelled planar motion is also handed to the body.
 #[derive(Component)]
 pub struct RootMotion {
     /// Chain product `MotionIntermediate * Hips` from the previous frame (`lock` in `int = lock * inv(hip)`).
     pub initial_hips_local: Option<Transform>,
     /// End-of-previous-frame Hips local pose; used to seam `MotionIntermediate` when rebase or init runs.
     pub prev_hip_local: Option<Transform>,
+    /// Physics body the rig belongs to; only read for [`RootMotionMode::Drive`].
+    pub body: Option<Entity>,
+    /// Planar hips motion measured by the last compensation pass, not yet applied to the body.
+    pub extracted: Option<ExtractedRootMotion>,
+    /// Root-motion velocity (world XZ) added to the body for the current physics step.
+    pub applied_velocity: Vec2,
 }
 
 impl Default for RootMotion {
@@ -17,10 +29,45 @@ impl Default for RootMotion {
         Self {
             initial_hips_local: None,
             prev_hip_local: None,
+            body: None,
+            extracted: None,
+            applied_velocity: Vec2::ZERO,
         }
     }
 }
 
+/// Per character body: what happens to the hips motion an animation carries.
+#[derive(Component, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RootMotionMode {
+    /// Cancel hips drift visually; the body only moves from input / physics.
+    #[default]
+    Compensate,
+    /// Cancel it visually *and* move the body by it: XZ translation is added to
+    /// [`Velocity`] for the physics step only, yaw to [`CharacterBodyYaw`]. Vertical motion,
+    /// pitch and roll stay with the skeleton.
+    Drive,
+}
+
+/// One frame of planar root motion in world space.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RootMotionDelta {
+    /// World XZ displacement (`x` = world X, `y` = world Z), metres.
+    pub translation: Vec2,
+    /// Rotation about world Y, radians (positive = counter-clockwise seen from above).
+    pub yaw: f32,
+}
+
+/// A [`RootMotionDelta`] plus the frame time it was measured over.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ExtractedRootMotion {
+    pub delta: RootMotionDelta,
+    pub secs: f32,
+}
+
+/// Larger per-frame steps are clip wraps or teleports, not motion; they are dropped.
+const MAX_ROOT_STEP: f32 = 0.5;
+const MAX_ROOT_YAW_STEP: f32 = FRAC_PI_4;
+
 /// Marks the extra transform inserted between a bone and its parent to cancel in-place root motion.
 #[derive(Component)]
 pub struct MotionIntermediateLayer {
@@ -54,13 +101,25 @@ impl Plugin for RootMotionPlugin {
                 // so the skeleton/layer chain is consistent for this frame.
                 .after(PhysicsSet::Writeback)
                 .before(TransformSystems::Propagate),
+        )
+        .add_systems(
+            PostUpdate,
+            // Input has already written this frame's velocity in `Update`; add last frame's
+            // extracted motion on top before the step.
+            drive_bodies_from_root_motion.before(PhysicsSet::SyncBackend),
+        )
+        .add_systems(
+            PostUpdate,
+            remove_root_motion_velocity.after(PhysicsSet::Writeback),
         );
     }
 }
 
 /// Inserts a [`MotionIntermediateLayer`] between the current parent and the hip bone.
+/// `body` is the physics body that [`RootMotionMode::Drive`] moves.
 pub fn wire_mixamo_hips_for_root_compensation(
     commands: &mut Commands,
+    body: Entity,
     parent_of_hips: Entity,
     hips: Entity,
 ) {
@@ -68,7 +127,10 @@ pub fn wire_mixamo_hips_for_root_compensation(
         .entity(hips)
         .insert((
             RootBone,
-            RootMotion::default(),
+            RootMotion {
+                body: Some(body),
+                ..default()
+            },
         ));
 
     let intermediate = commands
@@ -101,17 +163,55 @@ pub fn process_root_motion_rebase_requests(
     }
 }
 
+/// `lock * inv(hip)`: the intermediate pose that keeps `int * hip` at `lock`.
+fn compensated_intermediate(lock: Transform, hip: Transform) -> Transform {
+    Transform::from_matrix(lock.to_matrix() * hip.to_matrix().inverse())
+}
+
+/// Re-seams after a rebase so `int * hip` equals last frame's `int * prev_hip` and the
+/// model does not snap when the clip (and hence the hips pose) changes.
+fn seamed_intermediate(int: Transform, prev_hip: Transform, hip: Transform) -> Transform {
+    Transform::from_matrix(int.to_matrix() * prev_hip.to_matrix() * hip.to_matrix().inverse())
+}
+
+/// Rotation about `+Y` contained in `q` (twist of a swing-twist split), in `(-PI, PI]`.
+fn yaw_of(q: Quat) -> f32 {
+    let twist = 2.0 * q.y.atan2(q.w);
+    PI - (PI - twist).rem_euclid(TAU)
+}
+
+/// Planar world-space motion of the hips between two local poses. `to_world` maps the
+/// hips' parent space to world (scale and rotation; its translation cancels out).
+/// Returns `None` for steps too large to be animation (loop wraps, clip cuts).
+pub fn extract_root_motion_delta(
+    to_world: &Transform,
+    prev_hip: &Transform,
+    hip: &Transform,
+) -> Option<RootMotionDelta> {
+    let step = to_world.rotation * (to_world.scale * (hip.translation - prev_hip.translation));
+    let world_prev = to_world.rotation * prev_hip.rotation;
+    let world_cur = to_world.rotation * hip.rotation;
+    let delta = RootMotionDelta {
+        translation: Vec2::new(step.x, step.z),
+        yaw: yaw_of(world_cur * world_prev.inverse()),
+    };
+    (delta.translation.length() <= MAX_ROOT_STEP && delta.yaw.abs() <= MAX_ROOT_YAW_STEP)
+        .then_some(delta)
+}
+
 fn apply_root_compensation_to_intermediate(
+    time: Res<Time>,
     mut layers: Query<
-        (&MotionIntermediateLayer, &mut Transform),
+        (&MotionIntermediateLayer, &mut Transform, &ChildOf),
         Without<RootBone>,
     >,
     mut q_hips: Query<
         (&mut RootMotion, &Transform),
         (With<RootBone>, Without<MotionIntermediateLayer>),
     >,
+    globals: Query<&GlobalTransform>,
 ) {
-    for (layer, mut int_xform) in &mut layers {
+    for (layer, mut int_xform, child_of) in &mut layers {
         let Ok((mut rm, hip_xform)) = q_hips.get_mut(layer.root_bone_entity) else {
             continue;
         };
@@ -119,18 +219,23 @@ fn apply_root_compensation_to_intermediate(
             // Rebase / first init: do not throw away previous int — seam so world(hips) is continuous
             // (otherwise cross-fade to idle makes the model snap onto the physics root).
             if let Some(prev) = rm.prev_hip_local {
-                let m = int_xform
-                    .to_matrix()
-                    * prev.to_matrix()
-                    * (*hip_xform).to_matrix().inverse();
-                *int_xform = Transform::from_matrix(m);
+                *int_xform = seamed_intermediate(*int_xform, prev, *hip_xform);
             } else {
                 *int_xform = Transform::IDENTITY;
             }
         } else if let Some(t0) = rm.initial_hips_local {
             // Lock is the chain product (intermediate * hip) from the previous frame.
-            let m = t0.to_matrix() * (*hip_xform).to_matrix().inverse();
-            *int_xform = Transform::from_matrix(m);
+            *int_xform = compensated_intermediate(t0, *hip_xform);
+
+            // Only measure between frames of the same lock; a rebase is a clip cut, not motion.
+            if let (Some(prev), Ok(parent)) = (rm.prev_hip_local, globals.get(child_of.0)) {
+                let to_world = parent.compute_transform();
+                let delta = extract_root_motion_delta(&to_world, &prev, hip_xform);
+                rm.extracted = delta.map(|delta| ExtractedRootMotion {
+                    delta,
+                    secs: time.delta_secs(),
+                });
+            }
         }
         // Store int * hip = lock so `int = lock * inv(hip)` stays valid across blends and
         // re-seams (the previous `Some(hip)`-only value broke seam: lock must be the product).
@@ -138,3 +243,128 @@ fn apply_root_compensation_to_intermediate(
         rm.prev_hip_local = Some(*hip_xform);
     }
 }
+
+pub(crate) fn drive_bodies_from_root_motion(
+    mut hips: Query<&mut RootMotion, With<RootBone>>,
+    mut bodies: Query<(&RootMotionMode, &mut Velocity, &mut CharacterBodyYaw)>,
+) {
+    for mut rm in &mut hips {
+        let Some(extracted) = rm.extracted.take() else {
+            continue;
+        };
+        let Some(body) = rm.body else {
+            continue;
+        };
+        let Ok((mode, mut vel, mut yaw)) = bodies.get_mut(body) else {
+            continue;
+        };
+        if *mode != RootMotionMode::Drive || extracted.secs <= 0.0 {
+            continue;
+        }
+        let v = extracted.delta.translation / extracted.secs;
+        vel.linvel.x += v.x;
+        vel.linvel.z += v.y;
+        rm.applied_velocity = v;
+        yaw.0 += extracted.delta.yaw;
+    }
+}
+
+/// Takes the root-motion part back out after the step so movement keeps accelerating
+/// from its own velocity instead of accumulating the clip's.
+pub(crate) fn remove_root_motion_velocity(
+    mut hips: Query<&mut RootMotion, With<RootBone>>,
+    mut bodies: Query<&mut Velocity>,
+) {
+    for mut rm in &mut hips {
+        let v = std::mem::take(&mut rm.applied_velocity);
+        if v == Vec2::ZERO {
+            continue;
+        }
+        let Some(Ok(mut vel)) = rm.body.map(|body| bodies.get_mut(body)) else {
+            continue;
+        };
+        vel.linvel.x -= v.x;
+        vel.linvel.z -= v.y;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{a} != {b}");
+    }
+
+    #[test]
+    fn translation_is_planar_and_in_world_units() {
+        let prev = Transform::from_xyz(0.0, 1.0, 0.0);
+        let hip = Transform::from_xyz(0.1, 1.2, 0.2);
+        let d = extract_root_motion_delta(&Transform::IDENTITY, &prev, &hip).unwrap();
+        assert_close(d.translation.x, 0.1);
+        assert_close(d.translation.y, 0.2);
+        assert_close(d.yaw, 0.0);
+
+        // Mixamo rigs sit under a 0.01-scaled armature; parent rotated a quarter turn.
+        let to_world = Transform::from_rotation(Quat::from_rotation_y(FRAC_PI_2))
+            .with_scale(Vec3::splat(0.01));
+        let prev = Transform::from_xyz(0.0, 100.0, 0.0);
+        let hip = Transform::from_xyz(0.0, 100.0, 10.0);
+        let d = extract_root_motion_delta(&to_world, &prev, &hip).unwrap();
+        assert_close(d.translation.x, 0.1);
+        assert_close(d.translation.y, 0.0);
+    }
+
+    #[test]
+    fn yaw_ignores_pitch_and_roll() {
+        let prev = Transform::from_rotation(Quat::from_rotation_x(0.3));
+        let hip = Transform::from_rotation(Quat::from_rotation_y(0.2) * Quat::from_rotation_x(0.5));
+        let d = extract_root_motion_delta(&Transform::IDENTITY, &prev, &hip).unwrap();
+        assert_close(d.yaw, 0.2);
+    }
+
+    #[test]
+    fn yaw_follows_parent_up_axis() {
+        // Z-up armature: local Z rotations are world yaw.
+        let to_world = Transform::from_rotation(Quat::from_rotation_x(-FRAC_PI_2));
+        let hip = Transform::from_rotation(Quat::from_rotation_z(0.25));
+        let d = extract_root_motion_delta(&to_world, &Transform::IDENTITY, &hip).unwrap();
+        assert_close(d.yaw, 0.25);
+    }
+
+    #[test]
+    fn yaw_wraps_to_shortest_turn() {
+        assert_close(yaw_of(Quat::from_rotation_y(-0.1)), -0.1);
+        assert_close(yaw_of(Quat::from_rotation_y(TAU - 0.1)), -0.1);
+    }
+
+    #[test]
+    fn clip_wraps_are_dropped() {
+        let prev = Transform::from_xyz(0.0, 1.0, 2.0);
+        let hip = Transform::from_xyz(0.0, 1.0, 0.0);
+        assert!(extract_root_motion_delta(&Transform::IDENTITY, &prev, &hip).is_none());
+        let hip = Transform::from_rotation(Quat::from_rotation_y(PI));
+        assert!(
+            extract_root_motion_delta(&Transform::IDENTITY, &Transform::IDENTITY, &hip).is_none()
+        );
+    }
+
+    #[test]
+    fn compensation_holds_lock_and_seam_is_continuous() {
+        let lock = Transform::from_xyz(0.0, 1.0, 0.0);
+        let hip = Transform::from_xyz(0.3, 1.1, 0.4).with_rotation(Quat::from_rotation_y(0.4));
+        let int = compensated_intermediate(lock, hip);
+        let world = int.mul_transform(hip);
+        assert!(world.translation.abs_diff_eq(lock.translation, 1e-4));
+
+        // Clip switch: the new clip's first pose must land where the old one left the hips.
+        let new_hip = Transform::from_xyz(-0.5, 0.9, 0.0);
+        let seamed = seamed_intermediate(int, hip, new_hip);
+        let before = int.mul_transform(hip);
+        let after = seamed.mul_transform(new_hip);
+        assert!(after.translation.abs_diff_eq(before.translation, 1e-4));
+        assert!(after.rotation.abs_diff_eq(before.rotation, 1e-4));
+    }
+}
@@ -1,20 +1,34 @@
-use std::time::Duration;
-
 use bevy::app::AnimationSystems;
 use bevy::animation::RepeatAnimation;
 use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
-
-use crate::camera::GameCamera;
+use crab_feast_library::net::{NetMode, SnapshotBuffer};
+use crab_feast_library::utils::SpatialIndexPlugin;
+
+use crate::actions::{Action, ActionInput, DEBUG_CLIP_ACTIONS};
+use crate::blend_space::advance_phase;
+use crate::camera::{CameraRig, GameCamera, ThirdPersonCamera};
+use crate::character::{
+    spawn_character, CharacterAnimationBinding, CharacterAnimations, CharacterManifest,
+};
+use crate::character_body::CharacterBodyPlugin;
+use crate::debug::DebugMenuPlugin;
 use crate::input::{
-    CharacterBodyYaw, ControlInputPlugin, LookAxis, LookController, MovementController, MovementInput,
-    PlayerCharacterModelRoot,
+    CharacterBodyYaw, ControlInputPlugin, ControlIntent, Controlled, GroundState, LocalPlayer,
+    LookController, MovementInput,
+};
+use crate::locomotion::{
+    GroundPhase, LocomotionAnim, LocomotionParams, LocomotionSample, LocomotionStateMachine,
 };
-use crate::locomotion::{locomotion_anim_from_speed_and_force, LocomotionAnim, LocomotionInput};
+use crate::multiplayer::{NetLocomotion, Replica};
+use crate::navigation::NavigationPlugin;
+use crate::npc::{Npc, NpcPlugin, SteeringBehavior};
+use crate::prediction::Prediction;
 use crate::root_motion::{
     process_root_motion_rebase_requests, wire_mixamo_hips_for_root_compensation,
     CharacterRootMotionLink, RootMotionPlugin, RootMotionRebaseRequest,
 };
+use crate::utils::find_descendant_by_name;
 use crate::{GameAssets, GameState};
 
 pub struct ScenePlugin;
@@ -22,19 +36,31 @@ pub struct ScenePlugin;
 impl Plugin for ScenePlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(ControlInputPlugin)
+            .add_plugins(RapierPhysicsPlugin::<NoUserData>::default())
+            .add_plugins(RootMotionPlugin)
+            .add_plugins(CharacterBodyPlugin)
             .add_plugins((
-                RapierPhysicsPlugin::<NoUserData>::default(),
-                RapierDebugRenderPlugin::default(),
+                NavigationPlugin,
+                NpcPlugin,
+                SpatialIndexPlugin::default(),
+                DebugMenuPlugin,
             ))
-            .add_plugins(RootMotionPlugin)
-            .add_systems(OnEnter(GameState::Game), Self::setup)
+            .add_systems(
+                OnEnter(GameState::Game),
+                (
+                    Self::setup,
+                    // A client gets every character from the server.
+                    (Self::spawn_local_player, Self::spawn_npcs)
+                        .run_if(not(in_state(NetMode::Client))),
+                ),
+            )
             .add_systems(
                 Update,
                 setup_scene_once_loaded.run_if(in_state(GameState::Game)),
             )
             .add_systems(
                 Update,
-                try_wire_amy_hips_root_motion.run_if(in_state(GameState::Game)),
+                try_wire_character_hips_root_motion.run_if(in_state(GameState::Game)),
             )
             .add_systems(
                 Update,
@@ -43,7 +69,7 @@ impl Plugin for ScenePlugin {
             .add_systems(
                 PostUpdate,
                 (
-                    update_amy_locomotion_animation,
+                    update_character_locomotion_animation,
                     process_root_motion_rebase_requests,
                 )
                     .chain()
@@ -53,57 +79,36 @@ impl Plugin for ScenePlugin {
     }
 }
 
-/// Order: idle, walk, run, jog, silly, runba — used for locomotion (first four).
-#[derive(Resource)]
-struct AmyAnimationGraph {
-    graph_handle: Handle<AnimationGraph>,
-    idle: AnimationNodeIndex,
-    walk: AnimationNodeIndex,
-    run: AnimationNodeIndex,
-    jog: AnimationNodeIndex,
-    silly: AnimationNodeIndex,
-    runba: AnimationNodeIndex,
+/// Per animated character: locomotion state plus the body yaw seen last frame (for yaw rate).
+#[derive(Component, Default)]
+struct LocomotionTracker {
+    machine: LocomotionStateMachine,
+    last_yaw: Option<f32>,
 }
 
-#[derive(Resource)]
-struct AmyPlayerBinding {
-    body: Entity,
-    anim_player: Option<Entity>,
-    hips_wired: bool,
+/// Blend-space playback state for one animated character.
+#[derive(Component, Default)]
+struct LocomotionBlend {
+    /// Normalized gait phase shared by every blend-space clip.
+    phase: f32,
+    /// 0 = blend space sampled straight ahead, 1 = at the full body-space velocity; eases
+    /// toward 1 while the state machine strafes.
+    strafe_weight: f32,
+    /// Clip layered over the blend space (jump, turn, debug); kept while it fades out.
+    override_node: Option<AnimationNodeIndex>,
+    /// `false` once the override is no longer wanted and only fading out.
+    override_active: bool,
+    /// 0 = blend space only, 1 = override clip only.
+    override_weight: f32,
 }
 
-#[derive(Component, Clone, Copy, PartialEq, Eq)]
-struct LastLocomotion(LocomotionAnim);
-
 impl ScenePlugin {
     fn setup(
         mut commands: Commands,
-        game_assets: Res<GameAssets>,
         mut meshes: ResMut<Assets<Mesh>>,
         mut materials: ResMut<Assets<StandardMaterial>>,
-        mut graphs: ResMut<Assets<AnimationGraph>>,
         game_camera: Res<GameCamera>,
     ) {
-        let (graph, node_indices) = AnimationGraph::from_clips([
-            game_assets.idle.clone(),
-            game_assets.walking.clone(),
-            game_assets.running.clone(),
-            game_assets.jogging.clone(),
-            game_assets.silly_dancing.clone(),
-            game_assets.runba_dancing.clone(),
-        ]);
-
-        let graph_handle = graphs.add(graph);
-        commands.insert_resource(AmyAnimationGraph {
-            graph_handle,
-            idle: node_indices[0],
-            walk: node_indices[1],
-            run: node_indices[2],
-            jog: node_indices[3],
-            silly: node_indices[4],
-            runba: node_indices[5],
-        });
-
         commands.spawn((
             Mesh3d(meshes.add(Circle::new(4.0))),
             MeshMaterial3d(materials.add(Color::WHITE)),
@@ -144,267 +149,496 @@ impl ScenePlugin {
             ColliderDebugColor(Hsla::BLACK),
         ));
 
-        let player_entity = commands
-            .spawn((
-                Transform::from_xyz(0.0, 2.0, 0.0),
-                CharacterBodyYaw::default(),
-                RigidBody::Dynamic,
-                Collider::capsule_y(0.5, 0.2),
-                ColliderDebugColor(Hsla::WHITE),
-                Restitution::coefficient(0.3),
-                Damping {
-                    linear_damping: 0.3,
-                    angular_damping: 0.3,
-                },
-                LockedAxes::ROTATION_LOCKED,
-                Velocity::zero(),
-                MovementController::default(),
-                InheritedVisibility::default(),
-                Visibility::Visible,
-            ))
-            .with_children(|parent| {
-                parent
-                    .spawn((
-                        Name::new("PlayerCharacterModelRoot"),
-                        PlayerCharacterModelRoot,
-                        Transform::from_translation(Vec3::new(0.0, -0.7, 0.0)),
-                    ))
-                    .with_children(|p2| {
-                        p2.spawn((SceneRoot(game_assets.amy_model.clone()), Transform::default()));
-                    });
-            })
-            .id();
-
-        commands.insert_resource(AmyPlayerBinding {
-            body: player_entity,
-            anim_player: None,
-            hips_wired: false,
-        });
-
         commands.entity(game_camera.0).insert((
             Transform::from_xyz(0.0, 1.3, 5.0).looking_at(Vec3::new(0.0, 2.0, 0.0), Vec3::Y),
-            LookController {
-                axis: LookAxis::YawAndPitch,
-                ..Default::default()
-            },
+            LookController::default(),
+            CameraRig::default(),
+            ThirdPersonCamera::default(),
         ));
     }
-}
 
-const HIPS_BONE: &str = "mixamorig:Hips";
-
-fn is_descendant_of(
-    mut current: Entity,
-    child_of: &Query<&ChildOf>,
-    ancestor: Entity,
-) -> bool {
-    for _ in 0..256 {
-        if current == ancestor {
-            return true;
+    /// The character driven by this machine's input devices.
+    fn spawn_local_player(
+        mut commands: Commands,
+        game_assets: Res<GameAssets>,
+        manifests: Res<Assets<CharacterManifest>>,
+        mut graphs: ResMut<Assets<AnimationGraph>>,
+        local_player: Res<LocalPlayer>,
+    ) {
+        if let Some(player) = spawn_character(
+            &mut commands,
+            &manifests,
+            &mut graphs,
+            &game_assets.amy,
+            Transform::from_xyz(0.0, 2.0, 0.0),
+        ) {
+            commands
+                .entity(player)
+                .insert(Controlled { by: local_player.0 });
         }
-        if let Ok(c) = child_of.get(current) {
-            current = c.0;
-        } else {
-            return false;
+    }
+
+    /// A wanderer, a patrol whose route cuts through the boxes (obstacle avoidance) and one
+    /// that plans a path around them.
+    fn spawn_npcs(
+        mut commands: Commands,
+        game_assets: Res<GameAssets>,
+        manifests: Res<Assets<CharacterManifest>>,
+        mut graphs: ResMut<Assets<AnimationGraph>>,
+    ) {
+        let npcs = [
+            (
+                Vec3::new(6.0, 2.0, -4.0),
+                SteeringBehavior::Wander {
+                    home: Vec3::new(6.0, 0.0, -4.0),
+                    leash: 5.0,
+                },
+            ),
+            (
+                Vec3::new(-4.0, 2.0, -4.0),
+                SteeringBehavior::FollowPath {
+                    waypoints: vec![
+                        Vec3::new(-4.0, 0.0, -4.0),
+                        Vec3::new(4.0, 0.0, 4.0),
+                        Vec3::new(4.0, 0.0, -4.0),
+                    ],
+                    looped: true,
+                },
+            ),
+            (
+                Vec3::new(-3.0, 2.0, 3.0),
+                SteeringBehavior::GoTo(Vec3::new(3.0, 0.0, -3.0)),
+            ),
+        ];
+        for (position, behavior) in npcs {
+            if let Some(npc) = spawn_character(
+                &mut commands,
+                &manifests,
+                &mut graphs,
+                &game_assets.amy,
+                Transform::from_translation(position),
+            ) {
+                commands.entity(npc).insert(Npc::new(behavior));
+            }
         }
     }
-    false
 }
 
-fn find_descendant_by_name(
-    start: Entity,
-    children: &Query<&Children>,
-    names: &Query<&Name>,
-    target: &str,
+/// Walks up from `entity` to the nearest ancestor (or itself) carrying a character binding.
+fn find_character_body(
+    mut current: Entity,
+    child_of: &Query<&ChildOf>,
+    bodies: &Query<&mut CharacterAnimationBinding>,
 ) -> Option<Entity> {
-    let mut stack = vec![start];
-    while let Some(e) = stack.pop() {
-        if let Ok(n) = names.get(e) {
-            if n.as_str() == target {
-                return Some(e);
-            }
-        }
-        if let Ok(kids) = children.get(e) {
-            for c in kids.iter() {
-                stack.push(c);
-            }
+    for _ in 0..256 {
+        if bodies.contains(current) {
+            return Some(current);
         }
+        current = child_of.get(current).ok()?.0;
     }
     None
 }
 
 fn setup_scene_once_loaded(
     mut commands: Commands,
-    anims: Res<AmyAnimationGraph>,
-    mut binding: ResMut<AmyPlayerBinding>,
     child_of: Query<&ChildOf>,
+    mut bodies: Query<&mut CharacterAnimationBinding>,
+    animations: Query<&CharacterAnimations>,
     mut players: Query<(Entity, &mut AnimationPlayer), Added<AnimationPlayer>>,
 ) {
     for (entity, mut player) in &mut players {
-        if !is_descendant_of(entity, &child_of, binding.body) {
+        let Some(body) = find_character_body(entity, &child_of, &bodies) else {
             continue;
-        }
+        };
+        let Ok(anims) = animations.get(body) else {
+            continue;
+        };
+        let Ok(mut binding) = bodies.get_mut(body) else {
+            continue;
+        };
         if binding.anim_player.is_some() {
             continue;
         }
-        let mut transitions = AnimationTransitions::new();
-        transitions
-            .play(&mut player, anims.idle, Duration::ZERO)
-            .set_repeat(RepeatAnimation::Forever);
+        // Every blend-space clip stays active; weights and seek times are driven per frame.
+        let speed_samples = anims
+            .speed_locomotion
+            .iter()
+            .flat_map(|space| space.samples());
+        for sample in anims.locomotion.samples().iter().chain(speed_samples) {
+            player.play(sample.key.node).pause().set_weight(0.0);
+        }
 
         commands
             .entity(entity)
-            .insert(AnimationGraphHandle(anims.graph_handle.clone()))
-            .insert(transitions)
-            .insert(LastLocomotion(LocomotionAnim::Idle));
+            .insert(AnimationGraphHandle(anims.graph.clone()))
+            .insert(LocomotionTracker::default())
+            .insert(LocomotionBlend::default());
 
         binding.anim_player = Some(entity);
     }
 }
 
-fn try_wire_amy_hips_root_motion(
+fn try_wire_character_hips_root_motion(
     mut commands: Commands,
-    _anims: Res<AmyAnimationGraph>,
-    mut binding: ResMut<AmyPlayerBinding>,
+    mut bodies: Query<(Entity, &mut CharacterAnimationBinding)>,
     children: Query<&Children>,
     child_of: Query<&ChildOf>,
     names: Query<&Name>,
 ) {
-    if binding.hips_wired {
-        return;
-    }
-    let Some(anim_e) = binding.anim_player else {
-        return;
-    };
+    for (body, mut binding) in &mut bodies {
+        if binding.hips_wired {
+            continue;
+        }
+        let Some(anim_e) = binding.anim_player else {
+            continue;
+        };
 
-    let Some(hips) = find_descendant_by_name(binding.body, &children, &names, HIPS_BONE) else {
-        return;
-    };
-    let Ok(parent) = child_of.get(hips) else {
-        return;
-    };
-    let parent = parent.0;
+        let Some(hips) = find_descendant_by_name(body, &children, &names, &binding.hips_bone)
+        else {
+            continue;
+        };
+        let Ok(parent) = child_of.get(hips) else {
+            continue;
+        };
+        let parent = parent.0;
 
-    wire_mixamo_hips_for_root_compensation(&mut commands, parent, hips);
+        wire_mixamo_hips_for_root_compensation(&mut commands, body, parent, hips);
 
-    commands
-        .entity(anim_e)
-        .insert(CharacterRootMotionLink { hips_entity: hips });
-    binding.hips_wired = true;
+        commands
+            .entity(anim_e)
+            .insert(CharacterRootMotionLink { hips_entity: hips });
+        binding.hips_wired = true;
+    }
 }
 
-const FADE: Duration = Duration::from_millis(200);
-const LOCO_IDLE_MAX: f32 = 0.2;
-const LOCO_RUN_MIN: f32 = 4.0;
-const LOCO_RUN_FORCE: f32 = 0.9;
+const OVERRIDE_FADE_SECS: f32 = 0.2;
+/// Take-off should read immediately; a full [`OVERRIDE_FADE_SECS`] makes the jump look late.
+const JUMP_FADE_SECS: f32 = 0.1;
 
-/// Pauses automatic walk/run/idle for a few seconds after number-key debug switches.
+/// Plays a debug clip over locomotion for a few seconds after a number-key switch.
 #[derive(Component)]
 struct LocomotionDebugSuppress {
     end_secs: f32,
+    node: AnimationNodeIndex,
+}
+
+/// Points the override layer at `target`, starting its clip when needed; returns whether the
+/// override changed.
+fn switch_override(
+    ap: &mut AnimationPlayer,
+    blend: &mut LocomotionBlend,
+    target: Option<(AnimationNodeIndex, RepeatAnimation)>,
+) -> bool {
+    match target {
+        Some((node, repeat)) if blend.override_node != Some(node) || !blend.override_active => {
+            match blend.override_node {
+                Some(old) if old != node => {
+                    ap.stop(old);
+                    ap.start(node).set_repeat(repeat);
+                }
+                // Back to the clip still fading out: it carries on, unless a one-shot clip
+                // such as the jump already ran out and would only hold its last frame.
+                Some(_) => {
+                    if ap.animation(node).is_none_or(|active| active.is_finished()) {
+                        ap.start(node).set_repeat(repeat);
+                    }
+                }
+                None => {
+                    ap.start(node).set_repeat(repeat);
+                }
+            }
+            blend.override_node = Some(node);
+            blend.override_active = true;
+            true
+        }
+        None if blend.override_active => {
+            blend.override_active = false;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Moves `value` toward `target` by at most `step`.
+fn approach(value: f32, target: f32, step: f32) -> f32 {
+    if value < target {
+        (value + step).min(target)
+    } else {
+        (value - step).max(target)
+    }
+}
+
+fn intent_move_force(intent: &ControlIntent) -> Option<f32> {
+    match intent.movement {
+        MovementInput::Activated { direction, force } if direction.length() > 0.01 => Some(force),
+        _ => None,
+    }
 }
 
-fn update_amy_locomotion_animation(
+fn update_character_locomotion_animation(
     mut commands: Commands,
-    anims: Res<AmyAnimationGraph>,
-    binding: Res<AmyPlayerBinding>,
-    input: Res<MovementInput>,
     time: Res<Time>,
-    bodies: Query<&Velocity>,
+    clips: Res<Assets<AnimationClip>>,
+    mut bodies: Query<(
+        &Velocity,
+        &CharacterBodyYaw,
+        Option<&GroundState>,
+        &ControlIntent,
+        &CharacterAnimations,
+        &CharacterAnimationBinding,
+        Option<&mut NetLocomotion>,
+        Has<Replica>,
+        Option<&Prediction>,
+        Option<&SnapshotBuffer<Transform>>,
+    )>,
     mut anim_state: Query<(
-        Entity,
         &mut AnimationPlayer,
-        &mut AnimationTransitions,
-        &mut LastLocomotion,
+        &mut LocomotionTracker,
+        &mut LocomotionBlend,
         Option<&LocomotionDebugSuppress>,
     )>,
 ) {
-    let Some(anim_e) = binding.anim_player else {
-        return;
-    };
-    let Ok(vel) = bodies.get(binding.body) else {
-        return;
-    };
-    let Ok((anim_entity, mut ap, mut tr, mut last, debug_hold)) = anim_state.get_mut(anim_e) else {
-        return;
-    };
-    if let Some(s) = debug_hold {
-        if time.elapsed_secs() < s.end_secs {
-            return;
+    for (
+        vel,
+        body_yaw,
+        ground,
+        intent,
+        anims,
+        binding,
+        mut net,
+        replica,
+        prediction,
+        interpolation,
+    ) in &mut bodies
+    {
+        let Some(anim_entity) = binding.anim_player else {
+            continue;
+        };
+        let Ok((mut ap, mut tracker, mut blend, debug_hold)) = anim_state.get_mut(anim_entity)
+        else {
+            continue;
+        };
+        let dt = time.delta_secs();
+        let yaw_rate = match tracker.last_yaw {
+            Some(prev) if dt > 0.0 => {
+                let d = (body_yaw.0 - prev + std::f32::consts::PI)
+                    .rem_euclid(2.0 * std::f32::consts::PI)
+                    - std::f32::consts::PI;
+                d / dt
+            }
+            _ => 0.0,
+        };
+        tracker.last_yaw = Some(body_yaw.0);
+
+        // A replica is not simulated here; it animates from what the server sent and the
+        // interpolated motion, or from the prediction and local input when it is our own.
+        let remote = net.as_deref().filter(|_| replica);
+        let (linvel, ground, facing, move_force) = match (remote, prediction) {
+            (Some(net), Some(prediction)) => (
+                prediction.velocity(),
+                net.ground,
+                intent.facing,
+                intent_move_force(intent),
+            ),
+            (Some(net), None) => (
+                interpolation.map_or(Vec3::ZERO, SnapshotBuffer::velocity),
+                net.ground,
+                net.facing,
+                net.move_force,
+            ),
+            (None, _) => (
+                vel.linvel,
+                ground.map_or(GroundPhase::Grounded, |g| g.phase),
+                intent.facing,
+                intent_move_force(intent),
+            ),
+        };
+
+        // Body space: forward = (sin yaw, cos yaw) on XZ, right = forward × Y.
+        let (sin, cos) = body_yaw.0.sin_cos();
+        let velocity_body = Vec2::new(
+            -cos * linvel.x + sin * linvel.z,
+            sin * linvel.x + cos * linvel.z,
+        );
+        let sample = LocomotionSample {
+            velocity_body,
+            yaw_rate,
+            ground,
+            facing,
+            move_force,
+        };
+        let state = tracker
+            .machine
+            .update(&sample, &LocomotionParams::default());
+
+        let debug_node = debug_hold
+            .filter(|s| time.elapsed_secs() < s.end_secs)
+            .map(|s| s.node);
+        let jump = anims.slot("jump");
+        let target = match (debug_node, state) {
+            _ if remote.is_some() => remote
+                .and_then(|net| net.override_clip.as_deref())
+                .and_then(|slot| {
+                    // Mirrors the local choice below: only the jump clip plays once.
+                    let repeat = if slot == "jump" {
+                        RepeatAnimation::Never
+                    } else {
+                        RepeatAnimation::Forever
+                    };
+                    anims.slot(slot).map(|node| (node, repeat))
+                }),
+            (Some(node), _) => Some((node, RepeatAnimation::Forever)),
+            // Landing keeps the jump clip running: its tail is the touchdown recovery.
+            (None, LocomotionAnim::Jump | LocomotionAnim::Land) => {
+                jump.map(|node| (node, RepeatAnimation::Never))
+            }
+            (None, LocomotionAnim::TurnLeft) => anims
+                .slot("turn_left")
+                .map(|node| (node, RepeatAnimation::Forever)),
+            (None, LocomotionAnim::TurnRight) => anims
+                .slot("turn_right")
+                .map(|node| (node, RepeatAnimation::Forever)),
+            (None, _) => None,
+        };
+        if !replica && let Some(net) = net.as_mut() {
+            let override_clip = target.and_then(|(node, _)| anims.slot_name(node));
+            net.set_if_neq(NetLocomotion {
+                ground,
+                facing,
+                move_force,
+                override_clip: override_clip.map(str::to_owned),
+            });
         }
-    }
 
-    let horizontal = (vel.linvel * Vec3::new(1.0, 0.0, 1.0)).length();
-    let loco_in = match input.as_ref() {
-        MovementInput::Idle => LocomotionInput {
-            speed_horizontal: horizontal,
-            move_force: None,
-        },
-        MovementInput::Activated { direction, force } if direction.length() > 0.01 => {
-            LocomotionInput {
-                speed_horizontal: horizontal,
-                move_force: Some(*force),
-            }
+        if switch_override(&mut ap, &mut blend, target) {
+            commands.entity(anim_entity).insert(RootMotionRebaseRequest);
         }
-        _ => LocomotionInput {
-            speed_horizontal: horizontal,
-            move_force: None,
-        },
-    };
 
-    let desired = locomotion_anim_from_speed_and_force(
-        loco_in,
-        LOCO_IDLE_MAX,
-        LOCO_RUN_MIN,
-        LOCO_RUN_FORCE,
-    );
+        let fade_secs = if blend.override_node.is_some() && blend.override_node == jump {
+            JUMP_FADE_SECS
+        } else {
+            OVERRIDE_FADE_SECS
+        };
+        let override_target = if blend.override_active { 1.0 } else { 0.0 };
+        blend.override_weight = approach(blend.override_weight, override_target, dt / fade_secs);
+        if let Some(node) = blend.override_node {
+            if !blend.override_active && blend.override_weight <= 0.0 {
+                ap.stop(node);
+                blend.override_node = None;
+            } else if let Some(active) = ap.animation_mut(node) {
+                active.set_weight(blend.override_weight);
+            }
+        }
 
-    if last.0 == desired {
-        return;
+        let strafe_target = if state.is_strafing() { 1.0 } else { 0.0 };
+        blend.strafe_weight = approach(blend.strafe_weight, strafe_target, dt / OVERRIDE_FADE_SECS);
+        // Every layer's clips share one phase; its duration and rate are blended by share.
+        let layers: Vec<_> = anims
+            .ground_layers(state, velocity_body, blend.strafe_weight)
+            .into_iter()
+            .map(|(space, point, share)| {
+                let weights = space.weights(point);
+                let durations: Vec<f32> = space
+                    .samples()
+                    .iter()
+                    .map(|s| clips.get(s.key.clip).map_or(0.0, |c| c.duration()))
+                    .collect();
+                (space, weights, durations, share)
+            })
+            .collect();
+        let mut blended_duration = 0.0;
+        let mut rate = 0.0;
+        for (space, weights, durations, share) in &layers {
+            let duration: f32 = weights.iter().zip(durations).map(|(w, d)| w * d).sum();
+            blended_duration += share * duration;
+            rate += share * space.playback_rate(weights, velocity_body.length());
+        }
+        blend.phase = advance_phase(blend.phase, dt, rate, blended_duration);
+
+        let ground_weight = 1.0 - blend.override_weight;
+        for (space, weights, durations, share) in &layers {
+            for ((s, w), d) in space.samples().iter().zip(weights).zip(durations) {
+                ap.play(s.key.node)
+                    .pause()
+                    .set_seek_time(blend.phase * d)
+                    .set_weight(w * share * ground_weight);
+            }
+        }
     }
-    last.0 = desired;
-
-    let node = match desired {
-        LocomotionAnim::Idle => anims.idle,
-        LocomotionAnim::Walk => anims.walk,
-        LocomotionAnim::Run => anims.run,
-    };
-    tr.play(&mut ap, node, FADE)
-        .set_repeat(RepeatAnimation::Forever);
-    commands
-        .entity(anim_entity)
-        .insert(RootMotionRebaseRequest);
 }
 
 fn debug_animation_hotkeys(
     time: Res<Time>,
-    keyboard: Res<ButtonInput<KeyCode>>,
-    anims: Res<AmyAnimationGraph>,
-    mut q: Query<(
-        Entity,
-        &mut AnimationPlayer,
-        &mut AnimationTransitions,
-    ), With<LastLocomotion>>,
+    actions: ActionInput,
+    bodies: Query<(&CharacterAnimations, &CharacterAnimationBinding)>,
+    q: Query<(), With<LocomotionTracker>>,
     mut commands: Commands,
 ) {
-    let any = [
-        (KeyCode::Digit1, anims.silly),
-        (KeyCode::Digit2, anims.runba),
-        (KeyCode::Digit3, anims.jog),
-        (KeyCode::Digit4, anims.run),
-    ]
-    .into_iter()
-    .find(|(k, _)| keyboard.just_pressed(*k));
-    if let Some((_, node)) = any {
-        for (e, mut p, mut t) in &mut q {
-            t.play(&mut p, node, FADE)
-                .set_repeat(RepeatAnimation::Forever);
-            commands.entity(e).insert((
-                RootMotionRebaseRequest,
-                LocomotionDebugSuppress {
-                    end_secs: time.elapsed_secs() + 2.0,
-                },
-            ));
+    let Some(slot) =
+        (0..DEBUG_CLIP_ACTIONS).find(|&slot| actions.just_pressed(Action::DebugClip(slot)))
+    else {
+        return;
+    };
+    let index = usize::from(slot);
+    for (anims, binding) in &bodies {
+        let (Some(anim_e), Some(&node)) = (binding.anim_player, anims.debug.get(index)) else {
+            continue;
+        };
+        if q.contains(anim_e) {
+            commands.entity(anim_e).insert(LocomotionDebugSuppress {
+                end_secs: time.elapsed_secs() + 2.0,
+                node,
+            });
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::animation::advance_animations;
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::tasks::{ComputeTaskPool, TaskPool};
+
+    use super::*;
+
+    #[test]
+    fn back_to_back_jumps_restart_the_jump_clip() {
+        ComputeTaskPool::get_or_init(TaskPool::default);
+        let mut world = World::new();
+        let mut clips = Assets::<AnimationClip>::default();
+        let mut clip = AnimationClip::default();
+        clip.set_duration(0.5);
+        let (graph, jump) = AnimationGraph::from_clip(clips.add(clip));
+        let mut graphs = Assets::<AnimationGraph>::default();
+        let graph = graphs.add(graph);
+        world.insert_resource(clips);
+        world.insert_resource(graphs);
+        world.init_resource::<Time>();
+        let player = world
+            .spawn((AnimationPlayer::default(), AnimationGraphHandle(graph)))
+            .id();
+        let mut blend = LocomotionBlend::default();
+        let target = Some((jump, RepeatAnimation::Never));
+
+        let mut ap = world.get_mut::<AnimationPlayer>(player).unwrap();
+        assert!(switch_override(&mut ap, &mut blend, target));
+        // The first jump plays out and lands; the clip starts fading out.
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs_f32(1.0));
+        world.run_system_once(advance_animations).unwrap();
+        let mut ap = world.get_mut::<AnimationPlayer>(player).unwrap();
+        assert!(ap.animation(jump).unwrap().is_finished());
+        assert!(switch_override(&mut ap, &mut blend, None));
+        assert_eq!(blend.override_node, Some(jump));
+
+        // Jumping again before the fade ends plays the clip from the start.
+        assert!(switch_override(&mut ap, &mut blend, target));
+        let active = ap.animation(jump).unwrap();
+        assert!(!active.is_finished());
+        assert_eq!(active.seek_time(), 0.0);
+        assert!(blend.override_active);
+    }
+}
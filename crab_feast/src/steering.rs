@@ -0,0 +1,271 @@
+//! Steering behaviors on the XZ plane (tests cover the pure math).
+//!
+//! Vectors are world `(x, z)` packed into a [`Vec2`]. Every behavior returns a desired
+//! movement whose direction is where to go and whose length in \[0, 1\] is how hard to push,
+//! the same shape as a stick deflection, so the result maps straight onto a movement intent.
+
+use bevy::math::Vec2;
+
+/// Distance at which [`arrive`] considers the target reached.
+pub const ARRIVE_RADIUS: f32 = 0.3;
+/// Distance at which [`follow_path`] moves on to the next waypoint.
+pub const WAYPOINT_RADIUS: f32 = 0.6;
+/// Slowing radius used by [`follow_path`] on the last waypoint of an open path.
+pub const PATH_SLOWING_RADIUS: f32 = 2.0;
+/// Distance ahead of the body of the circle the [`wander`] target moves on.
+pub const WANDER_DISTANCE: f32 = 2.0;
+pub const WANDER_RADIUS: f32 = 1.0;
+
+/// Full push toward `target`.
+pub fn seek(position: Vec2, target: Vec2) -> Vec2 {
+    (target - position).normalize_or_zero()
+}
+
+/// Full push away from `threat` while it is closer than `panic_distance`, nothing otherwise.
+pub fn flee(position: Vec2, threat: Vec2, panic_distance: f32) -> Vec2 {
+    let away = position - threat;
+    if away.length() >= panic_distance {
+        return Vec2::ZERO;
+    }
+    // Standing exactly on the threat: any direction beats freezing.
+    away.normalize_or(Vec2::X)
+}
+
+/// Like [`seek`], but the push ramps down linearly inside `slowing_radius` and stops within
+/// [`ARRIVE_RADIUS`].
+pub fn arrive(position: Vec2, target: Vec2, slowing_radius: f32) -> Vec2 {
+    let to_target = target - position;
+    let distance = to_target.length();
+    if distance <= ARRIVE_RADIUS {
+        return Vec2::ZERO;
+    }
+    let force = if slowing_radius > 0.0 {
+        (distance / slowing_radius).min(1.0)
+    } else {
+        1.0
+    };
+    to_target / distance * force
+}
+
+/// Reynolds wander: aims at a point on a circle [`WANDER_DISTANCE`] ahead of `heading`, at
+/// `angle` relative to it. Jitter `angle` a little every frame for a smooth meander.
+pub fn wander(heading: Vec2, angle: f32) -> Vec2 {
+    let heading = heading.normalize_or(Vec2::Y);
+    let target =
+        heading * WANDER_DISTANCE + heading.rotate(Vec2::from_angle(angle)) * WANDER_RADIUS;
+    target.normalize_or_zero()
+}
+
+/// Adds a pull back toward `home` to a wander direction once the body is more than `leash`
+/// away; the pull reaches twice the wander weight at `2 * leash`.
+pub fn leash(desired: Vec2, position: Vec2, home: Vec2, leash: f32) -> Vec2 {
+    let distance = position.distance(home);
+    if leash <= 0.0 || distance <= leash {
+        return desired;
+    }
+    let pull = ((distance - leash) / leash).min(1.0) * 2.0;
+    (desired + seek(position, home) * pull).normalize_or_zero() * desired.length()
+}
+
+/// Seeks `waypoints[*next]`, advancing `next` once within [`WAYPOINT_RADIUS`]. Looped paths
+/// wrap around; open paths [`arrive`] at their last point.
+pub fn follow_path(position: Vec2, waypoints: &[Vec2], next: &mut usize, looped: bool) -> Vec2 {
+    let Some(&target) = waypoints.get(*next) else {
+        return Vec2::ZERO;
+    };
+    let last = *next + 1 == waypoints.len();
+    if last && !looped {
+        return arrive(position, target, PATH_SLOWING_RADIUS);
+    }
+    if position.distance(target) < WAYPOINT_RADIUS {
+        *next = (*next + 1) % waypoints.len();
+    }
+    seek(position, waypoints[*next])
+}
+
+/// An obstacle found by a feeler probe.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FeelerHit {
+    /// Surface normal of the obstacle on the XZ plane.
+    pub normal: Vec2,
+    /// `1` when touching the body, falling to `0` at the tip of the feeler.
+    pub proximity: f32,
+}
+
+/// Bends `desired` away from the obstacles in `hits`, keeping its force. Each hit pushes
+/// along its normal and along the wall on the side `desired` already leans to, so a head-on
+/// wall turns the body instead of stopping it.
+pub fn avoid_obstacles(desired: Vec2, hits: &[FeelerHit], strength: f32) -> Vec2 {
+    let force = desired.length();
+    if force <= f32::EPSILON || hits.is_empty() {
+        return desired;
+    }
+    let direction = desired / force;
+    let push: Vec2 = hits
+        .iter()
+        .map(|hit| {
+            let tangent = hit.normal.perp();
+            let tangent = if tangent.dot(direction) < 0.0 {
+                -tangent
+            } else {
+                tangent
+            };
+            (hit.normal + tangent) * hit.proximity.clamp(0.0, 1.0)
+        })
+        .sum();
+    (direction + push * strength).normalize_or(direction.perp()) * force
+}
+
+/// Bends `desired` away from `neighbours` closer than `radius`, keeping its force. The push
+/// from each neighbour grows linearly from nothing at `radius` to full when touching, so
+/// bodies heading the same way spread out instead of bumping into each other.
+pub fn separate(
+    desired: Vec2,
+    position: Vec2,
+    neighbours: &[Vec2],
+    radius: f32,
+    strength: f32,
+) -> Vec2 {
+    let force = desired.length();
+    if force <= f32::EPSILON || radius <= 0.0 {
+        return desired;
+    }
+    let direction = desired / force;
+    let push: Vec2 = neighbours
+        .iter()
+        .map(|neighbour| {
+            let away = position - *neighbour;
+            let distance = away.length();
+            if distance >= radius {
+                return Vec2::ZERO;
+            }
+            away.normalize_or(direction.perp()) * (1.0 - distance / radius)
+        })
+        .sum();
+    (direction + push * strength).normalize_or(direction.perp()) * force
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Vec2, b: Vec2) {
+        assert!(a.distance(b) < 1e-5, "{a} != {b}");
+    }
+
+    #[test]
+    fn seek_and_flee_point_opposite_ways() {
+        let position = Vec2::new(1.0, 1.0);
+        let other = Vec2::new(4.0, 5.0);
+        let towards = seek(position, other);
+        assert_close(towards, Vec2::new(0.6, 0.8));
+        assert_close(flee(position, other, 10.0), -towards);
+        assert_eq!(flee(position, other, 5.0), Vec2::ZERO);
+    }
+
+    #[test]
+    fn arrive_slows_down_and_stops() {
+        let target = Vec2::new(10.0, 0.0);
+        assert_close(arrive(Vec2::ZERO, target, 4.0), Vec2::X);
+        assert_close(arrive(Vec2::new(8.0, 0.0), target, 4.0), Vec2::X * 0.5);
+        assert_eq!(arrive(Vec2::new(9.9, 0.0), target, 4.0), Vec2::ZERO);
+    }
+
+    #[test]
+    fn wander_stays_ahead_of_heading() {
+        let heading = Vec2::Y;
+        assert_close(wander(heading, 0.0), Vec2::Y);
+        for angle in [-3.0, -1.5, 0.5, 2.0, 3.1] {
+            let out = wander(heading, angle);
+            assert!((out.length() - 1.0).abs() < 1e-5);
+            assert!(out.dot(heading) > 0.0, "{angle}: {out}");
+        }
+        // Positive angles turn counter-clockwise: left of +z is -x.
+        assert!(wander(heading, 1.0).x < 0.0);
+    }
+
+    #[test]
+    fn leash_pulls_wanderers_home() {
+        let out_of_reach = Vec2::new(20.0, 0.0);
+        let desired = Vec2::X;
+        assert_eq!(
+            leash(desired, Vec2::new(3.0, 0.0), Vec2::ZERO, 5.0),
+            desired
+        );
+        let pulled = leash(desired, out_of_reach, Vec2::ZERO, 5.0);
+        assert!(pulled.x < 0.0);
+    }
+
+    #[test]
+    fn follow_path_advances_and_loops() {
+        let path = [Vec2::ZERO, Vec2::new(5.0, 0.0), Vec2::new(5.0, 5.0)];
+        let mut next = 1;
+        assert_close(follow_path(Vec2::ZERO, &path, &mut next, true), Vec2::X);
+        assert_eq!(next, 1);
+
+        let steer = follow_path(Vec2::new(4.8, 0.0), &path, &mut next, true);
+        assert_eq!(next, 2);
+        assert!(steer.y > 0.9);
+
+        follow_path(Vec2::new(5.0, 4.9), &path, &mut next, true);
+        assert_eq!(next, 0);
+    }
+
+    #[test]
+    fn open_path_arrives_at_the_end() {
+        let path = [Vec2::ZERO, Vec2::new(5.0, 0.0)];
+        let mut next = 1;
+        assert_close(
+            follow_path(Vec2::new(4.0, 0.0), &path, &mut next, false),
+            Vec2::X * 0.5,
+        );
+        assert_eq!(
+            follow_path(Vec2::new(5.0, 0.0), &path, &mut next, false),
+            Vec2::ZERO
+        );
+        assert_eq!(next, 1);
+        assert_eq!(follow_path(Vec2::ZERO, &[], &mut 0, false), Vec2::ZERO);
+    }
+
+    #[test]
+    fn avoidance_turns_away_and_keeps_force() {
+        let desired = Vec2::Y * 0.5;
+        assert_eq!(avoid_obstacles(desired, &[], 1.5), desired);
+
+        // Wall ahead on the +x side: turn toward -x.
+        let right_wall = FeelerHit {
+            normal: Vec2::new(-1.0, -1.0).normalize(),
+            proximity: 0.6,
+        };
+        let out = avoid_obstacles(desired, &[right_wall], 1.5);
+        assert!(out.x < 0.0);
+        assert!((out.length() - 0.5).abs() < 1e-5);
+
+        // Head-on wall still produces a sideways push rather than zero.
+        let head_on = FeelerHit {
+            normal: -Vec2::Y,
+            proximity: 1.0,
+        };
+        let out = avoid_obstacles(desired, &[head_on], 1.0);
+        assert!(out.x.abs() > 0.1);
+        assert!((out.length() - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn separation_steers_away_from_close_neighbours() {
+        let desired = Vec2::Y * 0.5;
+        let far = [Vec2::new(3.0, 0.0)];
+        assert_eq!(separate(desired, Vec2::ZERO, &far, 2.0, 1.0), desired);
+
+        let beside = [Vec2::new(1.0, 0.0)];
+        let out = separate(desired, Vec2::ZERO, &beside, 2.0, 1.0);
+        assert!(out.x < 0.0);
+        assert!((out.length() - 0.5).abs() < 1e-5);
+
+        // A body standing still is not pushed.
+        assert_eq!(
+            separate(Vec2::ZERO, Vec2::ZERO, &beside, 2.0, 1.0),
+            Vec2::ZERO
+        );
+    }
+}
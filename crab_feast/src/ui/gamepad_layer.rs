@@ -0,0 +1,216 @@
+//! 手柄输入层：绑定到 [`AxisAction::Move`] 的摇杆 → 本机玩家的 [`MovementInput`]（径向死区），
+//! 绑定到 [`AxisAction::Look`] 的摇杆 → 连续 [`LookInput`]（默认左 / 右摇杆）。
+//! 手柄按键（跳跃、侧移、十字键移动等）与键鼠一样经 [`ActionMap`] 读取。
+//!
+//! 与触摸、键鼠共存：[`ActiveInputDevice`] 记录最近产生输入的设备，只有活跃的手柄会写入
+//! 移动状态；手柄拔出时回退到默认设备并停止移动。
+
+use bevy::{
+    input::{
+        gamepad::{GamepadConnection, GamepadConnectionEvent},
+        mouse::AccumulatedMouseMotion,
+        InputSystems,
+    },
+    prelude::*,
+};
+
+use crate::{
+    actions::{ActionMap, AxisAction, AxisBinding},
+    input::{ActiveInputDevice, LocalControl, LookInput, MovementInput},
+    look::LookSource,
+};
+
+pub struct GamepadInputPlugin;
+
+/// 手柄摇杆死区。
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct GamepadInputSettings {
+    /// 径向死区内沿：摇杆偏移量低于此值视为零。
+    pub inner_deadzone: f32,
+    /// 径向死区外沿：偏移量达到此值即视为满偏。
+    pub outer_deadzone: f32,
+}
+
+impl Default for GamepadInputSettings {
+    fn default() -> Self {
+        Self {
+            inner_deadzone: 0.15,
+            outer_deadzone: 0.95,
+        }
+    }
+}
+
+impl Plugin for GamepadInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GamepadInputSettings>().add_systems(
+            PreUpdate,
+            (
+                on_gamepad_connection,
+                track_active_input_device,
+                on_gamepad_sticks,
+            )
+                .chain()
+                .after(InputSystems),
+        );
+    }
+}
+
+/// 径向死区：按摇杆偏移量整体（而非逐轴）裁剪，并把 `[inner, outer]` 重新映射到 `[0, 1]`，
+/// 保持方向不变，斜向推杆不会被吸附到轴上。
+pub fn radial_deadzone(stick: Vec2, inner: f32, outer: f32) -> Vec2 {
+    let magnitude = stick.length();
+    if magnitude <= inner {
+        return Vec2::ZERO;
+    }
+    let scaled = if outer > inner {
+        ((magnitude - inner) / (outer - inner)).min(1.0)
+    } else {
+        1.0
+    };
+    stick / magnitude * scaled
+}
+
+fn deadzoned(stick: Vec2, settings: &GamepadInputSettings) -> Vec2 {
+    radial_deadzone(stick, settings.inner_deadzone, settings.outer_deadzone)
+}
+
+/// `axis` 绑定的第一个越过死区的摇杆值。
+fn axis_stick(
+    map: &ActionMap,
+    axis: AxisAction,
+    gamepad: &Gamepad,
+    settings: &GamepadInputSettings,
+) -> Vec2 {
+    map.axis_bindings(axis)
+        .iter()
+        .filter_map(|binding| match binding {
+            AxisBinding::LeftStick => Some(gamepad.left_stick()),
+            AxisBinding::RightStick => Some(gamepad.right_stick()),
+            AxisBinding::MouseMotion => None,
+        })
+        .map(|stick| deadzoned(stick, settings))
+        .find(|stick| *stick != Vec2::ZERO)
+        .unwrap_or(Vec2::ZERO)
+}
+
+fn on_gamepad_connection(
+    mut connections: MessageReader<GamepadConnectionEvent>,
+    mut active_device: ResMut<ActiveInputDevice>,
+    mut control: LocalControl,
+) {
+    for event in connections.read() {
+        match &event.connection {
+            GamepadConnection::Connected { name, .. } => {
+                info!("Gamepad connected: {name} ({:?})", event.gamepad);
+            }
+            GamepadConnection::Disconnected => {
+                info!("Gamepad disconnected: {:?}", event.gamepad);
+                if *active_device == ActiveInputDevice::Gamepad(event.gamepad) {
+                    *active_device = ActiveInputDevice::default();
+                    control.update(|intent| intent.movement = MovementInput::Idle);
+                }
+            }
+        }
+    }
+}
+
+/// 最近活跃设备仲裁：手柄按键或摇杆越过死区、触摸按下、键盘 / 鼠标按键或鼠标移动。
+pub(crate) fn track_active_input_device(
+    settings: Res<GamepadInputSettings>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    mouse_motion: Res<AccumulatedMouseMotion>,
+    touches: Res<Touches>,
+    gamepads: Query<(Entity, &Gamepad)>,
+    mut active_device: ResMut<ActiveInputDevice>,
+) {
+    let gamepad = gamepads.iter().find(|(_, gamepad)| {
+        gamepad.get_just_pressed().next().is_some()
+            || deadzoned(gamepad.left_stick(), &settings) != Vec2::ZERO
+            || deadzoned(gamepad.right_stick(), &settings) != Vec2::ZERO
+    });
+    let device = if let Some((entity, _)) = gamepad {
+        ActiveInputDevice::Gamepad(entity)
+    } else if touches.any_just_pressed() {
+        ActiveInputDevice::Touch
+    } else if keyboard.get_just_pressed().next().is_some()
+        || mouse_buttons.get_just_pressed().next().is_some()
+        || mouse_motion.delta != Vec2::ZERO
+    {
+        ActiveInputDevice::KeyboardMouse
+    } else {
+        return;
+    };
+    active_device.set_if_neq(device);
+}
+
+fn on_gamepad_sticks(
+    mut commands: Commands,
+    settings: Res<GamepadInputSettings>,
+    action_map: Res<ActionMap>,
+    active_device: Res<ActiveInputDevice>,
+    gamepads: Query<&Gamepad>,
+    mut control: LocalControl,
+    mut moving: Local<bool>,
+) {
+    let gamepad = match *active_device {
+        ActiveInputDevice::Gamepad(entity) => gamepads.get(entity).ok(),
+        _ => None,
+    };
+    let Some(gamepad) = gamepad else {
+        // 切换到其他设备时松开摇杆，避免角色一直走下去
+        if std::mem::take(&mut *moving) {
+            control.update(|intent| intent.movement = MovementInput::Idle);
+        }
+        return;
+    };
+
+    // 手柄上推为 +y，MovementInput / LookInput 与屏幕一致，向上为 -y
+    let stick = axis_stick(&action_map, AxisAction::Move, gamepad, &settings);
+    if stick != Vec2::ZERO {
+        let movement = MovementInput::Activated {
+            direction: Vec2::new(stick.x, -stick.y).normalize(),
+            force: stick.length(),
+        };
+        control.update(|intent| intent.movement = movement);
+        *moving = true;
+    } else if std::mem::take(&mut *moving) {
+        control.update(|intent| intent.movement = MovementInput::Idle);
+    }
+
+    let look = axis_stick(&action_map, AxisAction::Look, gamepad, &settings);
+    if look != Vec2::ZERO {
+        commands.trigger(LookInput {
+            delta: Vec2::new(look.x, -look.y),
+            source: LookSource::Gamepad,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn radial_deadzone_zeroes_small_deflection() {
+        assert_eq!(radial_deadzone(Vec2::new(0.1, 0.1), 0.15, 0.95), Vec2::ZERO);
+    }
+
+    #[test]
+    fn radial_deadzone_rescales_and_keeps_direction() {
+        let out = radial_deadzone(Vec2::new(0.55, 0.0), 0.15, 0.95);
+        assert!((out.x - 0.5).abs() < 1e-5);
+        assert_eq!(out.y, 0.0);
+
+        let diagonal = Vec2::new(1.0, 1.0).normalize() * 0.55;
+        let out = radial_deadzone(diagonal, 0.15, 0.95);
+        assert!((out.length() - 0.5).abs() < 1e-5);
+        assert!((out.x - out.y).abs() < 1e-6);
+    }
+
+    #[test]
+    fn radial_deadzone_saturates_past_outer_edge() {
+        let out = radial_deadzone(Vec2::new(0.0, -1.0), 0.15, 0.95);
+        assert_eq!(out, Vec2::new(0.0, -1.0));
+    }
+}
@@ -2,10 +2,14 @@ use std::any::TypeId;
 
 use bevy::{
     animation::{AnimatedBy, AnimationEntityMut, AnimationEvaluationError, AnimationTargetId},
-    input::keyboard::Key,
+    input::{
+        mouse::{AccumulatedMouseMotion, MouseScrollUnit, MouseWheel},
+        touch::Touch,
+    },
     picking::pointer::PointerId,
     platform::collections::HashSet,
     prelude::*,
+    window::{CursorGrabMode, CursorOptions, PrimaryWindow},
 };
 
 use crab_feast_ui_joysticks::{
@@ -16,10 +20,14 @@ use crab_feast_ui_joysticks::{
 use crab_feast_ui_joysticks::JoystickMarionettePlugin;
 
 use crate::{
-    input::{LookInput, MovementInput},
-    utils::is_non_mobile,
+    actions::{Action, ActionInput, AxisAction, AxisBinding},
+    input::{ActiveInputDevice, JumpInput, LocalControl, LookInput, MovementInput, ZoomInput},
+    look::{LookSettings, LookSource},
+    utils::{is_mobile, is_non_mobile},
 };
 
+use super::gamepad_layer::track_active_input_device;
+
 pub struct InputPlugin;
 
 #[derive(Resource, Debug, Default)]
@@ -37,12 +45,56 @@ struct JoystickFadeAnimatePlayer {
 #[derive(Component)]
 struct MoveInputJoystick;
 
+/// 键盘（及绑定到移动动作的手柄十字键）直接写入本机玩家 [`MovementInput`] 时的参数。
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct KeyboardMovementSettings {
+    /// 步行时的输入力度（0..1，与摇杆推杆幅度同义）。
+    pub walk_force: f32,
+    /// 按住 [`Action::Sprint`] 时的输入力度。
+    pub run_force: f32,
+    /// 力度从 0 升到 1 所需时间（秒）；0 表示立即到位。松开或降速立即生效。
+    pub ramp_up_secs: f32,
+    /// 屏幕摇杆是否跟随显示键盘输入；仅作视觉反馈，不参与移动。
+    pub mirror_joystick: bool,
+}
+
+impl Default for KeyboardMovementSettings {
+    fn default() -> Self {
+        Self {
+            walk_force: 0.65,
+            run_force: 1.0,
+            ramp_up_secs: 0.15,
+            mirror_joystick: true,
+        }
+    }
+}
+
+#[derive(Component)]
+struct JumpButton;
+
 impl Plugin for InputPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(JoystickPlugin)
             .init_resource::<LookInputIgnorePointers>()
+            .init_resource::<KeyboardMovementSettings>()
             .add_systems(OnEnter(crate::GameState::Game), Self::setup)
-            .add_systems(PreUpdate, on_keyboard_event.run_if(is_non_mobile));
+            .add_systems(
+                PreUpdate,
+                (
+                    on_keyboard_movement,
+                    on_mouse_wheel,
+                    // 菜单里的点击不应锁定光标
+                    grab_cursor.run_if(in_state(crate::GameState::Game)),
+                    on_mouse_motion,
+                )
+                    .after(track_active_input_device)
+                    .run_if(is_non_mobile),
+            )
+            .add_systems(
+                PreUpdate,
+                (on_jump_action, on_pinch).after(track_active_input_device),
+            )
+            .add_systems(Update, sync_touch_controls_visibility);
 
         #[cfg(not(any(target_os = "android", target_os = "ios")))]
         app.add_plugins(JoystickMarionettePlugin);
@@ -155,26 +207,75 @@ impl InputPlugin {
                 MoveInputJoystick,
             ))
             .observe(on_joystick_event);
+
+        if is_mobile() {
+            // 右下角跳跃按钮；输入层是 FlexStart 排列，这里用绝对定位避免挤占摇杆位置
+            commands
+                .spawn((
+                    Node {
+                        position_type: PositionType::Absolute,
+                        right: Val::Vw(8.0),
+                        bottom: Val::Vw(8.0),
+                        width: Val::Vw(8.0),
+                        height: Val::Vw(8.0),
+                        border: UiRect::all(Val::Percent(0.05)),
+                        border_radius: BorderRadius::all(Val::Percent(50.0)),
+                        ..Default::default()
+                    },
+                    BackgroundColor(joystick_idle_color),
+                    BorderColor::all(Color::hsla(0.0, 1.0, 1.0, 0.2)),
+                    ChildOf(input_layer_entity),
+                    JumpButton,
+                ))
+                .observe(on_jump_button_press)
+                .observe(on_jump_button_release);
+        }
     }
 }
 
+fn on_jump_button_press(
+    mut event: On<Pointer<Press>>,
+    mut control: LocalControl,
+    mut look_ignore_pointers: ResMut<LookInputIgnorePointers>,
+) {
+    // 不冒泡到输入层，否则右半屏按下会被当作视角拖拽
+    event.propagate(false);
+    look_ignore_pointers.0.insert(event.pointer_id);
+    control.update(|intent| intent.jump = JumpInput::Activated);
+}
+
+fn on_jump_button_release(
+    mut event: On<Pointer<Release>>,
+    mut look_ignore_pointers: ResMut<LookInputIgnorePointers>,
+) {
+    event.propagate(false);
+    look_ignore_pointers.0.remove(&event.pointer_id);
+}
+
 fn on_joystick_event(
     joystick_event: On<JoystickEvent>,
     mut joystick_fade_animate_player_query: Query<(
         &mut AnimationPlayer,
         &JoystickFadeAnimatePlayer,
     )>,
-    mut move_input_state: ResMut<MovementInput>,
+    mirrored_joysticks: Query<(), With<JoystickMarionette>>,
+    mut control: LocalControl,
     mut look_ignore_pointers: ResMut<LookInputIgnorePointers>,
 ) {
+    // 键盘镜像驱动的摇杆只做视觉反馈，移动输入已由 on_keyboard_movement 写入
+    let mirrored = mirrored_joysticks.contains(joystick_event.entity);
     match joystick_event.event {
         JoystickInteraction::Activated(pointer_id) => {
             // println!("Joystick activated: {:?}", joystick_event.entity);
-            look_ignore_pointers.0.insert(pointer_id);
-            *move_input_state = MovementInput::Activated {
-                direction: Vec2::ZERO,
-                force: 0.0,
-            };
+            if !mirrored {
+                look_ignore_pointers.0.insert(pointer_id);
+                control.update(|intent| {
+                    intent.movement = MovementInput::Activated {
+                        direction: Vec2::ZERO,
+                        force: 0.0,
+                    };
+                });
+            }
 
             joystick_fade_animate_player_query.iter_mut().for_each(
                 |(mut animation_player, joystick_fade_animate_player)| {
@@ -186,14 +287,19 @@ fn on_joystick_event(
         }
         JoystickInteraction::Moved(new_direction, new_force) => {
             // println!("Joystick moved: {:?}", joystick_event.entity);
-            if let MovementInput::Activated { direction, force } = move_input_state.as_mut() {
-                *direction = new_direction;
-                *force = new_force;
+            if mirrored {
+                return;
             }
+            control.update(|intent| {
+                if let MovementInput::Activated { direction, force } = &mut intent.movement {
+                    *direction = new_direction;
+                    *force = new_force;
+                }
+            });
         }
         JoystickInteraction::Deactivated(pointer_id) => {
             // println!("Joystick deactivated: {:?}", joystick_event.entity);
-            *move_input_state = MovementInput::Idle;
+            control.update(|intent| intent.movement = MovementInput::Idle);
             look_ignore_pointers.0.remove(&pointer_id);
         }
         JoystickInteraction::Rebound => {
@@ -287,6 +393,8 @@ fn on_rotate_plane_release(
 fn on_rotate_plane_drag(
     event: On<Pointer<Drag>>,
     mut commands: Commands,
+    settings: Res<LookSettings>,
+    touches: Res<Touches>,
     look_ignore_pointers: ResMut<LookInputIgnorePointers>,
     target_camera_query: Query<&ComputedUiTargetCamera>,
     camera_query: Query<&Camera>,
@@ -295,83 +403,216 @@ fn on_rotate_plane_drag(
         return;
     }
 
+    if event.pointer_id.is_mouse() {
+        // 锁定光标时由原始鼠标位移驱动视角，拖拽不再重复计入
+        if !settings.grab_cursor {
+            commands.trigger(LookInput {
+                delta: event.delta,
+                source: LookSource::Mouse,
+            });
+        }
+        return;
+    }
+
+    // 双指捏合时只缩放，不转动视角
+    if look_touches(&touches, &look_ignore_pointers).count() > 1 {
+        return;
+    }
+
+    // 触摸按逻辑视口宽度归一化，与屏幕尺寸、像素密度无关
     let scaled_delta = target_camera_query
         .get(event.entity)
         .ok()
         .and_then(|target| target.get())
         .and_then(|camera_entity| camera_query.get(camera_entity).ok())
-        .map(|camera| {
-            camera
-                .physical_viewport_size()
-                .map(|viewport_size| event.delta / viewport_size.x as f32)
-                .unwrap_or(event.delta)
-        });
+        .and_then(|camera| camera.logical_viewport_size())
+        .filter(|viewport_size| viewport_size.x > 0.0)
+        .map(|viewport_size| event.delta / viewport_size.x);
     if let Some(delta) = scaled_delta {
-        commands.trigger(LookInput(delta));
+        commands.trigger(LookInput {
+            delta,
+            source: LookSource::Touch,
+        });
     }
 }
 
-fn on_keyboard_event(
+/// 数字移动（键盘方向键 / 手柄十字键，见 [`Action::MoveForward`] 等）：直接写入本机玩家的
+/// [`MovementInput`]，力度按 [`KeyboardMovementSettings::ramp_up_secs`] 爬升；
+/// 屏幕摇杆仅通过摇杆木偶镜像显示。
+fn on_keyboard_movement(
     mut commands: Commands,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    key_input: Res<ButtonInput<Key>>,
-    move_input_joystick_query: Query<Entity, With<MoveInputJoystick>>,
-    mut joystick_marionette_query: Query<(Entity, &mut JoystickMarionette)>,
+    time: Res<Time>,
+    actions: ActionInput,
+    settings: Res<KeyboardMovementSettings>,
+    mut control: LocalControl,
+    mut current_force: Local<Option<f32>>,
+    mut joysticks: Query<(Entity, Option<&mut JoystickMarionette>), With<MoveInputJoystick>>,
 ) {
-    let walk_speed = 0.65;
-    let run_speed = 1.0;
-
-    let mut direction = Vec2::ZERO;
-    let mut speed = walk_speed;
-
-    let is_pressed = [
-        KeyCode::KeyW,
-        KeyCode::KeyA,
-        KeyCode::KeyS,
-        KeyCode::KeyD,
-        KeyCode::ArrowUp,
-        KeyCode::ArrowLeft,
-        KeyCode::ArrowDown,
-        KeyCode::ArrowRight,
-    ]
-    .iter()
-    .any(|&key| keyboard_input.pressed(key));
-    if !is_pressed {
-        joystick_marionette_query.iter().for_each(|(entity, ..)| {
-            commands.entity(entity).remove::<JoystickMarionette>();
+    if !actions.moving() {
+        // 只在松开的那一帧复位，避免覆盖触摸 / 手柄写入的输入
+        if current_force.take().is_some() {
+            control.update(|intent| intent.movement = MovementInput::Idle);
+        }
+        joysticks.iter().for_each(|(entity, marionette)| {
+            if marionette.is_some() {
+                commands.entity(entity).remove::<JoystickMarionette>();
+            }
         });
         return;
     }
 
-    [
-        ((KeyCode::KeyW, KeyCode::ArrowUp), -Vec2::Y),
-        ((KeyCode::KeyS, KeyCode::ArrowDown), Vec2::Y),
-        ((KeyCode::KeyA, KeyCode::ArrowLeft), -Vec2::X),
-        ((KeyCode::KeyD, KeyCode::ArrowRight), Vec2::X),
-    ]
-    .iter()
-    .for_each(|&(keys, offset)| {
-        if keyboard_input.pressed(keys.0) || keyboard_input.pressed(keys.1) {
-            direction += offset;
+    let direction = actions.move_direction();
+    let target_force = if direction == Vec2::ZERO {
+        0.0
+    } else if actions.pressed(Action::Sprint) {
+        settings.run_force
+    } else {
+        settings.walk_force
+    };
+    let previous = current_force.unwrap_or(0.0);
+    let force = if target_force <= previous || settings.ramp_up_secs <= 0.0 {
+        target_force
+    } else {
+        (previous + time.delta_secs() / settings.ramp_up_secs).min(target_force)
+    };
+    *current_force = Some(force);
+    control.update(|intent| intent.movement = MovementInput::Activated { direction, force });
+
+    for (entity, marionette) in &mut joysticks {
+        match marionette {
+            Some(_) if !settings.mirror_joystick => {
+                commands.entity(entity).remove::<JoystickMarionette>();
+            }
+            Some(mut marionette) => {
+                marionette.direction = direction;
+                marionette.force = force;
+            }
+            None if settings.mirror_joystick => {
+                commands.entity(entity).insert(JoystickMarionette {
+                    direction,
+                    force,
+                    ..Default::default()
+                });
+            }
+            None => {}
         }
-    });
+    }
+}
+
+fn on_jump_action(actions: ActionInput, mut control: LocalControl) {
+    if actions.just_pressed(Action::Jump) {
+        control.update(|intent| intent.jump = JumpInput::Activated);
+    }
+}
 
-    if key_input.pressed(Key::Shift) {
-        speed = run_speed;
+/// 每行滚轮对应的缩放量（对数比例）；触控板按像素滚动时用 `WHEEL_ZOOM_PER_PIXEL`。
+const WHEEL_ZOOM_PER_LINE: f32 = 0.1;
+const WHEEL_ZOOM_PER_PIXEL: f32 = 0.002;
+
+fn on_mouse_wheel(mut commands: Commands, mut wheel: MessageReader<MouseWheel>) {
+    let zoom: f32 = wheel
+        .read()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y * WHEEL_ZOOM_PER_LINE,
+            MouseScrollUnit::Pixel => event.y * WHEEL_ZOOM_PER_PIXEL,
+        })
+        .sum();
+    if zoom != 0.0 {
+        commands.trigger(ZoomInput(zoom));
     }
+}
 
-    direction = direction.normalize_or_zero();
+/// 可用于视角与捏合的触点：排除摇杆、跳跃按钮等占用的触点（见 [`LookInputIgnorePointers`]）。
+fn look_touches<'a>(
+    touches: &'a Touches,
+    look_ignore_pointers: &'a LookInputIgnorePointers,
+) -> impl Iterator<Item = &'a Touch> {
+    touches.iter().filter(move |touch| {
+        !look_ignore_pointers
+            .0
+            .contains(&PointerId::Touch(touch.id()))
+    })
+}
 
-    move_input_joystick_query.iter().for_each(|entity| {
-        if let Ok((_, mut joystick_marionette)) = joystick_marionette_query.get_mut(entity) {
-            joystick_marionette.direction = direction;
-            joystick_marionette.force = speed;
-        } else {
-            commands.entity(entity).insert(JoystickMarionette {
-                direction: direction,
-                force: speed,
-                ..Default::default()
-            });
+/// 双指捏合：两指距离变化的对数比例作为缩放量（张开为拉近）。
+/// 摇杆、跳跃按钮等占用的触点（见 [`LookInputIgnorePointers`]）不参与捏合。
+fn on_pinch(
+    mut commands: Commands,
+    touches: Res<Touches>,
+    look_ignore_pointers: Res<LookInputIgnorePointers>,
+    mut last_distance: Local<Option<f32>>,
+) {
+    let mut pressed = look_touches(&touches, &look_ignore_pointers);
+    let (Some(a), Some(b), None) = (pressed.next(), pressed.next(), pressed.next()) else {
+        *last_distance = None;
+        return;
+    };
+    let distance = a.position().distance(b.position());
+    if let Some(last) = last_distance.filter(|d| *d > f32::EPSILON) {
+        if distance > f32::EPSILON {
+            commands.trigger(ZoomInput((distance / last).ln()));
         }
-    });
+    }
+    *last_distance = Some(distance);
+}
+
+/// [`Action::GrabCursor`]（默认鼠标左键）锁定并隐藏光标，[`Action::Pause`]（默认 Esc）释放
+/// （Windows 不支持 `Locked`，改用 `Confined`）。
+fn grab_cursor(
+    settings: Res<LookSettings>,
+    actions: ActionInput,
+    mut cursor: Query<&mut CursorOptions, With<PrimaryWindow>>,
+) {
+    let Ok(mut cursor) = cursor.single_mut() else {
+        return;
+    };
+    let grabbed = cursor.grab_mode != CursorGrabMode::None;
+    if grabbed && (actions.just_pressed(Action::Pause) || !settings.grab_cursor) {
+        cursor.grab_mode = CursorGrabMode::None;
+        cursor.visible = true;
+    } else if !grabbed && settings.grab_cursor && actions.just_pressed(Action::GrabCursor) {
+        cursor.grab_mode = if cfg!(target_os = "windows") {
+            CursorGrabMode::Confined
+        } else {
+            CursorGrabMode::Locked
+        };
+        cursor.visible = false;
+    }
+}
+
+/// 光标锁定且 [`AxisAction::Look`] 绑定了鼠标时，把原始鼠标位移（像素）作为视角输入。
+fn on_mouse_motion(
+    mut commands: Commands,
+    actions: ActionInput,
+    motion: Res<AccumulatedMouseMotion>,
+    cursor: Query<&CursorOptions, With<PrimaryWindow>>,
+) {
+    let bound = actions
+        .map()
+        .axis_bindings(AxisAction::Look)
+        .contains(&AxisBinding::MouseMotion);
+    let grabbed = cursor
+        .single()
+        .is_ok_and(|cursor| cursor.grab_mode != CursorGrabMode::None);
+    if bound && grabbed && motion.delta != Vec2::ZERO {
+        commands.trigger(LookInput {
+            delta: motion.delta,
+            source: LookSource::Mouse,
+        });
+    }
+}
+
+/// 手柄活跃时隐藏屏幕摇杆与跳跃按钮，其他设备重新产生输入后恢复显示。
+fn sync_touch_controls_visibility(
+    active_device: Res<ActiveInputDevice>,
+    mut controls: Query<&mut Visibility, Or<(With<MoveInputJoystick>, With<JumpButton>)>>,
+) {
+    let visibility = if matches!(*active_device, ActiveInputDevice::Gamepad(_)) {
+        Visibility::Hidden
+    } else {
+        Visibility::Inherited
+    };
+    for mut control in &mut controls {
+        control.set_if_neq(visibility);
+    }
 }
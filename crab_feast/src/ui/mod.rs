@@ -1,13 +1,17 @@
 use bevy::app::{App, Plugin};
 
+mod gamepad_layer;
 mod input_layer;
 mod loading;
+mod net_menu;
 
 pub struct UiPlugin;
 
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(input_layer::InputPlugin)
-            .add_plugins(loading::LoadingUiPlugin);
+            .add_plugins(gamepad_layer::GamepadInputPlugin)
+            .add_plugins(loading::LoadingUiPlugin)
+            .add_plugins(net_menu::NetMenuPlugin);
     }
 }
@@ -0,0 +1,292 @@
+//! Start menu: play offline, host a game, or join one at the typed address.
+//!
+//! Joining stays on the menu until the connection is up, and shows why if it fails.
+//!
+//! Skipped when the launcher already chose a [`NetMode`] (`--host`, `--connect` or the
+//! environment).
+
+use std::net::{IpAddr, Ipv4Addr};
+
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::prelude::*;
+use bevy::winit::{UpdateMode, WinitSettings};
+use crab_feast_library::net::{parse_addr, ConnectionState, NetConfig, NetMode};
+
+use crate::GameState;
+
+/// Longest address the field accepts; a bracketed IPv6 address with port fits.
+const MAX_ADDRESS_LEN: usize = 48;
+const BUTTON_COLOR: Color = Color::srgb(0.15, 0.15, 0.2);
+const BUTTON_HOVER_COLOR: Color = Color::srgb(0.25, 0.25, 0.35);
+const STATUS_COLOR: Color = Color::srgb(0.8, 0.8, 0.8);
+const ERROR_COLOR: Color = Color::srgb(1.0, 0.4, 0.4);
+
+pub struct NetMenuPlugin;
+
+impl Plugin for NetMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Menu), setup_net_menu)
+            .add_systems(OnExit(GameState::Menu), cleanup_net_menu)
+            .add_systems(
+                Update,
+                (edit_address, press_menu_buttons, follow_connection)
+                    .chain()
+                    // Not there when the launcher chose the mode.
+                    .run_if(resource_exists::<NetMenu>),
+            );
+    }
+}
+
+#[derive(Resource)]
+struct NetMenu {
+    root: Entity,
+    /// `ip` or `ip:port`; hosting only uses the port.
+    address: String,
+}
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+enum MenuButton {
+    Offline,
+    Host,
+    Join,
+}
+
+#[derive(Component)]
+struct AddressText;
+
+#[derive(Component)]
+struct StatusText;
+
+fn setup_net_menu(
+    mut commands: Commands,
+    net_mode: Res<State<NetMode>>,
+    config: Res<NetConfig>,
+    mut next_game: ResMut<NextState<GameState>>,
+) {
+    if *net_mode.get() != NetMode::None {
+        next_game.set(GameState::Game);
+        return;
+    }
+    let address = config.addr().to_string();
+
+    let root = commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(16.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.9)),
+            GlobalZIndex(i32::MAX),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("Crab Feast"),
+                TextFont {
+                    font_size: 48.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Px(360.0),
+                        padding: UiRect::all(Val::Px(8.0)),
+                        border: UiRect::all(Val::Px(2.0)),
+                        ..default()
+                    },
+                    BorderColor::all(Color::WHITE),
+                ))
+                .with_child((
+                    Text::new(address.clone()),
+                    TextFont {
+                        font_size: 24.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                    AddressText,
+                ));
+            for (button, label) in [
+                (MenuButton::Offline, "Play offline"),
+                (MenuButton::Host, "Host"),
+                (MenuButton::Join, "Join"),
+            ] {
+                parent
+                    .spawn((
+                        Button,
+                        Node {
+                            width: Val::Px(360.0),
+                            padding: UiRect::all(Val::Px(10.0)),
+                            justify_content: JustifyContent::Center,
+                            ..default()
+                        },
+                        BackgroundColor(BUTTON_COLOR),
+                        button,
+                    ))
+                    .with_child((
+                        Text::new(label),
+                        TextFont {
+                            font_size: 28.0,
+                            ..default()
+                        },
+                        TextColor(Color::WHITE),
+                    ));
+            }
+            parent.spawn((
+                Text::default(),
+                TextFont {
+                    font_size: 20.0,
+                    ..default()
+                },
+                TextColor(ERROR_COLOR),
+                StatusText,
+            ));
+        })
+        .id();
+
+    commands.insert_resource(NetMenu { root, address });
+}
+
+fn cleanup_net_menu(mut commands: Commands, menu: Option<Res<NetMenu>>) {
+    if let Some(menu) = menu {
+        commands.entity(menu.root).despawn();
+        commands.remove_resource::<NetMenu>();
+    }
+}
+
+/// Typing edits the address; only characters that can appear in one are taken.
+fn edit_address(
+    mut keyboard: MessageReader<KeyboardInput>,
+    mut menu: ResMut<NetMenu>,
+    mut texts: Query<&mut Text, With<AddressText>>,
+) {
+    let mut changed = false;
+    for input in keyboard.read() {
+        if !input.state.is_pressed() {
+            continue;
+        }
+        match &input.logical_key {
+            Key::Character(characters) => {
+                for c in characters.chars() {
+                    if menu.address.len() < MAX_ADDRESS_LEN
+                        && (c.is_ascii_hexdigit() || matches!(c, '.' | ':' | '[' | ']'))
+                    {
+                        menu.address.push(c);
+                        changed = true;
+                    }
+                }
+            }
+            Key::Backspace => {
+                changed |= menu.address.pop().is_some();
+            }
+            _ => {}
+        }
+    }
+    if changed {
+        for mut text in &mut texts {
+            text.0.clone_from(&menu.address);
+        }
+    }
+}
+
+fn set_status(
+    statuses: &mut Query<(&mut Text, &mut TextColor), With<StatusText>>,
+    text: String,
+    color: Color,
+) {
+    for (mut status, mut status_color) in statuses.iter_mut() {
+        status.0.clone_from(&text);
+        status_color.0 = color;
+    }
+}
+
+/// Writes the [`NetConfig`] for the chosen button and sets [`NetMode`]; offline and hosting
+/// start the game right away, joining waits in [`follow_connection`].
+fn press_menu_buttons(
+    menu: Res<NetMenu>,
+    mut buttons: Query<(&Interaction, &MenuButton, &mut BackgroundColor), Changed<Interaction>>,
+    mut statuses: Query<(&mut Text, &mut TextColor), With<StatusText>>,
+    mut config: ResMut<NetConfig>,
+    mut next_mode: ResMut<NextState<NetMode>>,
+    mut next_game: ResMut<NextState<GameState>>,
+    winit: Option<ResMut<WinitSettings>>,
+) {
+    let mut chosen = None;
+    for (interaction, button, mut color) in &mut buttons {
+        color.0 = match interaction {
+            Interaction::Hovered => BUTTON_HOVER_COLOR,
+            _ => BUTTON_COLOR,
+        };
+        if *interaction == Interaction::Pressed {
+            chosen = Some(*button);
+        }
+    }
+    let Some(button) = chosen else {
+        return;
+    };
+    let (mode, addr) = match button {
+        MenuButton::Offline => {
+            next_game.set(GameState::Game);
+            return;
+        }
+        MenuButton::Host => (NetMode::Server, parse_addr(&menu.address, config.port())),
+        MenuButton::Join => (NetMode::Client, parse_addr(&menu.address, config.port())),
+    };
+    let addr = match addr {
+        Ok(addr) => addr,
+        Err(e) => {
+            set_status(&mut statuses, e.to_string(), ERROR_COLOR);
+            return;
+        }
+    };
+    let ip = match mode {
+        // Listen on every interface so the LAN can join.
+        NetMode::Server => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        _ => addr.ip(),
+    };
+    *config = std::mem::take(&mut *config)
+        .with_ip(ip)
+        .with_port(addr.port());
+    // A networked game must keep ticking while another window has focus.
+    if let Some(mut winit) = winit {
+        winit.unfocused_mode = UpdateMode::Continuous;
+    }
+    next_mode.set(mode);
+    if mode == NetMode::Server {
+        next_game.set(GameState::Game);
+    } else {
+        set_status(
+            &mut statuses,
+            format!("Connecting to {addr}..."),
+            STATUS_COLOR,
+        );
+    }
+}
+
+/// While joining, starts the game once connected, or shows why the attempt failed.
+fn follow_connection(
+    connection: Res<ConnectionState>,
+    mut statuses: Query<(&mut Text, &mut TextColor), With<StatusText>>,
+    mut next_game: ResMut<NextState<GameState>>,
+) {
+    if !connection.is_changed() {
+        return;
+    }
+    match &*connection {
+        ConnectionState::Connected => next_game.set(GameState::Game),
+        ConnectionState::Reconnecting { attempt, .. } => set_status(
+            &mut statuses,
+            format!("Connection failed, retrying (attempt {attempt})..."),
+            STATUS_COLOR,
+        ),
+        ConnectionState::Failed { reason } => {
+            set_status(&mut statuses, reason.clone(), ERROR_COLOR)
+        }
+        _ => {}
+    }
+}
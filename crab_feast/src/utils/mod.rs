@@ -1,3 +1,5 @@
+use bevy::prelude::*;
+
 pub fn is_mobile() -> bool {
     cfg!(target_os = "android") || cfg!(target_os = "ios")
 }
@@ -5,3 +7,26 @@ pub fn is_mobile() -> bool {
 pub fn is_non_mobile() -> bool {
     !is_mobile()
 }
+
+/// 深度优先查找名为 `target` 的后代（含 `start` 自身），用于按骨骼名定位 glTF 节点。
+pub fn find_descendant_by_name(
+    start: Entity,
+    children: &Query<&Children>,
+    names: &Query<&Name>,
+    target: &str,
+) -> Option<Entity> {
+    let mut stack = vec![start];
+    while let Some(e) = stack.pop() {
+        if let Ok(n) = names.get(e) {
+            if n.as_str() == target {
+                return Some(e);
+            }
+        }
+        if let Ok(kids) = children.get(e) {
+            for c in kids.iter() {
+                stack.push(c);
+            }
+        }
+    }
+    None
+}
@@ -2,4 +2,5 @@
 
 // pub mod ui;
 pub mod utils;
-// pub mod net;
+pub mod nav;
+pub mod net;
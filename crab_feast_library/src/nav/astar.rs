@@ -0,0 +1,298 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use bevy::prelude::*;
+
+use super::WalkabilityMap;
+
+/// How far (in cells) a start or goal inside an obstacle is moved to the closest free cell.
+pub const SNAP_RADIUS_CELLS: i32 = 3;
+
+const NEIGHBOURS: [IVec2; 8] = [
+    IVec2::new(1, 0),
+    IVec2::new(-1, 0),
+    IVec2::new(0, 1),
+    IVec2::new(0, -1),
+    IVec2::new(1, 1),
+    IVec2::new(1, -1),
+    IVec2::new(-1, 1),
+    IVec2::new(-1, -1),
+];
+
+#[derive(Clone, Copy, PartialEq)]
+struct OpenNode {
+    estimate: f32,
+    index: usize,
+}
+
+impl Eq for OpenNode {}
+
+impl Ord for OpenNode {
+    // Reversed so the max-heap pops the smallest estimate first.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .estimate
+            .total_cmp(&self.estimate)
+            .then_with(|| other.index.cmp(&self.index))
+    }
+}
+
+impl PartialOrd for OpenNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Octile distance in cells: exact cost of an unobstructed 8-connected walk.
+fn octile(a: IVec2, b: IVec2) -> f32 {
+    let d = (a - b).abs();
+    let (long, short) = (d.max_element() as f32, d.min_element() as f32);
+    long - short + short * std::f32::consts::SQRT_2
+}
+
+/// 8-connected A* over the walkable cells of `map`, from `start` to `goal` in world units.
+///
+/// Diagonal steps never cut the corner of a blocked cell. A start or goal that falls in a
+/// blocked cell snaps to the nearest free cell within [`SNAP_RADIUS_CELLS`]. The returned
+/// polyline runs from `start` through cell centres to `goal` (or the snapped goal cell's
+/// centre); `None` when the goal is unreachable or outside the map.
+pub fn find_path(map: &WalkabilityMap, start: Vec2, goal: Vec2) -> Option<Vec<Vec2>> {
+    let start_cell = map.nearest_walkable(map.cell_at(start)?, SNAP_RADIUS_CELLS)?;
+    let goal_requested = map.cell_at(goal)?;
+    let goal_cell = map.nearest_walkable(goal_requested, SNAP_RADIUS_CELLS)?;
+
+    let width = map.size().x as usize;
+    let index = |cell: IVec2| cell.y as usize * width + cell.x as usize;
+    let cell_of = |index: usize| IVec2::new((index % width) as i32, (index / width) as i32);
+    let cells = width * map.size().y as usize;
+
+    let mut cost = vec![f32::INFINITY; cells];
+    let mut came_from = vec![usize::MAX; cells];
+    let mut open = BinaryHeap::new();
+    cost[index(start_cell)] = 0.0;
+    open.push(OpenNode {
+        estimate: octile(start_cell, goal_cell),
+        index: index(start_cell),
+    });
+
+    while let Some(OpenNode {
+        estimate,
+        index: current,
+    }) = open.pop()
+    {
+        let cell = cell_of(current);
+        if cell == goal_cell {
+            let mut cells = vec![cell];
+            let mut at = current;
+            while came_from[at] != usize::MAX {
+                at = came_from[at];
+                cells.push(cell_of(at));
+            }
+            cells.reverse();
+
+            let mut path = vec![start];
+            if !map.is_walkable_at(start) {
+                path.push(map.cell_center(start_cell));
+            }
+            let interior = cells.len().saturating_sub(1).max(1);
+            path.extend(cells[1..interior].iter().map(|c| map.cell_center(*c)));
+            path.push(if goal_cell == goal_requested {
+                goal
+            } else {
+                map.cell_center(goal_cell)
+            });
+            path.dedup();
+            return Some(path);
+        }
+        // Stale heap entry: a cheaper route to this cell was already expanded.
+        if estimate > cost[current] + octile(cell, goal_cell) + 1e-4 {
+            continue;
+        }
+
+        for offset in NEIGHBOURS {
+            let next = cell + offset;
+            if !map.is_walkable(next) {
+                continue;
+            }
+            let diagonal = offset.x != 0 && offset.y != 0;
+            if diagonal
+                && (!map.is_walkable(IVec2::new(next.x, cell.y))
+                    || !map.is_walkable(IVec2::new(cell.x, next.y)))
+            {
+                continue;
+            }
+            let step = if diagonal {
+                std::f32::consts::SQRT_2
+            } else {
+                1.0
+            };
+            let next_cost = cost[current] + step;
+            let next_index = index(next);
+            if next_cost < cost[next_index] {
+                cost[next_index] = next_cost;
+                came_from[next_index] = current;
+                open.push(OpenNode {
+                    estimate: next_cost + octile(next, goal_cell),
+                    index: next_index,
+                });
+            }
+        }
+    }
+    None
+}
+
+/// Whether the straight segment `from`→`to` stays on walkable cells, sampled every quarter
+/// cell.
+pub fn line_of_sight(map: &WalkabilityMap, from: Vec2, to: Vec2) -> bool {
+    let length = from.distance(to);
+    let steps = (length / (map.cell_size() * 0.25)).ceil().max(1.0) as usize;
+    (0..=steps).all(|i| map.is_walkable_at(from.lerp(to, i as f32 / steps as f32)))
+}
+
+/// String pulling: drops every waypoint that the previous kept waypoint can see past, so
+/// the staircase of cell centres becomes a few straight legs. Endpoints are kept.
+pub fn smooth_path(map: &WalkabilityMap, path: &[Vec2]) -> Vec<Vec2> {
+    let Some((&first, rest)) = path.split_first() else {
+        return Vec::new();
+    };
+    let mut smoothed = vec![first];
+    let mut anchor = first;
+    for (i, &point) in rest.iter().enumerate() {
+        let Some(&next) = rest.get(i + 1) else {
+            smoothed.push(point);
+            break;
+        };
+        if !line_of_sight(map, anchor, next) {
+            smoothed.push(point);
+            anchor = point;
+        }
+    }
+    smoothed
+}
+
+#[cfg(test)]
+mod astar_tests {
+    use bevy::math::bounding::Aabb2d;
+
+    use super::*;
+
+    fn open_map() -> WalkabilityMap {
+        WalkabilityMap::new(Aabb2d::new(Vec2::splat(5.0), Vec2::splat(5.0)), 1.0, 0.0)
+    }
+
+    /// Vertical wall at x in 4..6 from y = 0 to y = 8, leaving a gap at the top.
+    fn wall_map() -> WalkabilityMap {
+        WalkabilityMap::from_footprints(
+            Aabb2d::new(Vec2::splat(5.0), Vec2::splat(5.0)),
+            1.0,
+            0.0,
+            [Aabb2d {
+                min: Vec2::new(4.0, 0.0),
+                max: Vec2::new(6.0, 8.0),
+            }],
+        )
+        .unwrap()
+    }
+
+    fn length(path: &[Vec2]) -> f32 {
+        path.windows(2).map(|w| w[0].distance(w[1])).sum()
+    }
+
+    fn assert_walkable(map: &WalkabilityMap, path: &[Vec2]) {
+        for leg in path.windows(2) {
+            assert!(
+                line_of_sight(map, leg[0], leg[1]),
+                "{:?} → {:?}",
+                leg[0],
+                leg[1]
+            );
+        }
+    }
+
+    #[test]
+    fn test_straight_path_in_open_map() {
+        let map = open_map();
+        let start = Vec2::new(0.5, 0.5);
+        let goal = Vec2::new(8.5, 0.5);
+        let path = find_path(&map, start, goal).unwrap();
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&goal));
+        assert!((length(&path) - 8.0).abs() < 1e-4);
+        assert_eq!(smooth_path(&map, &path), vec![start, goal]);
+    }
+
+    #[test]
+    fn test_path_goes_around_wall() {
+        let map = wall_map();
+        let start = Vec2::new(1.5, 1.5);
+        let goal = Vec2::new(8.5, 1.5);
+        let path = find_path(&map, start, goal).unwrap();
+        assert_walkable(&map, &path);
+        assert!(path.iter().any(|p| p.y > 8.0));
+        for point in &path {
+            assert!(map.is_walkable_at(*point), "{point:?}");
+        }
+
+        let smoothed = smooth_path(&map, &path);
+        assert_walkable(&map, &smoothed);
+        assert!(smoothed.len() < path.len());
+        assert!(length(&smoothed) <= length(&path) + 1e-4);
+        assert_eq!(smoothed.first(), Some(&start));
+        assert_eq!(smoothed.last(), Some(&goal));
+    }
+
+    #[test]
+    fn test_no_path_when_walled_off() {
+        let map = WalkabilityMap::from_footprints(
+            Aabb2d::new(Vec2::splat(5.0), Vec2::splat(5.0)),
+            1.0,
+            0.0,
+            [Aabb2d {
+                min: Vec2::new(4.0, 0.0),
+                max: Vec2::new(6.0, 10.0),
+            }],
+        )
+        .unwrap();
+        assert_eq!(
+            find_path(&map, Vec2::new(1.5, 1.5), Vec2::new(8.5, 1.5)),
+            None
+        );
+        assert_eq!(
+            find_path(&map, Vec2::new(1.5, 1.5), Vec2::new(20.0, 1.5)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_no_corner_cutting() {
+        // Two blocks touching only at a corner: the diagonal between them is closed.
+        let map = WalkabilityMap::from_footprints(
+            Aabb2d::new(Vec2::splat(1.5), Vec2::splat(1.5)),
+            1.0,
+            0.0,
+            [
+                Aabb2d::new(Vec2::new(1.5, 0.5), Vec2::splat(0.5)),
+                Aabb2d::new(Vec2::new(0.5, 1.5), Vec2::splat(0.5)),
+            ],
+        )
+        .unwrap();
+        let path = find_path(&map, Vec2::new(0.5, 0.5), Vec2::new(1.5, 1.5));
+        assert_eq!(path, None);
+    }
+
+    #[test]
+    fn test_blocked_goal_snaps_to_free_cell() {
+        let map = WalkabilityMap::from_footprints(
+            Aabb2d::new(Vec2::splat(5.0), Vec2::splat(5.0)),
+            1.0,
+            0.0,
+            [Aabb2d::new(Vec2::splat(5.0), Vec2::splat(1.0))],
+        )
+        .unwrap();
+        let goal = Vec2::new(5.0, 5.0);
+        let path = find_path(&map, Vec2::new(0.5, 0.5), goal).unwrap();
+        let end = *path.last().unwrap();
+        assert!(map.is_walkable_at(end));
+        assert!(end.distance(goal) < 2.5);
+    }
+}
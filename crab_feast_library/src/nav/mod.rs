@@ -0,0 +1,153 @@
+mod astar;
+mod walkability;
+
+pub use astar::*;
+pub use walkability::*;
+
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use bevy::tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task};
+
+/// Runs [`PathRequest`]s on the async compute pool against the current [`NavGrid`].
+pub struct NavPlugin;
+
+impl Plugin for NavPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (start_path_requests, poll_path_tasks).chain());
+    }
+}
+
+/// The walkability map path requests are solved on. Replace it when the level changes;
+/// requests already running keep the map they started with.
+#[derive(Resource, Clone)]
+pub struct NavGrid(pub Arc<WalkabilityMap>);
+
+/// Ask for a path on the [`NavGrid`] plane. Solved in the background; the request is
+/// replaced by a [`PathResult`] once done. Inserting a new request while one is running
+/// restarts it.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct PathRequest {
+    pub start: Vec2,
+    pub goal: Vec2,
+    /// Run [`smooth_path`] on the result.
+    pub smooth: bool,
+}
+
+impl PathRequest {
+    pub fn new(start: Vec2, goal: Vec2) -> Self {
+        Self {
+            start,
+            goal,
+            smooth: true,
+        }
+    }
+}
+
+#[derive(Component, Clone, Debug, PartialEq)]
+pub enum PathResult {
+    /// Waypoints from the start to the goal.
+    Found(Vec<Vec2>),
+    NotFound,
+}
+
+#[derive(Component)]
+struct PathTask(Task<PathResult>);
+
+fn solve(map: &WalkabilityMap, request: PathRequest) -> PathResult {
+    match find_path(map, request.start, request.goal) {
+        Some(path) if request.smooth => PathResult::Found(smooth_path(map, &path)),
+        Some(path) => PathResult::Found(path),
+        None => PathResult::NotFound,
+    }
+}
+
+fn start_path_requests(
+    mut commands: Commands,
+    grid: Option<Res<NavGrid>>,
+    requests: Query<(Entity, Ref<PathRequest>, Has<PathTask>)>,
+) {
+    // Requests wait until a grid exists.
+    let Some(grid) = grid else {
+        return;
+    };
+    let pool = AsyncComputeTaskPool::get();
+    for (entity, request, running) in &requests {
+        if running && !request.is_changed() {
+            continue;
+        }
+        let map = grid.0.clone();
+        let request = *request;
+        let task = pool.spawn(async move { solve(&map, request) });
+        // Replacing a running task drops it, which cancels it.
+        commands
+            .entity(entity)
+            .insert(PathTask(task))
+            .remove::<PathResult>();
+    }
+}
+
+fn poll_path_tasks(mut commands: Commands, mut tasks: Query<(Entity, &mut PathTask)>) {
+    for (entity, mut task) in &mut tasks {
+        if let Some(result) = block_on(future::poll_once(&mut task.0)) {
+            commands
+                .entity(entity)
+                .remove::<(PathTask, PathRequest)>()
+                .insert(result);
+        }
+    }
+}
+
+#[cfg(test)]
+mod nav_tests {
+    use bevy::math::bounding::Aabb2d;
+
+    use super::*;
+
+    #[test]
+    fn test_path_request_resolves_headless() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, NavPlugin));
+        app.insert_resource(NavGrid(Arc::new(
+            WalkabilityMap::from_footprints(
+                Aabb2d::new(Vec2::splat(5.0), Vec2::splat(5.0)),
+                0.5,
+                0.25,
+                [Aabb2d {
+                    min: Vec2::new(4.0, 0.0),
+                    max: Vec2::new(6.0, 8.0),
+                }],
+            )
+            .unwrap(),
+        )));
+        let start = Vec2::new(1.0, 1.0);
+        let goal = Vec2::new(9.0, 1.0);
+        let reachable = app.world_mut().spawn(PathRequest::new(start, goal)).id();
+        let unreachable = app
+            .world_mut()
+            .spawn(PathRequest::new(start, Vec2::new(50.0, 1.0)))
+            .id();
+
+        for _ in 0..1000 {
+            app.update();
+            let world = app.world();
+            if world.get::<PathResult>(reachable).is_some()
+                && world.get::<PathResult>(unreachable).is_some()
+            {
+                break;
+            }
+        }
+
+        let world = app.world();
+        let Some(PathResult::Found(path)) = world.get::<PathResult>(reachable) else {
+            panic!("no path found");
+        };
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&goal));
+        assert!(world.get::<PathRequest>(reachable).is_none());
+        assert_eq!(
+            world.get::<PathResult>(unreachable),
+            Some(&PathResult::NotFound)
+        );
+    }
+}
@@ -0,0 +1,249 @@
+use std::sync::Arc;
+
+use bevy::math::bounding::{Aabb2d, BoundingVolume};
+use bevy::prelude::*;
+
+use crate::utils::{QuadNode, QuadTreeData, QuadTreeError};
+
+/// Cells are slightly shrunk before testing overlap so an obstacle that only touches a cell
+/// edge does not block the neighbouring cell.
+const CELL_EDGE_EPSILON: f32 = 1e-4;
+
+/// Plan-view footprint of a static obstacle, already grown by the agent clearance.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Footprint(pub Aabb2d);
+
+impl QuadTreeData for Footprint {
+    fn aabb_2d(&self) -> Aabb2d {
+        self.0
+    }
+}
+
+/// Uniform grid of walkable / blocked cells over `bounds`, rasterized from obstacle
+/// footprints stored in a [`QuadNode`].
+///
+/// Cell `(0, 0)` starts at `bounds.min`; `x` and `y` grow with the world axes of the plane.
+pub struct WalkabilityMap {
+    bounds: Aabb2d,
+    cell_size: f32,
+    /// Distance every footprint is grown by, typically the agent radius.
+    clearance: f32,
+    size: UVec2,
+    obstacles: QuadNode<Footprint>,
+    blocked: Vec<bool>,
+}
+
+impl WalkabilityMap {
+    pub fn new(bounds: Aabb2d, cell_size: f32, clearance: f32) -> Self {
+        let cell_size = cell_size.max(f32::EPSILON);
+        let extent = bounds.max - bounds.min;
+        let size = (extent / cell_size).ceil().max(Vec2::ONE).as_uvec2();
+        Self {
+            bounds,
+            cell_size,
+            clearance: clearance.max(0.0),
+            size,
+            obstacles: QuadNode::new(bounds),
+            blocked: vec![false; (size.x * size.y) as usize],
+        }
+    }
+
+    /// Builds a map and rasterizes `footprints` (ungrown obstacle outlines) into it.
+    pub fn from_footprints(
+        bounds: Aabb2d,
+        cell_size: f32,
+        clearance: f32,
+        footprints: impl IntoIterator<Item = Aabb2d>,
+    ) -> Result<Self, QuadTreeError> {
+        let mut map = Self::new(bounds, cell_size, clearance);
+        for footprint in footprints {
+            map.add_obstacle(footprint)?;
+        }
+        Ok(map)
+    }
+
+    /// Adds an obstacle outline, grows it by the clearance and re-rasterizes the cells it
+    /// covers. Outlines reaching past the map bounds are fine; only the cells inside count.
+    pub fn add_obstacle(&mut self, footprint: Aabb2d) -> Result<(), QuadTreeError> {
+        let grown = footprint.grow(Vec2::splat(self.clearance));
+        self.obstacles.insert(Arc::new(Footprint(grown)))?;
+        self.rasterize(&grown);
+        Ok(())
+    }
+
+    pub fn bounds(&self) -> &Aabb2d {
+        &self.bounds
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    /// Number of cells along each axis.
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    pub fn obstacles(&self) -> &QuadNode<Footprint> {
+        &self.obstacles
+    }
+
+    pub fn contains_cell(&self, cell: IVec2) -> bool {
+        cell.x >= 0 && cell.y >= 0 && (cell.x as u32) < self.size.x && (cell.y as u32) < self.size.y
+    }
+
+    /// Cell containing `point`, or `None` outside the bounds.
+    pub fn cell_at(&self, point: Vec2) -> Option<IVec2> {
+        let cell = ((point - self.bounds.min) / self.cell_size)
+            .floor()
+            .as_ivec2();
+        self.contains_cell(cell).then_some(cell)
+    }
+
+    pub fn cell_center(&self, cell: IVec2) -> Vec2 {
+        self.bounds.min + (cell.as_vec2() + 0.5) * self.cell_size
+    }
+
+    pub fn cell_aabb(&self, cell: IVec2) -> Aabb2d {
+        let min = self.bounds.min + cell.as_vec2() * self.cell_size;
+        Aabb2d {
+            min,
+            max: min + Vec2::splat(self.cell_size),
+        }
+    }
+
+    /// Out-of-bounds cells are never walkable.
+    pub fn is_walkable(&self, cell: IVec2) -> bool {
+        self.contains_cell(cell) && !self.blocked[self.index(cell)]
+    }
+
+    pub fn is_walkable_at(&self, point: Vec2) -> bool {
+        self.cell_at(point)
+            .is_some_and(|cell| self.is_walkable(cell))
+    }
+
+    /// Closest walkable cell to `cell` within `radius` cells (Chebyshev rings), if any.
+    pub fn nearest_walkable(&self, cell: IVec2, radius: i32) -> Option<IVec2> {
+        if self.is_walkable(cell) {
+            return Some(cell);
+        }
+        (1..=radius).find_map(|ring| {
+            (-ring..=ring)
+                .flat_map(|dx| (-ring..=ring).map(move |dy| IVec2::new(dx, dy)))
+                .filter(|offset| offset.x.abs() == ring || offset.y.abs() == ring)
+                .map(|offset| cell + offset)
+                .filter(|candidate| self.is_walkable(*candidate))
+                .min_by_key(|candidate| (*candidate - cell).length_squared())
+        })
+    }
+
+    fn index(&self, cell: IVec2) -> usize {
+        cell.y as usize * self.size.x as usize + cell.x as usize
+    }
+
+    /// Recomputes every cell overlapping `area` from the obstacle quadtree.
+    fn rasterize(&mut self, area: &Aabb2d) {
+        let min = ((area.min - self.bounds.min) / self.cell_size)
+            .floor()
+            .as_ivec2();
+        let max = ((area.max - self.bounds.min) / self.cell_size)
+            .floor()
+            .as_ivec2();
+        let min = min.max(IVec2::ZERO);
+        let max = max.min(self.size.as_ivec2() - 1);
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let cell = IVec2::new(x, y);
+                let probe = self.cell_aabb(cell).shrink(Vec2::splat(CELL_EDGE_EPSILON));
+                let blocked = !self.obstacles.query(&probe).is_empty();
+                let index = self.index(cell);
+                self.blocked[index] = blocked;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod walkability_tests {
+    use super::*;
+
+    fn map_with(footprints: impl IntoIterator<Item = Aabb2d>) -> WalkabilityMap {
+        WalkabilityMap::from_footprints(
+            Aabb2d::new(Vec2::splat(5.0), Vec2::splat(5.0)),
+            1.0,
+            0.0,
+            footprints,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_cells_cover_bounds() {
+        let map = map_with([]);
+        assert_eq!(map.size(), UVec2::new(10, 10));
+        assert_eq!(map.cell_at(Vec2::new(0.5, 9.5)), Some(IVec2::new(0, 9)));
+        assert_eq!(map.cell_at(Vec2::new(-0.1, 5.0)), None);
+        assert_eq!(map.cell_center(IVec2::new(2, 3)), Vec2::new(2.5, 3.5));
+        assert!(map.is_walkable(IVec2::new(9, 9)));
+        assert!(!map.is_walkable(IVec2::new(10, 0)));
+    }
+
+    #[test]
+    fn test_obstacle_blocks_only_covered_cells() {
+        // Exactly covers cells (2..4, 2..4); touching edges must not leak into neighbours.
+        let map = map_with([Aabb2d::new(Vec2::splat(3.0), Vec2::splat(1.0))]);
+        for y in 0..10 {
+            for x in 0..10 {
+                let inside = (2..4).contains(&x) && (2..4).contains(&y);
+                assert_eq!(map.is_walkable(IVec2::new(x, y)), !inside, "({x}, {y})");
+            }
+        }
+    }
+
+    #[test]
+    fn test_clearance_grows_obstacles() {
+        let map = WalkabilityMap::from_footprints(
+            Aabb2d::new(Vec2::splat(5.0), Vec2::splat(5.0)),
+            1.0,
+            0.4,
+            [Aabb2d::new(Vec2::splat(5.0), Vec2::splat(0.5))],
+        )
+        .unwrap();
+        assert!(!map.is_walkable_at(Vec2::new(4.2, 5.0)));
+        assert!(!map.is_walkable_at(Vec2::new(5.8, 5.0)));
+        assert!(map.is_walkable_at(Vec2::new(3.5, 5.0)));
+    }
+
+    #[test]
+    fn test_nearest_walkable() {
+        let map = map_with([Aabb2d::new(Vec2::splat(5.0), Vec2::splat(1.5))]);
+        let center = IVec2::new(5, 5);
+        assert!(!map.is_walkable(center));
+        let nearest = map.nearest_walkable(center, 3).unwrap();
+        assert!(map.is_walkable(nearest));
+        assert_eq!((nearest - center).abs().max_element(), 2);
+        assert_eq!(map.nearest_walkable(center, 1), None);
+    }
+
+    #[test]
+    fn test_obstacles_survive_quadtree_subdivision() {
+        // Enough obstacles to subdivide the quadtree, some straddling its centre lines.
+        let footprints: Vec<_> = [
+            Vec2::new(1.5, 1.5),
+            Vec2::new(8.5, 1.5),
+            Vec2::new(1.5, 8.5),
+            Vec2::new(8.5, 8.5),
+            Vec2::new(5.0, 5.0),
+            Vec2::new(5.0, 2.5),
+            Vec2::new(2.5, 5.0),
+        ]
+        .into_iter()
+        .map(|center| Aabb2d::new(center, Vec2::splat(0.5)))
+        .collect();
+        let map = map_with(footprints.iter().copied());
+        for footprint in &footprints {
+            assert!(!map.is_walkable_at(footprint.center()), "{footprint:?}");
+        }
+        assert_eq!(map.obstacles().query(map.bounds()).len(), footprints.len());
+    }
+}
@@ -0,0 +1,198 @@
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bevy_replicon_renet::netcode::{
+    generate_random_bytes, ConnectToken, TokenGenerationError, NETCODE_KEY_BYTES,
+};
+
+/// Protocol id of this build: clients and servers only connect when theirs match.
+pub const PROTOCOL_ID: u64 = protocol_id(concat!(
+    env!("CARGO_PKG_NAME"),
+    "/",
+    env!("CARGO_PKG_VERSION")
+));
+
+/// Seconds a connect token stays valid after it is issued.
+pub const TOKEN_EXPIRE_SECS: u64 = 300;
+/// Seconds without packets before either side drops the connection.
+pub const TOKEN_TIMEOUT_SECS: i32 = 15;
+
+/// A stable id for `version` (64-bit FNV-1a), so every build of the same version agrees.
+pub const fn protocol_id(version: &str) -> u64 {
+    let bytes = version.as_bytes();
+    let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+        i += 1;
+    }
+    hash
+}
+
+/// A random client id; never `0`, which is [`NetId::HOST`](super::NetId::HOST).
+pub fn generate_client_id() -> u64 {
+    loop {
+        let id = u64::from_le_bytes(generate_random_bytes());
+        if id != 0 {
+            return id;
+        }
+    }
+}
+
+/// How a server admits clients.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum NetAuthentication {
+    /// Anyone who knows the protocol id may connect, unencrypted. For LAN and development.
+    #[default]
+    Unsecure,
+    /// Clients need a netcode connect token signed with `private_key`, and traffic is
+    /// encrypted. The key stays on the server; clients get their tokens from a
+    /// [`TokenProvider`].
+    Secure {
+        private_key: [u8; NETCODE_KEY_BYTES],
+    },
+}
+
+impl NetAuthentication {
+    /// Secure mode with a fresh random key, e.g. for a host that issues the tokens itself.
+    pub fn secure_with_random_key() -> Self {
+        Self::Secure {
+            private_key: generate_random_bytes(),
+        }
+    }
+}
+
+/// Hands a client the connect token for each connection attempt: from a backend that holds
+/// the server's key, or, on the server's own machine, from a [`TokenIssuer`].
+///
+/// Called with the current time since the Unix epoch, the client id of the attempt and the
+/// server addresses the token must be valid for.
+#[derive(Clone)]
+pub struct TokenProvider(
+    Arc<
+        dyn Fn(Duration, u64, Vec<SocketAddr>) -> Result<ConnectToken, TokenGenerationError>
+            + Send
+            + Sync,
+    >,
+);
+
+impl TokenProvider {
+    pub fn new(
+        provide: impl Fn(Duration, u64, Vec<SocketAddr>) -> Result<ConnectToken, TokenGenerationError>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        Self(Arc::new(provide))
+    }
+
+    pub fn token(
+        &self,
+        current_time: Duration,
+        client_id: u64,
+        server_addresses: Vec<SocketAddr>,
+    ) -> Result<ConnectToken, TokenGenerationError> {
+        (self.0)(current_time, client_id, server_addresses)
+    }
+}
+
+impl fmt::Debug for TokenProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TokenProvider")
+    }
+}
+
+/// Providers are only equal when they are clones of each other.
+impl PartialEq for TokenProvider {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Issues connect tokens locally from the server's private key.
+pub struct TokenIssuer {
+    private_key: [u8; NETCODE_KEY_BYTES],
+    protocol_id: u64,
+    pub expire_secs: u64,
+    pub timeout_secs: i32,
+}
+
+impl TokenIssuer {
+    pub fn new(private_key: [u8; NETCODE_KEY_BYTES], protocol_id: u64) -> Self {
+        Self {
+            private_key,
+            protocol_id,
+            expire_secs: TOKEN_EXPIRE_SECS,
+            timeout_secs: TOKEN_TIMEOUT_SECS,
+        }
+    }
+
+    /// A token letting `client_id` join any of `server_addresses`; they must match the
+    /// server's public addresses. `current_time` is since the Unix epoch.
+    pub fn issue(
+        &self,
+        current_time: Duration,
+        client_id: u64,
+        server_addresses: Vec<SocketAddr>,
+    ) -> Result<ConnectToken, TokenGenerationError> {
+        ConnectToken::generate(
+            current_time,
+            self.protocol_id,
+            self.expire_secs,
+            client_id,
+            self.timeout_secs,
+            server_addresses,
+            None,
+            &self.private_key,
+        )
+    }
+}
+
+impl From<TokenIssuer> for TokenProvider {
+    fn from(issuer: TokenIssuer) -> Self {
+        Self::new(move |current_time, client_id, server_addresses| {
+            issuer.issue(current_time, client_id, server_addresses)
+        })
+    }
+}
+
+#[cfg(test)]
+mod auth_tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use super::*;
+
+    #[test]
+    fn test_protocol_id_follows_version() {
+        // Reference FNV-1a value.
+        assert_eq!(protocol_id("a"), 0xaf63_dc4c_8601_ec8c);
+        assert_ne!(
+            protocol_id("crab_feast/0.1.0"),
+            protocol_id("crab_feast/0.1.1")
+        );
+    }
+
+    #[test]
+    fn test_client_ids_are_unique_and_not_host() {
+        let ids: std::collections::HashSet<u64> = (0..1000).map(|_| generate_client_id()).collect();
+        assert_eq!(ids.len(), 1000);
+        assert!(!ids.contains(&0));
+    }
+
+    #[test]
+    fn test_issues_tokens_for_server_addresses() {
+        let NetAuthentication::Secure { private_key } = NetAuthentication::secure_with_random_key()
+        else {
+            unreachable!();
+        };
+        let provider = TokenProvider::from(TokenIssuer::new(private_key, PROTOCOL_ID));
+        assert_eq!(provider.clone(), provider);
+        let server = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 5000);
+        assert!(provider
+            .token(Duration::from_secs(1_700_000_000), 42, vec![server])
+            .is_ok());
+    }
+}
@@ -0,0 +1,345 @@
+use std::fmt;
+use std::time::SystemTimeError;
+
+use bevy::prelude::*;
+use bevy_replicon::prelude::*;
+use bevy_replicon_renet::netcode::{
+    NetcodeClientTransport, NetcodeDisconnectReason, NetcodeError, TokenGenerationError,
+};
+
+use super::{LocalNetId, NetConfig, NetId, NetMode};
+
+/// Why a server or client could not start. Triggered as an event; [`NetMode`] then goes back
+/// to [`NetMode::None`].
+#[derive(Event, Debug)]
+pub enum NetError {
+    /// The system clock is before the Unix epoch.
+    Clock(SystemTimeError),
+    /// The socket could not be bound or the server transport created, e.g. a busy port.
+    Io(std::io::Error),
+    Token(TokenGenerationError),
+    Netcode(NetcodeError),
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Clock(e) => write!(f, "system clock is before the Unix epoch: {e}"),
+            Self::Io(e) => write!(f, "could not open the network socket: {e}"),
+            Self::Token(e) => write!(f, "could not issue a connect token: {e}"),
+            Self::Netcode(e) => write!(f, "could not start the client transport: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for NetError {}
+
+impl From<SystemTimeError> for NetError {
+    fn from(e: SystemTimeError) -> Self {
+        Self::Clock(e)
+    }
+}
+
+impl From<std::io::Error> for NetError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<TokenGenerationError> for NetError {
+    fn from(e: TokenGenerationError) -> Self {
+        Self::Token(e)
+    }
+}
+
+impl From<NetcodeError> for NetError {
+    fn from(e: NetcodeError) -> Self {
+        Self::Netcode(e)
+    }
+}
+
+/// A player joined: on the server a remote client, on a client this app itself.
+#[derive(Event, Clone, Debug, PartialEq)]
+pub struct ClientConnected {
+    pub id: NetId,
+}
+
+/// A connected player left: on the server a remote client, on a client this app itself.
+/// A client that times out triggers [`TimedOut`] instead.
+#[derive(Event, Clone, Debug, PartialEq)]
+pub struct ClientDisconnected {
+    pub id: NetId,
+    /// `None` on the server, where the transport only reports that the client left.
+    pub reason: Option<String>,
+}
+
+/// A client attempt ended before it connected, other than by timing out.
+#[derive(Event, Clone, Debug, PartialEq)]
+pub struct ConnectionFailed {
+    pub reason: String,
+}
+
+/// The client stopped hearing from the server, while connecting or connected.
+#[derive(Event, Clone, Debug, PartialEq)]
+pub struct TimedOut {
+    pub reason: String,
+}
+
+/// Where this app's networking stands, for menus and HUDs.
+#[derive(Resource, Clone, Debug, Default, PartialEq)]
+pub enum ConnectionState {
+    #[default]
+    Offline,
+    Hosting {
+        clients: usize,
+    },
+    /// `attempt` is `0` for the first try and counts reconnects after that.
+    Connecting {
+        attempt: u32,
+    },
+    Connected,
+    /// Waiting until `retry_at` (real time, in seconds) to reconnect.
+    Reconnecting {
+        attempt: u32,
+        retry_at: f32,
+    },
+    /// Networking stopped and [`NetMode`] went back to [`NetMode::None`].
+    Failed {
+        reason: String,
+    },
+}
+
+/// How a client retries after losing or failing to get a connection.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Backoff {
+    /// Delay before the first retry; it doubles with each one after.
+    pub initial_secs: f32,
+    pub max_secs: f32,
+    /// Retries before giving up; `None` retries forever.
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial_secs: 1.0,
+            max_secs: 30.0,
+            max_attempts: Some(8),
+        }
+    }
+}
+
+impl Backoff {
+    /// Never retries.
+    pub const DISABLED: Self = Self {
+        initial_secs: 0.0,
+        max_secs: 0.0,
+        max_attempts: Some(0),
+    };
+
+    /// Delay before retry number `attempt`, counted from `1`, or `None` once they are used up.
+    pub fn delay(&self, attempt: u32) -> Option<f32> {
+        if attempt == 0 || self.max_attempts.is_some_and(|max| attempt > max) {
+            return None;
+        }
+        let doublings = (attempt - 1).min(31) as i32;
+        Some((self.initial_secs * 2.0_f32.powi(doublings)).min(self.max_secs))
+    }
+}
+
+/// Logs `error`, triggers it and leaves networking.
+pub(super) fn report_failure(
+    commands: &mut Commands,
+    state: &mut ConnectionState,
+    next_mode: &mut NextState<NetMode>,
+    error: NetError,
+) {
+    error!("Networking stopped: {error}");
+    *state = ConnectionState::Failed {
+        reason: error.to_string(),
+    };
+    next_mode.set(NetMode::None);
+    commands.trigger(error);
+}
+
+/// Keeps a [`ConnectionState::Failed`] around for the UI after its mode exits.
+pub(super) fn go_offline(mut state: ResMut<ConnectionState>) {
+    if !matches!(*state, ConnectionState::Failed { .. }) {
+        *state = ConnectionState::Offline;
+    }
+}
+
+pub(super) fn on_client_authorized(
+    trigger: On<Add, AuthorizedClient>,
+    mut commands: Commands,
+    clients: Query<&NetworkId>,
+    mut state: ResMut<ConnectionState>,
+) {
+    let Ok(network_id) = clients.get(trigger.event_target()) else {
+        return;
+    };
+    if let ConnectionState::Hosting { clients } = &mut *state {
+        *clients += 1;
+    }
+    commands.trigger(ClientConnected {
+        id: NetId(network_id.get()),
+    });
+}
+
+pub(super) fn on_client_removed(
+    trigger: On<Remove, AuthorizedClient>,
+    mut commands: Commands,
+    clients: Query<&NetworkId>,
+    mut state: ResMut<ConnectionState>,
+) {
+    let Ok(network_id) = clients.get(trigger.event_target()) else {
+        return;
+    };
+    if let ConnectionState::Hosting { clients } = &mut *state {
+        *clients = clients.saturating_sub(1);
+    }
+    commands.trigger(ClientDisconnected {
+        id: NetId(network_id.get()),
+        reason: None,
+    });
+}
+
+pub(super) fn on_connected(
+    mut commands: Commands,
+    local_id: Res<LocalNetId>,
+    mut state: ResMut<ConnectionState>,
+) {
+    *state = ConnectionState::Connected;
+    commands.trigger(ClientConnected { id: local_id.0 });
+}
+
+/// What to do after the client transport disconnected for `reason`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Disconnect {
+    TimedOut,
+    Retry,
+    /// The server refused or dropped us, or we left; retrying would not help.
+    GiveUp,
+}
+
+fn classify(reason: NetcodeDisconnectReason) -> Disconnect {
+    match reason {
+        NetcodeDisconnectReason::ConnectionTimedOut
+        | NetcodeDisconnectReason::ConnectionResponseTimedOut
+        | NetcodeDisconnectReason::ConnectionRequestTimedOut => Disconnect::TimedOut,
+        // A retry issues a fresh token.
+        NetcodeDisconnectReason::ConnectTokenExpired => Disconnect::Retry,
+        NetcodeDisconnectReason::ConnectionDenied
+        | NetcodeDisconnectReason::DisconnectedByServer
+        | NetcodeDisconnectReason::DisconnectedByClient => Disconnect::GiveUp,
+    }
+}
+
+/// Reports why the client lost its connection and schedules a reconnect, or gives up.
+///
+/// Only an attempt that was under way counts; entering [`ClientState::Disconnected`] at
+/// startup, or with the transport still up, is not a disconnect.
+pub(super) fn on_disconnected(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    config: Res<NetConfig>,
+    transport: Option<Res<NetcodeClientTransport>>,
+    local_id: Option<Res<LocalNetId>>,
+    mut state: ResMut<ConnectionState>,
+    mut next_mode: ResMut<NextState<NetMode>>,
+) {
+    let attempt = match *state {
+        ConnectionState::Connecting { attempt } => attempt + 1,
+        ConnectionState::Connected => 1,
+        _ => return,
+    };
+    let Some(transport) = transport.filter(|transport| transport.is_disconnected()) else {
+        return;
+    };
+    // Renet's own reason would only say that the transport failed.
+    let (reason, disconnect) = match transport.disconnect_reason() {
+        Some(reason) => (reason.to_string(), classify(reason)),
+        None => ("connection lost".to_string(), Disconnect::Retry),
+    };
+    match disconnect {
+        Disconnect::TimedOut => commands.trigger(TimedOut {
+            reason: reason.clone(),
+        }),
+        _ if *state == ConnectionState::Connected => commands.trigger(ClientDisconnected {
+            id: local_id.map_or(NetId::HOST, |id| id.0),
+            reason: Some(reason.clone()),
+        }),
+        _ => commands.trigger(ConnectionFailed {
+            reason: reason.clone(),
+        }),
+    }
+    let delay = match disconnect {
+        Disconnect::GiveUp => None,
+        _ => config.reconnect().delay(attempt),
+    };
+    match delay {
+        Some(delay) => {
+            warn!("Disconnected ({reason}), retrying in {delay:.1}s");
+            *state = ConnectionState::Reconnecting {
+                attempt,
+                retry_at: time.elapsed_secs() + delay,
+            };
+        }
+        None => {
+            warn!("Disconnected ({reason}), giving up");
+            *state = ConnectionState::Failed { reason };
+            next_mode.set(NetMode::None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod connection_tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_cap() {
+        let backoff = Backoff {
+            initial_secs: 1.0,
+            max_secs: 5.0,
+            max_attempts: Some(4),
+        };
+        let delays: Vec<_> = (1..=5).map(|attempt| backoff.delay(attempt)).collect();
+        assert_eq!(delays, [Some(1.0), Some(2.0), Some(4.0), Some(5.0), None]);
+        assert_eq!(Backoff::DISABLED.delay(1), None);
+    }
+
+    #[test]
+    fn test_only_refusals_give_up() {
+        assert_eq!(
+            classify(NetcodeDisconnectReason::ConnectionRequestTimedOut),
+            Disconnect::TimedOut
+        );
+        assert_eq!(
+            classify(NetcodeDisconnectReason::ConnectTokenExpired),
+            Disconnect::Retry
+        );
+        assert_eq!(
+            classify(NetcodeDisconnectReason::ConnectionDenied),
+            Disconnect::GiveUp
+        );
+    }
+
+    #[test]
+    fn test_disconnect_needs_an_attempt_and_a_transport() {
+        for state in [
+            ConnectionState::Offline,
+            ConnectionState::Connecting { attempt: 0 },
+        ] {
+            let mut world = World::new();
+            world.insert_resource(state.clone());
+            world.init_resource::<Time<Real>>();
+            world.init_resource::<NetConfig>();
+            world.init_resource::<NextState<NetMode>>();
+            world.run_system_once(on_disconnected).unwrap();
+            assert_eq!(*world.resource::<ConnectionState>(), state);
+        }
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+
+use bevy::ecs::component::Mutable;
+use bevy::prelude::*;
+use bevy_replicon::client::confirm_history::ConfirmHistory;
+use bevy_replicon::prelude::*;
+use bevy_replicon::shared::replicon_tick::RepliconTick;
+
+/// Snapshots kept per buffer at most; at typical tick rates this is well over a second.
+const MAX_SNAPSHOTS: usize = 32;
+
+/// A replicated value that can be blended between two snapshots.
+pub trait Interpolate: Clone + PartialEq + Send + Sync + 'static {
+    /// `t` is `0.0` at `from` and `1.0` at `to`; values above `1.0` extrapolate past `to`.
+    fn interpolate(from: &Self, to: &Self, t: f32) -> Self;
+}
+
+/// Translation and scale extrapolate linearly; rotation stops at `to`, since spinning on
+/// past the last snapshot looks worse than pausing.
+impl Interpolate for Transform {
+    fn interpolate(from: &Self, to: &Self, t: f32) -> Self {
+        Transform {
+            translation: from.translation.lerp(to.translation, t),
+            rotation: from.rotation.slerp(to.rotation, t.min(1.0)),
+            scale: from.scale.lerp(to.scale, t),
+        }
+    }
+}
+
+/// Shows a replicated component of `C` a fixed delay in the past, blended between the
+/// snapshots received around that time, on every entity that has a [`SnapshotBuffer<C>`].
+///
+/// [`NetPlugin`](super::NetPlugin) adds it for [`Transform`].
+pub struct InterpolationPlugin<C>(PhantomData<C>);
+
+impl<C> Default for InterpolationPlugin<C> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<C: Component<Mutability = Mutable> + Interpolate> Plugin for InterpolationPlugin<C> {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PreUpdate,
+            interpolate_snapshots::<C>.after(ClientSystems::Receive),
+        );
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Snapshot<C> {
+    /// `Time::elapsed_secs` when the snapshot was received.
+    pub secs: f32,
+    pub value: C,
+}
+
+/// Received values of a replicated component `C`, and the interpolation settings for it.
+///
+/// Opt an entity in by inserting the buffer next to the replicated component. Snapshots are
+/// stamped with their arrival time, so `delay` should cover a few server ticks plus jitter.
+/// Every server tick that confirms the entity is a snapshot, even when the value did not
+/// change, so an entity that stops holds still. When nothing arrives (packet loss) the
+/// value keeps moving along the last two snapshots for up to `max_extrapolation` seconds,
+/// then eases back to the last snapshot over the same time and holds there.
+#[derive(Component, Clone, Debug)]
+pub struct SnapshotBuffer<C> {
+    /// How far in the past the entity is shown, in seconds.
+    pub delay: f32,
+    pub max_extrapolation: f32,
+    snapshots: VecDeque<Snapshot<C>>,
+    /// Snapshots the last sample was taken between; `None` while converging or holding.
+    segment: Option<(Snapshot<C>, Snapshot<C>)>,
+    /// Value the buffer last wrote to the component, to tell new snapshots from it.
+    last_written: Option<C>,
+    /// Last server tick the entity was confirmed at, to tell new ticks from it.
+    confirmed_tick: Option<RepliconTick>,
+}
+
+impl<C: Interpolate> SnapshotBuffer<C> {
+    pub fn new(delay: f32) -> Self {
+        Self {
+            delay,
+            max_extrapolation: 0.25,
+            snapshots: VecDeque::new(),
+            segment: None,
+            last_written: None,
+            confirmed_tick: None,
+        }
+    }
+
+    pub fn with_max_extrapolation(mut self, secs: f32) -> Self {
+        self.max_extrapolation = secs;
+        self
+    }
+
+    pub fn push(&mut self, secs: f32, value: C) {
+        if self.snapshots.len() == MAX_SNAPSHOTS {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(Snapshot { secs, value });
+    }
+
+    /// Takes what is in the component at `now` as a snapshot if replication changed it, or
+    /// repeats the last snapshot if `new_tick` confirms the value is still current.
+    fn observe(&mut self, now: f32, value: &C, new_tick: bool) {
+        // Anything other than what the buffer wrote came from replication.
+        if self.last_written.as_ref() != Some(value) {
+            self.push(now, value.clone());
+        } else if new_tick && let Some(last) = self.snapshots.back() {
+            let value = last.value.clone();
+            self.push(now, value);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    /// The value to show at `now`, or `None` before the first snapshot.
+    pub fn sample(&mut self, now: f32) -> Option<C> {
+        let render = now - self.delay;
+        // Only one snapshot at or before the render time is needed.
+        while self.snapshots.len() > 2 && self.snapshots[1].secs <= render {
+            self.snapshots.pop_front();
+        }
+        self.segment = None;
+        let first = self.snapshots.front()?;
+        if self.snapshots.len() == 1 || render <= first.secs {
+            return Some(first.value.clone());
+        }
+        let (from, to) = match self.snapshots.iter().position(|s| s.secs >= render) {
+            Some(index) => (&self.snapshots[index - 1], &self.snapshots[index]),
+            None => {
+                let last = self.snapshots.len() - 1;
+                (&self.snapshots[last - 1], &self.snapshots[last])
+            }
+        };
+        let span = (to.secs - from.secs).max(f32::EPSILON);
+        let overrun = render - (to.secs + self.max_extrapolation);
+        if overrun < 0.0 {
+            let value = C::interpolate(&from.value, &to.value, (render - from.secs) / span);
+            self.segment = Some((from.clone(), to.clone()));
+            return Some(value);
+        }
+        // Out of extrapolation: ease from the furthest guess back to what was received.
+        let furthest = C::interpolate(
+            &from.value,
+            &to.value,
+            (to.secs + self.max_extrapolation - from.secs) / span,
+        );
+        let t = (overrun / self.max_extrapolation.max(f32::EPSILON)).min(1.0);
+        Some(C::interpolate(&furthest, &to.value, t))
+    }
+
+    /// Snapshots the last [`sample`](Self::sample) blended between, `None` once
+    /// extrapolation has run out.
+    pub fn segment(&self) -> Option<(&Snapshot<C>, &Snapshot<C>)> {
+        self.segment.as_ref().map(|(from, to)| (from, to))
+    }
+}
+
+impl SnapshotBuffer<Transform> {
+    /// Velocity of the shown translation, in units per second; zero once extrapolation has
+    /// run out.
+    pub fn velocity(&self) -> Vec3 {
+        self.segment().map_or(Vec3::ZERO, |(from, to)| {
+            (to.value.translation - from.value.translation)
+                / (to.secs - from.secs).max(f32::EPSILON)
+        })
+    }
+}
+
+fn interpolate_snapshots<C: Component<Mutability = Mutable> + Interpolate>(
+    time: Res<Time>,
+    mut entities: Query<(&mut C, &mut SnapshotBuffer<C>, Option<&ConfirmHistory>)>,
+) {
+    let now = time.elapsed_secs();
+    for (mut value, mut buffer, history) in &mut entities {
+        // The server only sends changes, so a tick confirming the entity without touching
+        // `C` means `C` was unchanged at that tick.
+        let tick = history.map(ConfirmHistory::last_tick);
+        let new_tick = tick.is_some() && tick != buffer.confirmed_tick;
+        buffer.confirmed_tick = tick;
+        buffer.observe(now, &value, new_tick);
+        if let Some(sampled) = buffer.sample(now) {
+            value.set_if_neq(sampled.clone());
+            buffer.last_written = Some(sampled);
+        }
+    }
+}
+
+#[cfg(test)]
+mod interpolation_tests {
+    use super::*;
+
+    fn at(x: f32) -> Transform {
+        Transform::from_xyz(x, 0.0, 0.0)
+    }
+
+    fn buffer() -> SnapshotBuffer<Transform> {
+        let mut buffer = SnapshotBuffer::new(0.1).with_max_extrapolation(0.2);
+        for (secs, x) in [(1.0, 0.0), (1.1, 1.0), (1.2, 2.0)] {
+            buffer.push(secs, at(x));
+        }
+        buffer
+    }
+
+    #[test]
+    fn test_interpolates_behind_by_delay() {
+        let mut buffer = buffer();
+        assert_eq!(buffer.sample(1.05), Some(at(0.0)));
+        let sampled = buffer.sample(1.25).unwrap();
+        assert!((sampled.translation.x - 1.5).abs() < 1e-4);
+        assert!((buffer.velocity().x - 10.0).abs() < 1e-3);
+        // The first snapshot is no longer needed.
+        assert_eq!(buffer.len(), 2);
+    }
+
+    #[test]
+    fn test_extrapolates_then_returns_to_last_snapshot() {
+        let mut buffer = buffer();
+        let sampled = buffer.sample(1.4).unwrap();
+        assert!((sampled.translation.x - 3.0).abs() < 1e-4);
+        assert!(buffer.velocity().x > 0.0);
+
+        // Extrapolation peaks at x = 4.0 and is halfway back to the last snapshot.
+        let converging = buffer.sample(1.6).unwrap();
+        assert!((converging.translation.x - 3.0).abs() < 1e-4);
+        assert_eq!(buffer.velocity(), Vec3::ZERO);
+
+        let held = buffer.sample(2.0).unwrap();
+        assert!((held.translation.x - 2.0).abs() < 1e-4);
+        assert_eq!(buffer.velocity(), Vec3::ZERO);
+    }
+
+    #[test]
+    fn test_confirmed_tick_holds_a_stopped_value() {
+        let mut stopped = buffer();
+        let shown = stopped.sample(1.3).unwrap();
+        stopped.last_written = Some(shown.clone());
+        // The entity stopped at x = 2.0; the next tick confirms it without a change.
+        stopped.observe(1.3, &shown, true);
+        let held = stopped.sample(1.45).unwrap();
+        assert!((held.translation.x - 2.0).abs() < 1e-4);
+        assert_eq!(stopped.velocity(), Vec3::ZERO);
+
+        // Without a confirmed tick the gap is treated as packet loss.
+        let mut lost = buffer();
+        let shown = lost.sample(1.3).unwrap();
+        lost.last_written = Some(shown.clone());
+        lost.observe(1.3, &shown, false);
+        let extrapolated = lost.sample(1.45).unwrap();
+        assert!((extrapolated.translation.x - 3.5).abs() < 1e-4);
+    }
+}
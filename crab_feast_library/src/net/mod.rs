@@ -1,3 +1,11 @@
+mod auth;
+mod connection;
+mod interpolation;
 mod net_plugin;
+mod options;
 
+pub use auth::*;
+pub use connection::*;
+pub use interpolation::*;
 pub use net_plugin::*;
+pub use options::*;
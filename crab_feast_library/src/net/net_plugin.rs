@@ -1,10 +1,14 @@
 
 
-use std::{net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket}, time::SystemTime};
+use std::{net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket}, time::SystemTime};
 
 use bevy::prelude::*;
 use bevy_replicon::prelude::*;
 use bevy_replicon_renet::{netcode::{ClientAuthentication, NetcodeClientTransport, NetcodeServerTransport, ServerAuthentication, ServerConfig}, renet::{ConnectionConfig, RenetClient, RenetServer}, RenetChannelsExt, RepliconRenetPlugins};
+use serde::{Deserialize, Serialize};
+
+use super::connection::{go_offline, on_client_authorized, on_client_removed, on_connected, on_disconnected, report_failure};
+use super::{generate_client_id, Backoff, ConnectionState, InterpolationPlugin, NetAuthentication, NetError, TokenProvider, PROTOCOL_ID};
 
 pub struct NetPlugin;
 
@@ -17,40 +21,195 @@ pub enum NetMode {
     // Hotseat,
 }
 
-#[derive(Resource)]
+/// Identifies a player across the network: the netcode client id of a remote client, or
+/// [`NetId::HOST`] for the player on the server itself.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NetId(pub u64);
+
+impl NetId {
+    /// [`generate_client_id`] never returns `0`, so it is free for the host.
+    pub const HOST: Self = Self(0);
+}
+
+/// The [`NetId`] of this app's player while a server or client is running.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LocalNetId(pub NetId);
+
+/// Port servers listen on unless configured otherwise.
+pub const DEFAULT_PORT: u16 = 5000;
+
+/// Where to host or connect, and how. Read when [`NetMode`] enters `Server` or `Client`, so
+/// change it before setting the mode.
+#[derive(Resource, Clone, Debug, PartialEq)]
 pub struct NetConfig {
+    /// The address a server binds, or the server a client connects to.
     ip: IpAddr,
     port: u16, 
+    /// What clients dial, when it differs from the bound address.
+    public_addr: Option<SocketAddr>,
+    /// Only peers with the same id connect; defaults to [`PROTOCOL_ID`] of this version.
+    protocol_id: u64,
+    max_clients: usize,
+    authentication: NetAuthentication,
+    /// Where a client gets connect tokens for a secure server; `None` connects unsecure.
+    token_provider: Option<TokenProvider>,
+    reconnect: Backoff,
 }
 
-impl FromWorld for NetConfig {
-    fn from_world(_world: &mut World) -> Self {
+impl Default for NetConfig {
+    fn default() -> Self {
         NetConfig {
             ip: IpAddr::V4(Ipv4Addr::LOCALHOST),
-            port: 5000,
+            port: DEFAULT_PORT,
+            public_addr: None,
+            protocol_id: PROTOCOL_ID,
+            max_clients: 10,
+            authentication: NetAuthentication::Unsecure,
+            token_provider: None,
+            reconnect: Backoff::default(),
         }
     }
 }
 
+impl NetConfig {
+    /// For a server the interface to listen on: [`Ipv4Addr::UNSPECIFIED`] (`0.0.0.0`) accepts
+    /// connections on all of them, e.g. from the LAN.
+    pub fn with_ip(mut self, ip: IpAddr) -> Self {
+        self.ip = ip;
+        self
+    }
+
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    pub fn with_addr(self, addr: SocketAddr) -> Self {
+        self.with_ip(addr.ip()).with_port(addr.port())
+    }
+
+    /// Secure servers only accept tokens for this address. Without it a server bound to
+    /// `0.0.0.0` advertises loopback and this machine's LAN address.
+    pub fn with_public_addr(mut self, addr: SocketAddr) -> Self {
+        self.public_addr = Some(addr);
+        self
+    }
+
+    pub fn ip(&self) -> IpAddr {
+        self.ip
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        SocketAddr::new(self.ip, self.port)
+    }
+
+    /// The addresses a server tells clients it is reachable at; connect tokens are only valid
+    /// for these. An unspecified bind is reachable on loopback and on the LAN, but is not an
+    /// address clients can dial itself.
+    pub fn public_addresses(&self) -> Vec<SocketAddr> {
+        if let Some(addr) = self.public_addr {
+            return vec![addr];
+        }
+        if !self.ip.is_unspecified() {
+            return vec![self.addr()];
+        }
+        let loopback = match self.ip {
+            IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+        };
+        let mut addresses = vec![SocketAddr::new(loopback, self.port)];
+        if let Some(ip) = lan_ip(self.ip) {
+            addresses.push(SocketAddr::new(ip, self.port));
+        }
+        addresses
+    }
+
+    pub fn with_protocol_id(mut self, protocol_id: u64) -> Self {
+        self.protocol_id = protocol_id;
+        self
+    }
+
+    pub fn with_max_clients(mut self, max_clients: usize) -> Self {
+        self.max_clients = max_clients;
+        self
+    }
+
+    /// How a server admits clients. Clients of a secure server need
+    /// [`with_token_provider`](Self::with_token_provider) instead.
+    pub fn with_authentication(mut self, authentication: NetAuthentication) -> Self {
+        self.authentication = authentication;
+        self
+    }
+
+    /// Makes a client join securely, asking `provider` for a token on every connection.
+    pub fn with_token_provider(mut self, provider: impl Into<TokenProvider>) -> Self {
+        self.token_provider = Some(provider.into());
+        self
+    }
+
+    /// How clients retry a lost or failed connection; [`Backoff::DISABLED`] never does.
+    pub fn with_reconnect(mut self, reconnect: Backoff) -> Self {
+        self.reconnect = reconnect;
+        self
+    }
+
+    pub fn reconnect(&self) -> &Backoff {
+        &self.reconnect
+    }
+}
+
+/// The address this machine reaches the network from, in the family of `unspecified`.
+/// Connecting a UDP socket only picks a route; nothing is sent.
+fn lan_ip(unspecified: IpAddr) -> Option<IpAddr> {
+    let probe = match unspecified {
+        IpAddr::V4(_) => SocketAddr::from(([192, 0, 2, 1], 9)),
+        IpAddr::V6(_) => SocketAddr::from(([0x2001, 0xdb8, 0, 0, 0, 0, 0, 1], 9)),
+    };
+    let socket = UdpSocket::bind((unspecified, 0)).ok()?;
+    socket.connect(probe).ok()?;
+    let ip = socket.local_addr().ok()?.ip();
+    (!ip.is_unspecified() && !ip.is_loopback()).then_some(ip)
+}
+
 impl Plugin for NetPlugin {
     fn build(&self, app: &mut App) {
         app
         .add_plugins(RepliconPlugins)
         .add_plugins(RepliconRenetPlugins)
+        .add_plugins(InterpolationPlugin::<Transform>::default())
         .init_state::<NetMode>()
         .init_resource::<NetConfig>()
+        .init_resource::<ConnectionState>()
+        .add_observer(on_client_authorized)
+        .add_observer(on_client_removed)
         .add_systems(OnEnter(NetMode::Server), Self::handle_server_mode)
-        .add_systems(OnExit(NetMode::Server), Self::disable_server_mode)
+        .add_systems(OnExit(NetMode::Server), (Self::disable_server_mode, go_offline))
         .add_systems(OnEnter(NetMode::Client), Self::handle_client_mode)
-        .add_systems(OnExit(NetMode::Client), Self::disable_client_mode);
+        .add_systems(OnExit(NetMode::Client), (Self::disable_client_mode, go_offline))
+        .add_systems(OnEnter(ClientState::Connected), on_connected.run_if(in_state(NetMode::Client)))
+        .add_systems(OnEnter(ClientState::Disconnected), on_disconnected.run_if(in_state(NetMode::Client)))
+        .add_systems(Update, Self::reconnect.run_if(in_state(NetMode::Client)));
     }
 }
 
 impl NetPlugin {
     fn handle_server_mode(mut commands: Commands, 
         channels: Res<RepliconChannels>,
-        config: Res<NetConfig>
+        config: Res<NetConfig>,
+        mut state: ResMut<ConnectionState>,
+        mut next_mode: ResMut<NextState<NetMode>>,
     ) {
+        match Self::start_server(&mut commands, &channels, &config) {
+            Ok(()) => *state = ConnectionState::Hosting { clients: 0 },
+            Err(error) => report_failure(&mut commands, &mut state, &mut next_mode, error),
+        }
+    }
+
+    fn start_server(commands: &mut Commands, channels: &RepliconChannels, config: &NetConfig) -> Result<(), NetError> {
         let server_channels_config = channels.get_server_configs();
         let client_channels_config = channels.get_client_configs();
         let server = RenetServer::new(
@@ -60,29 +219,69 @@ impl NetPlugin {
                 ..Default::default()
             }
         );
-        let current_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap();
-        let socket = UdpSocket::bind((config.ip, config.port)).unwrap();
+        let current_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
+        let socket = UdpSocket::bind(config.addr())?;
+        let authentication = match config.authentication {
+            NetAuthentication::Unsecure => ServerAuthentication::Unsecure,
+            NetAuthentication::Secure { private_key } => ServerAuthentication::Secure { private_key },
+        };
         let server_config = ServerConfig {
             current_time,
-            max_clients: 10,
-            protocol_id: 0,
-            authentication: ServerAuthentication::Unsecure,
-            public_addresses: Default::default(),
+            max_clients: config.max_clients,
+            protocol_id: config.protocol_id,
+            authentication,
+            // Connect tokens are only valid for these addresses.
+            public_addresses: config.public_addresses(),
         };
-        let transport = NetcodeServerTransport::new(server_config, socket).unwrap();
+        let transport = NetcodeServerTransport::new(server_config, socket)?;
         commands.insert_resource(server);
         commands.insert_resource(transport);
+        commands.insert_resource(LocalNetId(NetId::HOST));
+        Ok(())
     }
 
     fn disable_server_mode(mut commands: Commands) {
         commands.remove_resource::<RenetServer>();
         commands.remove_resource::<NetcodeServerTransport>();
+        commands.remove_resource::<LocalNetId>();
     }
     
     fn handle_client_mode(mut commands: Commands, 
         channels: Res<RepliconChannels>,
-        config: Res<NetConfig>
+        config: Res<NetConfig>,
+        mut state: ResMut<ConnectionState>,
+        mut next_mode: ResMut<NextState<NetMode>>,
+    ) {
+        match Self::start_client(&mut commands, &channels, &config) {
+            Ok(()) => *state = ConnectionState::Connecting { attempt: 0 },
+            Err(error) => report_failure(&mut commands, &mut state, &mut next_mode, error),
+        }
+    }
+
+    /// Starts a new connection once a scheduled reconnect is due.
+    fn reconnect(mut commands: Commands,
+        time: Res<Time<Real>>,
+        channels: Res<RepliconChannels>,
+        config: Res<NetConfig>,
+        mut state: ResMut<ConnectionState>,
+        mut next_mode: ResMut<NextState<NetMode>>,
     ) {
+        let ConnectionState::Reconnecting { attempt, retry_at } = *state else {
+            return;
+        };
+        if time.elapsed_secs() < retry_at {
+            return;
+        }
+        info!("Reconnecting, attempt {attempt}");
+        match Self::start_client(&mut commands, &channels, &config) {
+            Ok(()) => *state = ConnectionState::Connecting { attempt },
+            Err(error) => report_failure(&mut commands, &mut state, &mut next_mode, error),
+        }
+    }
+
+    /// Replaces any previous client, so reconnects also go through here. Every connection
+    /// gets a new client id.
+    fn start_client(commands: &mut Commands, channels: &RepliconChannels, config: &NetConfig) -> Result<(), NetError> {
         let server_channels_config = channels.get_server_configs();
         let client_channels_config = channels.get_client_configs();
 
@@ -92,25 +291,34 @@ impl NetPlugin {
             ..Default::default()
         });
 
-        let current_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap();
-        let client_id = current_time.as_millis() as u64;
-        let server_addr = SocketAddr::new(config.ip, config.port);
-        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
-        let authentication = ClientAuthentication::Unsecure {
-            client_id,
-            protocol_id: 0,
-            server_addr,
-            user_data: None,
+        let current_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
+        let client_id = generate_client_id();
+        let server_addr = config.addr();
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        let authentication = match &config.token_provider {
+            None => ClientAuthentication::Unsecure {
+                client_id,
+                protocol_id: config.protocol_id,
+                server_addr,
+                user_data: None,
+            },
+            Some(provider) => {
+                let connect_token = provider.token(current_time, client_id, vec![server_addr])?;
+                ClientAuthentication::Secure { connect_token }
+            }
         };
-        let transport = NetcodeClientTransport::new(current_time, authentication, socket).unwrap();
+        let transport = NetcodeClientTransport::new(current_time, authentication, socket)?;
 
         commands.insert_resource(client);
         commands.insert_resource(transport);
+        commands.insert_resource(LocalNetId(NetId(client_id)));
+        Ok(())
     }
 
     fn disable_client_mode(mut commands: Commands) {
         commands.remove_resource::<RenetClient>();
         commands.remove_resource::<NetcodeClientTransport>();
+        commands.remove_resource::<LocalNetId>();
     }
     
     // fn handle_hotseat_mode(mut commands: Commands) {
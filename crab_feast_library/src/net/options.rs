@@ -0,0 +1,225 @@
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use super::{NetConfig, NetMode};
+
+/// `host`, `connect` or `offline`, like the command-line flags.
+pub const ENV_NET_MODE: &str = "CRAB_FEAST_NET";
+/// `ip` or `ip:port`: the address to host on or connect to.
+pub const ENV_NET_ADDR: &str = "CRAB_FEAST_ADDR";
+pub const ENV_NET_PORT: &str = "CRAB_FEAST_PORT";
+
+/// Networking chosen at launch: the mode to start in and the config for it.
+///
+/// Environment variables are applied first, so command-line arguments override them; within
+/// each, later settings override earlier ones.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NetOptions {
+    pub mode: NetMode,
+    pub config: NetConfig,
+}
+
+impl NetOptions {
+    pub const USAGE: &'static str = "\
+Options:
+  --host [ip]               host a game; binds ip (default 0.0.0.0, every interface)
+  --connect [ip[:port]]     join a game (default 127.0.0.1)
+  --port <port>             port to host on or connect to (default 5000)
+
+Environment:
+  CRAB_FEAST_NET            host, connect or offline
+  CRAB_FEAST_ADDR           ip or ip:port
+  CRAB_FEAST_PORT           port";
+
+    /// Options from this process's environment and command line.
+    pub fn from_env_and_args() -> Result<Self, NetOptionsError> {
+        Self::default()
+            .with_env(|name| std::env::var(name).ok())?
+            .with_args(std::env::args().skip(1))
+    }
+
+    /// Applies the `CRAB_FEAST_*` variables that `var` returns.
+    pub fn with_env(
+        mut self,
+        var: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, NetOptionsError> {
+        if let Some(mode) = var(ENV_NET_MODE) {
+            match mode.as_str() {
+                "host" => self.host(),
+                "connect" => self.mode = NetMode::Client,
+                "offline" => self.mode = NetMode::None,
+                _ => return Err(NetOptionsError::InvalidMode(mode)),
+            }
+        }
+        if let Some(addr) = var(ENV_NET_ADDR) {
+            self.set_addr(&addr)?;
+        }
+        if let Some(port) = var(ENV_NET_PORT) {
+            self.set_port(&port)?;
+        }
+        Ok(self)
+    }
+
+    /// Applies `--host`, `--connect` and `--port`, without the program name.
+    pub fn with_args(
+        mut self,
+        args: impl IntoIterator<Item = String>,
+    ) -> Result<Self, NetOptionsError> {
+        let mut args = args.into_iter().peekable();
+        while let Some(arg) = args.next() {
+            // Values are optional for the mode flags, so anything that is not a flag is one.
+            let value = args.next_if(|next| !next.starts_with("--"));
+            match arg.as_str() {
+                "--host" => {
+                    self.host();
+                    if let Some(ip) = value {
+                        let ip = ip
+                            .parse()
+                            .map_err(|_| NetOptionsError::InvalidAddress(ip))?;
+                        self.config = std::mem::take(&mut self.config).with_ip(ip);
+                    }
+                }
+                "--connect" => {
+                    self.mode = NetMode::Client;
+                    if let Some(addr) = value {
+                        self.set_addr(&addr)?;
+                    }
+                }
+                "--port" => {
+                    let port = value.ok_or(NetOptionsError::MissingValue("--port"))?;
+                    self.set_port(&port)?;
+                }
+                _ => return Err(NetOptionsError::UnknownArgument(arg)),
+            }
+        }
+        Ok(self)
+    }
+
+    /// Hosting listens on every interface unless an address is given.
+    fn host(&mut self) {
+        self.mode = NetMode::Server;
+        let every_interface = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+        self.config = std::mem::take(&mut self.config).with_ip(every_interface);
+    }
+
+    fn set_addr(&mut self, addr: &str) -> Result<(), NetOptionsError> {
+        let addr = parse_addr(addr, self.config.port())?;
+        self.config = std::mem::take(&mut self.config).with_addr(addr);
+        Ok(())
+    }
+
+    fn set_port(&mut self, port: &str) -> Result<(), NetOptionsError> {
+        let port = port
+            .parse()
+            .map_err(|_| NetOptionsError::InvalidPort(port.to_string()))?;
+        self.config = std::mem::take(&mut self.config).with_port(port);
+        Ok(())
+    }
+}
+
+/// Parses `ip` or `ip:port` (`[ip]:port` for IPv6), using `default_port` when none is given.
+pub fn parse_addr(addr: &str, default_port: u16) -> Result<SocketAddr, NetOptionsError> {
+    let addr = addr.trim();
+    addr.parse::<SocketAddr>()
+        .or_else(|_| {
+            addr.parse::<IpAddr>()
+                .map(|ip| SocketAddr::new(ip, default_port))
+        })
+        .map_err(|_| NetOptionsError::InvalidAddress(addr.to_string()))
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NetOptionsError {
+    UnknownArgument(String),
+    /// The flag needs a value that was not given.
+    MissingValue(&'static str),
+    InvalidAddress(String),
+    InvalidPort(String),
+    InvalidMode(String),
+}
+
+impl fmt::Display for NetOptionsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownArgument(arg) => write!(f, "unknown argument `{arg}`"),
+            Self::MissingValue(flag) => write!(f, "`{flag}` needs a value"),
+            Self::InvalidAddress(addr) => {
+                write!(f, "`{addr}` is not an ip address or ip:port")
+            }
+            Self::InvalidPort(port) => write!(f, "`{port}` is not a port number"),
+            Self::InvalidMode(mode) => {
+                write!(f, "`{mode}` is not one of host, connect or offline")
+            }
+        }
+    }
+}
+
+impl std::error::Error for NetOptionsError {}
+
+#[cfg(test)]
+mod options_tests {
+    use super::*;
+
+    fn args(args: &str) -> Result<NetOptions, NetOptionsError> {
+        NetOptions::default().with_args(args.split_whitespace().map(str::to_string))
+    }
+
+    #[test]
+    fn test_parses_command_line() {
+        let host = args("--host --port 6000").unwrap();
+        assert_eq!(host.mode, NetMode::Server);
+        assert_eq!(host.config.addr(), "0.0.0.0:6000".parse().unwrap());
+
+        let join = args("--connect 192.168.1.20:7000").unwrap();
+        assert_eq!(join.mode, NetMode::Client);
+        assert_eq!(join.config.addr(), "192.168.1.20:7000".parse().unwrap());
+
+        let local = args("--port 6000 --connect").unwrap();
+        assert_eq!(local.config.addr(), "127.0.0.1:6000".parse().unwrap());
+
+        assert_eq!(
+            args("--connect nowhere").unwrap_err(),
+            NetOptionsError::InvalidAddress("nowhere".into())
+        );
+        assert_eq!(
+            args("--port").unwrap_err(),
+            NetOptionsError::MissingValue("--port")
+        );
+        assert!(args("--fullscreen").is_err());
+    }
+
+    #[test]
+    fn test_hosting_on_every_interface_advertises_dialable_addresses() {
+        let host = args("--host --port 6000").unwrap();
+        let addresses = host.config.public_addresses();
+        assert!(addresses.contains(&"127.0.0.1:6000".parse().unwrap()));
+        assert!(addresses
+            .iter()
+            .all(|addr| !addr.ip().is_unspecified() && addr.port() == 6000));
+
+        let public = "203.0.113.7:6000".parse().unwrap();
+        let forwarded = host.config.with_public_addr(public);
+        assert_eq!(forwarded.public_addresses(), vec![public]);
+    }
+
+    #[test]
+    fn test_arguments_override_environment() {
+        let env = |name: &str| match name {
+            ENV_NET_MODE => Some("connect".to_string()),
+            ENV_NET_ADDR => Some("[::1]:7000".to_string()),
+            _ => None,
+        };
+        let options = NetOptions::default().with_env(env).unwrap();
+        assert_eq!(options.mode, NetMode::Client);
+        assert_eq!(options.config.addr(), "[::1]:7000".parse().unwrap());
+
+        let options = options
+            .with_args(["--port".to_string(), "6000".to_string()])
+            .unwrap();
+        assert_eq!(options.config.addr(), "[::1]:6000".parse().unwrap());
+        assert_eq!(
+            parse_addr("10.0.0.2", 5000),
+            Ok("10.0.0.2:5000".parse().unwrap())
+        );
+    }
+}
@@ -0,0 +1,340 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+use bevy::math::bounding::{Aabb3d, BoundingSphere, BoundingVolume, IntersectsVolume, RayCast3d};
+use bevy::math::{I64Vec3, Vec3A};
+use bevy::prelude::*;
+
+use super::spatial_structure::{cuboid, is_finite};
+use super::{SpatialError, SpatialStructure};
+
+/// Most cells a single box may cover; bigger boxes belong in an [`Octree`](super::Octree).
+pub const MAX_CELLS_PER_ENTRY: u64 = 4096;
+
+/// Cells drawn in full red; emptier cells shade toward green.
+const DRAW_CROWDED_CELL: usize = 8;
+
+/// An unbounded uniform grid of cubic cells, hashed by cell coordinate. Each key is listed in
+/// every cell its box overlaps, so queries only look at the cells they touch.
+///
+/// Best for many objects of similar size, about one cell across, spread evenly. Moving a key
+/// within the same cells only updates its box.
+pub struct HashGrid<K> {
+    cell_size: f32,
+    cells: HashMap<IVec3, Vec<K>>,
+    entries: HashMap<K, Aabb3d>,
+    /// Union of every box inserted since the grid was last empty; raycasts are clipped to it.
+    bounds: Option<Aabb3d>,
+}
+
+/// Inclusive range of cell coordinates.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct CellRange {
+    min: IVec3,
+    max: IVec3,
+}
+
+impl CellRange {
+    fn count(&self) -> u64 {
+        let size = (self.max.as_i64vec3() - self.min.as_i64vec3() + 1).max(I64Vec3::ZERO);
+        (size.x as u64)
+            .saturating_mul(size.y as u64)
+            .saturating_mul(size.z as u64)
+    }
+
+    fn contains(&self, cell: IVec3) -> bool {
+        cell.cmpge(self.min).all() && cell.cmple(self.max).all()
+    }
+
+    fn iter(self) -> impl Iterator<Item = IVec3> {
+        (self.min.z..=self.max.z).flat_map(move |z| {
+            (self.min.y..=self.max.y)
+                .flat_map(move |y| (self.min.x..=self.max.x).map(move |x| IVec3::new(x, y, z)))
+        })
+    }
+}
+
+impl<K: Copy + Eq + Hash> HashGrid<K> {
+    pub fn new(cell_size: f32) -> Self {
+        assert!(cell_size > 0.0, "cell size must be positive");
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+            entries: HashMap::new(),
+            bounds: None,
+        }
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    pub fn cell_at(&self, point: Vec3) -> IVec3 {
+        (point / self.cell_size).floor().as_ivec3()
+    }
+
+    pub fn cell_aabb(&self, cell: IVec3) -> Aabb3d {
+        let min = cell.as_vec3() * self.cell_size;
+        Aabb3d {
+            min: min.into(),
+            max: (min + self.cell_size).into(),
+        }
+    }
+
+    /// Keys listed in `cell`.
+    pub fn cell(&self, cell: IVec3) -> &[K] {
+        self.cells.get(&cell).map_or(&[], Vec::as_slice)
+    }
+
+    /// Occupied cells and the keys listed in each.
+    pub fn iter_cells(&self) -> impl Iterator<Item = (IVec3, &[K])> + '_ {
+        self.cells
+            .iter()
+            .map(|(cell, keys)| (*cell, keys.as_slice()))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (K, Aabb3d)> + '_ {
+        self.entries.iter().map(|(key, aabb)| (*key, *aabb))
+    }
+
+    fn cell_range(&self, aabb: &Aabb3d) -> CellRange {
+        CellRange {
+            min: self.cell_at(aabb.min.into()),
+            max: self.cell_at(aabb.max.into()),
+        }
+    }
+
+    fn link(&mut self, key: K, range: CellRange) {
+        for cell in range.iter() {
+            self.cells.entry(cell).or_default().push(key);
+        }
+    }
+
+    fn unlink(&mut self, key: K, range: CellRange) {
+        for cell in range.iter() {
+            if let Some(keys) = self.cells.get_mut(&cell) {
+                keys.retain(|k| *k != key);
+                if keys.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        }
+    }
+
+    /// Distinct keys listed in the cells of `range` whose box passes `filter`. Walks the
+    /// occupied cells instead when the range covers more cells than there are.
+    fn collect(&self, range: CellRange, filter: impl Fn(&Aabb3d) -> bool) -> Vec<K> {
+        let mut seen = HashSet::new();
+        let mut results = Vec::new();
+        let mut visit = |keys: &[K]| {
+            for &key in keys {
+                if seen.insert(key) && filter(&self.entries[&key]) {
+                    results.push(key);
+                }
+            }
+        };
+        if range.count() > self.cells.len() as u64 {
+            for (cell, keys) in &self.cells {
+                if range.contains(*cell) {
+                    visit(keys);
+                }
+            }
+        } else {
+            for cell in range.iter() {
+                visit(self.cell(cell));
+            }
+        }
+        results
+    }
+
+    /// Cells crossed by the ray, in order, clipped to the area anything was inserted in
+    /// (3D DDA over the grid).
+    fn cells_on_ray(&self, ray: &RayCast3d) -> Vec<IVec3> {
+        let Some(bounds) = self.bounds else {
+            return Vec::new();
+        };
+        let Some(enter) = ray.aabb_intersection_at(&bounds) else {
+            return Vec::new();
+        };
+        let range = self.cell_range(&bounds);
+        let direction = *ray.direction;
+        let start = ray.origin + direction * enter;
+        let mut cell = self.cell_at(start.into()).clamp(range.min, range.max);
+        let step = IVec3::from_array(direction.to_array().map(|d| {
+            if d > 0.0 {
+                1
+            } else if d < 0.0 {
+                -1
+            } else {
+                0
+            }
+        }));
+        // Distance along the ray to the next cell boundary on each axis.
+        let next_boundary = (cell + step.max(IVec3::ZERO)).as_vec3a() * self.cell_size;
+        let mut t_max = Vec3A::select(
+            direction.cmpne(Vec3A::ZERO),
+            (next_boundary - ray.origin) / direction,
+            Vec3A::INFINITY,
+        );
+        let t_delta = self.cell_size / direction.abs();
+
+        let mut cells = Vec::new();
+        while range.contains(cell) {
+            cells.push(cell);
+            let axis = t_max.min_position();
+            if t_max[axis] > ray.max {
+                break;
+            }
+            cell[axis] += step[axis];
+            t_max[axis] += t_delta[axis];
+        }
+        cells
+    }
+}
+
+impl<K: Copy + Eq + Hash> SpatialStructure<K> for HashGrid<K> {
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn get(&self, key: K) -> Option<Aabb3d> {
+        self.entries.get(&key).copied()
+    }
+
+    fn insert(&mut self, key: K, aabb: Aabb3d) -> Result<(), SpatialError> {
+        self.remove(key);
+        if !is_finite(&aabb) {
+            return Err(SpatialError::NonFinite(aabb));
+        }
+        let range = self.cell_range(&aabb);
+        let cells = range.count();
+        if cells > MAX_CELLS_PER_ENTRY {
+            return Err(SpatialError::TooLarge {
+                cells,
+                limit: MAX_CELLS_PER_ENTRY,
+            });
+        }
+        self.link(key, range);
+        self.entries.insert(key, aabb);
+        self.bounds = Some(self.bounds.map_or(aabb, |bounds| bounds.merge(&aabb)));
+        Ok(())
+    }
+
+    fn remove(&mut self, key: K) -> Option<Aabb3d> {
+        let aabb = self.entries.remove(&key)?;
+        self.unlink(key, self.cell_range(&aabb));
+        if self.entries.is_empty() {
+            self.bounds = None;
+        }
+        Some(aabb)
+    }
+
+    fn update(&mut self, key: K, aabb: Aabb3d) -> Result<(), SpatialError> {
+        let Some(previous) = self.entries.get(&key).copied() else {
+            return Err(SpatialError::NotFound);
+        };
+        if !is_finite(&aabb) || self.cell_range(&aabb) != self.cell_range(&previous) {
+            return self.insert(key, aabb);
+        }
+        self.entries.insert(key, aabb);
+        self.bounds = self.bounds.map(|bounds| bounds.merge(&aabb));
+        Ok(())
+    }
+
+    fn query_aabb(&self, area: &Aabb3d) -> Vec<K> {
+        self.collect(self.cell_range(area), |aabb| area.intersects(aabb))
+    }
+
+    fn query_sphere(&self, sphere: &BoundingSphere) -> Vec<K> {
+        self.collect(self.cell_range(&sphere.aabb_3d()), |aabb| {
+            sphere.intersects(aabb)
+        })
+    }
+
+    fn raycast(&self, ray: &RayCast3d) -> Vec<(K, f32)> {
+        let mut seen = HashSet::new();
+        let mut hits = Vec::new();
+        for cell in self.cells_on_ray(ray) {
+            for &key in self.cell(cell) {
+                if seen.insert(key)
+                    && let Some(distance) = ray.aabb_intersection_at(&self.entries[&key])
+                {
+                    hits.push((key, distance));
+                }
+            }
+        }
+        hits.sort_by(|a, b| a.1.total_cmp(&b.1));
+        hits
+    }
+
+    /// Occupied cells are shaded from green to red by how many keys they list; the boxes
+    /// are drawn in white.
+    fn draw(&self, gizmos: &mut Gizmos) {
+        for (cell, keys) in &self.cells {
+            let crowding = (keys.len() as f32 / DRAW_CROWDED_CELL as f32).min(1.0);
+            let color = Color::hsv(120.0 * (1.0 - crowding), 0.6, 0.6);
+            gizmos.cuboid(cuboid(&self.cell_aabb(*cell)), color);
+        }
+        for aabb in self.entries.values() {
+            gizmos.cuboid(cuboid(aabb), Color::WHITE);
+        }
+    }
+}
+
+#[cfg(test)]
+mod hash_grid_tests {
+    use super::*;
+
+    #[test]
+    fn test_cells_cover_boxes_and_empty_on_remove() {
+        let mut grid = HashGrid::new(2.0);
+        grid.insert(1, Aabb3d::new(Vec3::new(1.0, 1.0, 1.0), Vec3::splat(0.5)))
+            .unwrap();
+        grid.insert(2, Aabb3d::new(Vec3::new(2.0, 1.0, 1.0), Vec3::splat(0.5)))
+            .unwrap();
+        assert_eq!(grid.cell(IVec3::ZERO), &[1, 2]);
+        assert_eq!(grid.cell(IVec3::X), &[2]);
+        assert_eq!(grid.iter_cells().count(), 2);
+
+        // Same cells: only the box changes.
+        let nudged = Aabb3d::new(Vec3::new(1.2, 1.0, 1.0), Vec3::splat(0.5));
+        grid.update(1, nudged).unwrap();
+        assert_eq!(grid.get(1), Some(nudged));
+        assert_eq!(grid.cell(IVec3::ZERO), &[1, 2]);
+
+        grid.remove(2);
+        grid.remove(1);
+        assert_eq!(grid.iter_cells().count(), 0);
+        assert!(grid
+            .raycast(&RayCast3d::new(Vec3::ZERO, Dir3::X, 10.0))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_rejects_boxes_spanning_too_many_cells() {
+        let mut grid = HashGrid::new(1.0);
+        let huge = Aabb3d::new(Vec3::ZERO, Vec3::splat(1.0e6));
+        assert!(matches!(
+            grid.insert(1, huge),
+            Err(SpatialError::TooLarge { .. })
+        ));
+        assert!(grid.is_empty());
+    }
+
+    #[test]
+    fn test_raycast_walks_cells_in_order() {
+        let mut grid = HashGrid::new(1.0);
+        for x in 0..10 {
+            let center = Vec3::new(x as f32 * 3.0 + 0.5, 0.5, 0.5);
+            grid.insert(x, Aabb3d::new(center, Vec3::splat(0.25)))
+                .unwrap();
+        }
+        let ray = RayCast3d::new(Vec3::new(-5.0, 0.5, 0.5), Dir3::X, 11.0);
+        assert_eq!(grid.raycast(&ray), vec![(0, 5.25), (1, 8.25)]);
+
+        let backwards = RayCast3d::new(Vec3::new(40.0, 0.5, 0.5), Dir3::NEG_X, 100.0);
+        let hits = grid.raycast(&backwards);
+        assert_eq!(hits.len(), 10);
+        assert_eq!(hits[0].0, 9);
+    }
+}
@@ -1,6 +1,20 @@
+mod hash_grid;
+mod octree;
 #[allow(dead_code)]
 mod quadtree;
+mod spatial_debug;
+mod spatial_index;
+mod spatial_plugin;
+mod spatial_structure;
+#[cfg(test)]
+pub(crate) mod test_rng;
 
 pub mod prelude;
 
-pub use quadtree::*;
\ No newline at end of file
+pub use hash_grid::*;
+pub use octree::*;
+pub use quadtree::*;
+pub use spatial_debug::*;
+pub use spatial_index::*;
+pub use spatial_plugin::*;
+pub use spatial_structure::*;
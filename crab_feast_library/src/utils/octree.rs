@@ -0,0 +1,349 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use bevy::math::bounding::{Aabb3d, BoundingSphere, BoundingVolume, IntersectsVolume, RayCast3d};
+use bevy::math::{BVec3A, Vec3A};
+use bevy::prelude::*;
+
+use super::spatial_structure::{cuboid, is_finite};
+use super::{OutOfBounds, QuadTreeConfig, SpatialError, SpatialStructure};
+
+/// Subdivision settings of an [`Octree`]; the same knobs as the quadtree's.
+pub type OctreeConfig = QuadTreeConfig;
+
+/// An octree of keyed boxes, the 3D counterpart of [`SpatialTree`](super::SpatialTree).
+/// Each box lives in the deepest node that fully contains it, so boxes straddling a split
+/// stay at the parent.
+///
+/// Nodes split once they hold `max_items` and merge back when a removal leaves their subtree
+/// with no more than that. A box outside the root grows the root toward it, or is rejected,
+/// depending on [`OctreeConfig::out_of_bounds`].
+pub struct Octree<K> {
+    root: OctNode<K>,
+    config: OctreeConfig,
+    entries: HashMap<K, Aabb3d>,
+}
+
+struct OctNode<K> {
+    boundary: Aabb3d,
+    /// Octant `i` covers the upper half on x, y, z where bit 0, 1, 2 of `i` is set.
+    children: Option<Box<[OctNode<K>; 8]>>,
+    /// Every item of a leaf; only the items straddling a split once subdivided.
+    items: Vec<(K, Aabb3d)>,
+}
+
+impl<K: Copy + Eq + Hash> Octree<K> {
+    pub fn new(boundary: Aabb3d) -> Self {
+        Self::with_config(boundary, OctreeConfig::default())
+    }
+
+    pub fn with_config(boundary: Aabb3d, config: OctreeConfig) -> Self {
+        Self {
+            root: OctNode::new(boundary),
+            config,
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn config(&self) -> &OctreeConfig {
+        &self.config
+    }
+
+    /// Current area of the root node.
+    pub fn boundary(&self) -> &Aabb3d {
+        &self.root.boundary
+    }
+
+    /// Number of levels below the root.
+    pub fn depth(&self) -> u32 {
+        self.root.depth()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (K, Aabb3d)> + '_ {
+        self.entries.iter().map(|(key, aabb)| (*key, *aabb))
+    }
+
+    /// Visits every node overlapping `filter` and returns the items it accepts.
+    fn collect<T>(
+        &self,
+        filter: impl Fn(&Aabb3d) -> bool,
+        mut hit: impl FnMut(&(K, Aabb3d)) -> Option<T>,
+    ) -> Vec<T> {
+        let mut results = Vec::new();
+        if !filter(&self.root.boundary) {
+            return results;
+        }
+        let mut stack = vec![&self.root];
+        while let Some(node) = stack.pop() {
+            results.extend(node.items.iter().filter_map(&mut hit));
+            if let Some(children) = &node.children {
+                stack.extend(children.iter().filter(|child| filter(&child.boundary)));
+            }
+        }
+        results
+    }
+
+    /// Doubles the root toward `aabb` until it fits; the old root becomes one octant of
+    /// the new one (so its nodes may end up one level deeper than `max_depth`).
+    fn grow_to_contain(&mut self, aabb: &Aabb3d) {
+        while !self.root.boundary.contains(aabb) {
+            let old = self.root.boundary;
+            let size = (old.max - old.min).max(Vec3A::splat(f32::EPSILON));
+            // Growing toward -x / -y / -z puts the old root in the upper octant on that axis.
+            let shift = aabb.min.cmplt(old.min);
+            let offset = Vec3A::select(shift, size, Vec3A::ZERO);
+            let boundary = Aabb3d {
+                min: old.min - offset,
+                max: old.max + (size - offset),
+            };
+            let previous = std::mem::replace(&mut self.root, OctNode::new(boundary));
+            self.root.split();
+            if let Some(children) = &mut self.root.children {
+                children[octant_index(shift)] = previous;
+            }
+            self.root.try_merge(&self.config);
+        }
+    }
+}
+
+impl<K: Copy + Eq + Hash> SpatialStructure<K> for Octree<K> {
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn get(&self, key: K) -> Option<Aabb3d> {
+        self.entries.get(&key).copied()
+    }
+
+    fn insert(&mut self, key: K, aabb: Aabb3d) -> Result<(), SpatialError> {
+        self.remove(key);
+        if !is_finite(&aabb) {
+            return Err(SpatialError::NonFinite(aabb));
+        }
+        if !self.root.boundary.contains(&aabb) {
+            if self.config.out_of_bounds == OutOfBounds::Reject {
+                return Err(SpatialError::OutOfBounds {
+                    aabb,
+                    boundary: self.root.boundary,
+                });
+            }
+            self.grow_to_contain(&aabb);
+        }
+        self.root.insert(key, aabb, 0, &self.config);
+        self.entries.insert(key, aabb);
+        Ok(())
+    }
+
+    fn remove(&mut self, key: K) -> Option<Aabb3d> {
+        let aabb = self.entries.remove(&key)?;
+        self.root.remove(key, &aabb, &self.config);
+        Some(aabb)
+    }
+
+    fn query_aabb(&self, area: &Aabb3d) -> Vec<K> {
+        self.collect(
+            |boundary| area.intersects(boundary),
+            |(key, aabb)| area.intersects(aabb).then_some(*key),
+        )
+    }
+
+    fn query_sphere(&self, sphere: &BoundingSphere) -> Vec<K> {
+        self.collect(
+            |boundary| sphere.intersects(boundary),
+            |(key, aabb)| sphere.intersects(aabb).then_some(*key),
+        )
+    }
+
+    fn raycast(&self, ray: &RayCast3d) -> Vec<(K, f32)> {
+        let mut hits = self.collect(
+            |boundary| ray.aabb_intersection_at(boundary).is_some(),
+            |(key, aabb)| {
+                ray.aabb_intersection_at(aabb)
+                    .map(|distance| (*key, distance))
+            },
+        );
+        hits.sort_by(|a, b| a.1.total_cmp(&b.1));
+        hits
+    }
+
+    /// Nodes are drawn with the hue shifting by depth, each item in its node's color.
+    fn draw(&self, gizmos: &mut Gizmos) {
+        self.root.draw(gizmos, 0.0);
+    }
+}
+
+impl<K: Copy + Eq> OctNode<K> {
+    fn new(boundary: Aabb3d) -> Self {
+        Self {
+            boundary,
+            children: None,
+            items: Vec::new(),
+        }
+    }
+
+    fn depth(&self) -> u32 {
+        self.children.as_ref().map_or(0, |children| {
+            1 + children.iter().map(OctNode::depth).max().unwrap_or(0)
+        })
+    }
+
+    /// The octant that fully contains `aabb`, if it does not straddle the center.
+    fn octant_of(&self, aabb: &Aabb3d) -> Option<usize> {
+        let center = self.boundary.center();
+        let upper = aabb.min.cmpge(center);
+        let lower = aabb.max.cmple(center);
+        (upper | lower).all().then(|| octant_index(upper & !lower))
+    }
+
+    fn insert(&mut self, key: K, aabb: Aabb3d, depth: u32, config: &OctreeConfig) {
+        if self.children.is_none() && self.items.len() >= config.max_items {
+            let half_size = self.boundary.half_size().min_element();
+            if depth < config.max_depth && half_size >= config.min_size {
+                self.split();
+                for (key, aabb) in std::mem::take(&mut self.items) {
+                    self.insert(key, aabb, depth, config);
+                }
+            }
+        }
+        let octant = self.octant_of(&aabb);
+        match (&mut self.children, octant) {
+            (Some(children), Some(octant)) => children[octant].insert(key, aabb, depth + 1, config),
+            _ => self.items.push((key, aabb)),
+        }
+    }
+
+    fn split(&mut self) {
+        let center = self.boundary.center();
+        let (min, max) = (self.boundary.min, self.boundary.max);
+        self.children = Some(Box::new(std::array::from_fn(|octant| {
+            let upper = BVec3A::new(octant & 1 != 0, octant & 2 != 0, octant & 4 != 0);
+            OctNode::new(Aabb3d {
+                min: Vec3A::select(upper, center, min),
+                max: Vec3A::select(upper, max, center),
+            })
+        })));
+    }
+
+    fn remove(&mut self, key: K, aabb: &Aabb3d, config: &OctreeConfig) -> bool {
+        if let Some(index) = self.items.iter().position(|(k, _)| *k == key) {
+            self.items.swap_remove(index);
+            self.try_merge(config);
+            return true;
+        }
+        let octant = self.octant_of(aabb);
+        let (Some(children), Some(octant)) = (&mut self.children, octant) else {
+            return false;
+        };
+        if !children[octant].remove(key, aabb, config) {
+            return false;
+        }
+        self.try_merge(config);
+        true
+    }
+
+    /// Pulls the items of leaf children back up once they fit in this node.
+    fn try_merge(&mut self, config: &OctreeConfig) {
+        let Some(children) = &self.children else {
+            return;
+        };
+        if children.iter().any(|child| child.children.is_some()) {
+            return;
+        }
+        let count = self.items.len()
+            + children
+                .iter()
+                .map(|child| child.items.len())
+                .sum::<usize>();
+        if count > config.max_items {
+            return;
+        }
+        if let Some(children) = self.children.take() {
+            for child in *children {
+                self.items.extend(child.items);
+            }
+        }
+    }
+
+    fn draw(&self, gizmos: &mut Gizmos, hue: f32) {
+        let color = Color::hsv(hue, 0.6, 0.6);
+        gizmos.cuboid(cuboid(&self.boundary), color);
+        for (_, aabb) in &self.items {
+            gizmos.cuboid(cuboid(aabb), color);
+        }
+        if let Some(children) = &self.children {
+            for child in children.iter() {
+                child.draw(gizmos, (hue + 30.0) % 360.0);
+            }
+        }
+    }
+}
+
+fn octant_index(upper: BVec3A) -> usize {
+    upper.bitmask() as usize
+}
+
+#[cfg(test)]
+mod octree_tests {
+    use super::*;
+
+    fn boxed(center: Vec3, half_size: f32) -> Aabb3d {
+        Aabb3d::new(center, Vec3::splat(half_size))
+    }
+
+    #[test]
+    fn test_subdivides_and_merges_back() {
+        let config = OctreeConfig {
+            max_items: 2,
+            ..default()
+        };
+        let mut tree = Octree::with_config(boxed(Vec3::ZERO, 16.0), config);
+        let corners = [
+            Vec3::splat(-8.0),
+            Vec3::splat(8.0),
+            Vec3::new(8.0, -8.0, 8.0),
+        ];
+        for (key, center) in corners.into_iter().enumerate() {
+            tree.insert(key, boxed(center, 1.0)).unwrap();
+        }
+        // Straddles every split, so it stays at the root.
+        tree.insert(3, boxed(Vec3::ZERO, 1.0)).unwrap();
+        assert_eq!(tree.depth(), 1);
+        assert_eq!(tree.root.items.len(), 1);
+        assert_eq!(tree.query_aabb(&boxed(Vec3::splat(8.0), 2.0)), vec![1]);
+
+        assert!(tree.remove(0).is_some());
+        assert!(tree.remove(1).is_some());
+        assert_eq!(tree.depth(), 0);
+        assert_eq!(tree.len(), 2);
+        assert_eq!(
+            tree.query_sphere(&BoundingSphere::new(Vec3::ZERO, 0.5)),
+            vec![3]
+        );
+    }
+
+    #[test]
+    fn test_out_of_bounds_grow_and_reject() {
+        let mut tree = Octree::new(boxed(Vec3::ZERO, 1.0));
+        tree.insert(1, boxed(Vec3::new(-6.0, 0.5, 0.5), 0.5))
+            .unwrap();
+        assert_eq!(tree.boundary().min, Vec3A::new(-7.0, -1.0, -1.0));
+        assert_eq!(tree.boundary().max, Vec3A::new(1.0, 7.0, 7.0));
+        let ray = RayCast3d::new(Vec3::new(0.0, 0.5, 0.5), Dir3::NEG_X, 10.0);
+        assert_eq!(tree.raycast(&ray), vec![(1, 5.5)]);
+
+        let config = OctreeConfig {
+            out_of_bounds: OutOfBounds::Reject,
+            ..default()
+        };
+        let mut tree = Octree::with_config(boxed(Vec3::ZERO, 1.0), config);
+        let outside = boxed(Vec3::splat(3.0), 0.5);
+        assert_eq!(
+            tree.insert(1, outside),
+            Err(SpatialError::OutOfBounds {
+                aabb: outside,
+                boundary: boxed(Vec3::ZERO, 1.0),
+            })
+        );
+        assert!(tree.is_empty());
+    }
+}
@@ -1,69 +1,147 @@
-
-
 use bevy::{math::bounding::{Aabb2d, BoundingVolume, IntersectsVolume}, prelude::*};
-use std::{ptr, sync::Arc};
+use std::{fmt, ptr, sync::Arc};
 
 pub trait QuadTreeData {
     fn aabb_2d(&self) -> Aabb2d;
 }
 
+/// 插入的数据不完全在根节点范围内时的处理方式。
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutOfBounds {
+    /// 根节点向数据方向成倍扩大，直到包含它
+    #[default]
+    Grow,
+    /// 拒绝插入，返回 [`QuadTreeError::OutOfBounds`]
+    Reject,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QuadTreeConfig {
+    /// 叶子节点的数据达到此数量后，再插入时细分
+    pub max_items: usize,
+    /// 子节点边长不能小于此值
+    pub min_size: f32,
+    /// 根节点深度为 0，达到此深度的节点不再细分
+    pub max_depth: u32,
+    pub out_of_bounds: OutOfBounds,
+}
+
+impl Default for QuadTreeConfig {
+    fn default() -> Self {
+        Self {
+            max_items: 8,
+            min_size: 1.0,
+            max_depth: 8,
+            out_of_bounds: OutOfBounds::Grow,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum QuadTreeError {
+    /// 数据不在根节点范围内（[`OutOfBounds::Reject`]），或包围盒不是有限值
+    OutOfBounds { aabb: Aabb2d, boundary: Aabb2d },
+}
+
+impl fmt::Display for QuadTreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfBounds { aabb, boundary } => write!(
+                f,
+                "{:?}..{:?} is outside the quadtree bounds {:?}..{:?}",
+                aabb.min, aabb.max, boundary.min, boundary.max
+            ),
+        }
+    }
+}
+
+impl std::error::Error for QuadTreeError {}
+
 pub struct QuadNode<T> {
     boundary: Aabb2d,
+    config: QuadTreeConfig,
     children: Option<[Box<QuadNode<T>>; 4]>,
+    /// 叶子节点的全部数据；有子节点时只保存跨越子节点边界的数据
     data: Vec<Arc<T>>,
 }
 
 impl <T : QuadTreeData> QuadNode<T> {
     pub fn new(boundary: Aabb2d) -> Self {
+        Self::with_config(boundary, QuadTreeConfig::default())
+    }
+
+    pub fn with_config(boundary: Aabb2d, config: QuadTreeConfig) -> Self {
         Self {
             children: None,
             boundary,
+            config,
             data: Vec::new(),
         }
     }
 
-    pub fn insert(&mut self, data: Arc<T>) {
-        if self.children.is_none() && self.data.len() > 1 && self.boundary.half_size().xy().length_squared() > 6.0 {
-            self.subdivide();
-        }
+    pub fn config(&self) -> &QuadTreeConfig {
+        &self.config
+    }
 
-        match self.children {
-            Some(ref mut children) => {
-                for child in children {
-                    if child.boundary.contains(&data.aabb_2d()) {
-                        child.insert(data);
-                        break;
-                    }
-                }
-            }
-            None => {
-                self.data.push(data);
+    pub fn insert(&mut self, data: Arc<T>) -> Result<(), QuadTreeError> {
+        let aabb = data.aabb_2d();
+        if !self.boundary.contains(&aabb) {
+            let finite = aabb.min.is_finite() && aabb.max.is_finite();
+            if !finite || self.config.out_of_bounds == OutOfBounds::Reject {
+                return Err(QuadTreeError::OutOfBounds { aabb, boundary: self.boundary });
             }
+            self.grow_to_contain(&aabb);
         }
+        self.insert_at(data, &aabb, 0);
+        Ok(())
     }
 
-    pub fn remove(&mut self, data: &T) {
-        for i in 0..self.data.len() {
-            if ptr::eq(self.data[i].as_ref(), data) {
-                self.data.remove(i);
-                return;
-            }
+    fn insert_at(&mut self, data: Arc<T>, aabb: &Aabb2d, depth: u32) {
+        if self.children.is_none() && self.data.len() >= self.config.max_items && self.can_subdivide(depth) {
+            self.subdivide(depth);
         }
 
-        if let Some(children) = &mut self.children {
-            for child in children {
-                if child.boundary.contains(&data.aabb_2d()) {
-                    child.remove(data);
-                    break;
-                }
-            }
+        if let Some(ref mut children) = self.children
+            && let Some(child) = children.iter_mut().find(|child| child.boundary.contains(aabb))
+        {
+            child.insert_at(data, aabb, depth + 1);
+            return;
         }
+        // 跨越子节点边界的数据留在当前节点
+        self.data.push(data);
+    }
+
+    /// 按指针移除数据；移除后子树数据不超过 `max_items` 的节点会合并子节点。
+    pub fn remove(&mut self, data: &T) -> bool {
+        if let Some(i) = self.data.iter().position(|d| ptr::eq(d.as_ref(), data)) {
+            self.data.remove(i);
+            self.try_merge();
+            return true;
+        }
+
+        let aabb = data.aabb_2d();
+        let Some(children) = &mut self.children else {
+            return false;
+        };
+        let Some(child) = children.iter_mut().find(|child| child.boundary.contains(&aabb)) else {
+            return false;
+        };
+        if !child.remove(data) {
+            return false;
+        }
+        self.try_merge();
+        true
     }
 
     pub fn query(&self, boundary: &Aabb2d) -> Vec<Arc<T>> {
         let mut results = Vec::new();
+        self.query_into(boundary, &mut results);
+        results
+    }
+
+    fn query_into(&self, boundary: &Aabb2d, results: &mut Vec<Arc<T>>) {
         if !self.boundary.intersects(boundary) {
-            return results;
+            return;
         }
 
         for data in &self.data {
@@ -74,12 +152,24 @@ impl <T : QuadTreeData> QuadNode<T> {
 
         if let Some(children) = &self.children {
             for child in children {
-                results.extend(child.query(boundary));
+                child.query_into(boundary, results);
             }
         }
-        results
     }
-    
+
+    /// 子树中的数据总数。
+    pub fn len(&self) -> usize {
+        self.data.len() + self.iter_children().map_or(0, |children| children.map(|child| child.len()).sum())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn depth(&self) -> u32 {
+        self.iter_children().map_or(0, |children| 1 + children.map(|child| child.depth()).max().unwrap_or(0))
+    }
+
     pub fn iter_children(&self) -> Option<impl Iterator<Item = &Box<QuadNode<T>>> + '_> {
         self.children.as_ref().map(|c| c.iter())
     }
@@ -120,7 +210,11 @@ impl <T : QuadTreeData> QuadNode<T> {
         &self.boundary
     }
 
-    fn subdivide(&mut self) {
+    fn can_subdivide(&self, depth: u32) -> bool {
+        depth < self.config.max_depth && self.boundary.half_size().min_element() >= self.config.min_size
+    }
+
+    fn subdivide(&mut self, depth: u32) {
         if self.children.is_some() {
             return;
         }
@@ -128,37 +222,90 @@ impl <T : QuadTreeData> QuadNode<T> {
         let min = self.boundary.min;
         let max = self.boundary.max;
         let center = self.boundary.center();
+        let config = self.config;
         // 0 1
         // 3 2
         self.children = Some([
-            Box::new(QuadNode::new(Aabb2d{
+            Box::new(QuadNode::with_config(Aabb2d{
                 min: Vec2::new(self.boundary.min.x, center.y),
                 max: Vec2::new(center.x, max.y),
-            })),
-            Box::new(QuadNode::new(Aabb2d{
+            }, config)),
+            Box::new(QuadNode::with_config(Aabb2d{
                 min: center,
                 max,
-            })),
-            Box::new(QuadNode::new(Aabb2d{
+            }, config)),
+            Box::new(QuadNode::with_config(Aabb2d{
                 min: Vec2::new(center.x, self.boundary.min.y),
                 max: Vec2::new(max.x, center.y),
-            })),
-            Box::new(QuadNode::new(Aabb2d{
+            }, config)),
+            Box::new(QuadNode::with_config(Aabb2d{
                 min,
                 max: center,
-            })),
+            }, config)),
         ]);
 
-        self.data = self.data.drain(..).filter_map(|t| {
-            for child in self.children.as_mut().unwrap().iter_mut() {
-                if child.boundary.contains(&t.aabb_2d()) {
-                    child.insert(t);
-                    return None;
-                }
+        let data = std::mem::take(&mut self.data);
+        for t in data {
+            let aabb = t.aabb_2d();
+            self.insert_at(t, &aabb, depth);
+        }
+    }
+
+    /// 子节点都是叶子且数据总数不超过 `max_items` 时，把数据收回当前节点。
+    fn try_merge(&mut self) {
+        let Some(children) = &self.children else {
+            return;
+        };
+        if children.iter().any(|child| child.children.is_some()) {
+            return;
+        }
+        let count = self.data.len() + children.iter().map(|child| child.data.len()).sum::<usize>();
+        if count > self.config.max_items {
+            return;
+        }
+        if let Some(children) = self.children.take() {
+            for child in children {
+                self.data.extend(child.data);
             }
-            return Some(t);
-        }).collect();
-        
+        }
+    }
+
+    /// 根节点朝 `aabb` 的方向成倍扩大，原根节点成为新根节点的一个子节点
+    /// （原有节点因此可能比 `max_depth` 深一层）。
+    fn grow_to_contain(&mut self, aabb: &Aabb2d) {
+        while !self.boundary.contains(aabb) {
+            let old = self.boundary;
+            let size = (old.max - old.min).max(Vec2::splat(f32::EPSILON));
+            // 原节点在新根节点中的象限：向 -x / -y 扩大时位于右 / 上
+            let shift = IVec2::new((aabb.min.x < old.min.x) as i32, (aabb.min.y < old.min.y) as i32);
+            let quadrant = |ix: i32, iy: i32| {
+                let offset = IVec2::new(ix, iy) - shift;
+                let offset = offset.as_vec2() * size;
+                Aabb2d {
+                    min: old.min + offset,
+                    max: old.max + offset,
+                }
+            };
+            let boundary = Aabb2d {
+                min: old.min - shift.as_vec2() * size,
+                max: old.max + (IVec2::ONE - shift).as_vec2() * size,
+            };
+
+            let config = self.config;
+            let previous = std::mem::replace(self, QuadNode::with_config(boundary, config));
+            let slot = |ix: i32, iy: i32| Box::new(QuadNode::with_config(quadrant(ix, iy), config));
+            let mut children = [slot(0, 1), slot(1, 1), slot(1, 0), slot(0, 0)];
+            let index = match (shift.x, shift.y) {
+                (0, 1) => 0,
+                (1, 1) => 1,
+                (1, 0) => 2,
+                _ => 3,
+            };
+            *children[index] = previous;
+            self.children = Some(children);
+            // 原根节点是数据不多的叶子时，直接收回新根节点
+            self.try_merge();
+        }
     }
 
     pub fn draw(&self, gizmos: &mut Gizmos, hue: Option<f32>) {
@@ -180,11 +327,13 @@ impl <T : QuadTreeData> QuadNode<T> {
 
 
 
+
 #[cfg(test)]
 mod quadtree_tests {
     use bevy::math::bounding::Bounded2d;
     use std::ptr;
 
+    use super::super::test_rng::Rng;
     use super::*;
 
    struct TestData(Rectangle, Vec2);
@@ -195,16 +344,23 @@ mod quadtree_tests {
         }
     }
 
+    fn two_per_node() -> QuadTreeConfig {
+        QuadTreeConfig {
+            max_items: 2,
+            ..default()
+        }
+    }
+
     #[test]
     fn test_insert() {
-        let mut tree = QuadNode::new(Aabb2d{
+        let mut tree = QuadNode::with_config(Aabb2d{
             min: Vec2::new(0.0, 0.0),
             max: Vec2::new(10.0, 10.0),
-        });
+        }, two_per_node());
 
         let data1 = Arc::new(TestData(Rectangle::from_size(Vec2::new(1.0, 1.0)), Vec2::new(4.0, 2.0)));
         let data1_ref = Arc::clone(&data1);
-        tree.insert(data1);
+        tree.insert(data1).unwrap();
 
         assert!(tree.children.is_none());
         assert!(tree.data.len() == 1);
@@ -212,7 +368,7 @@ mod quadtree_tests {
 
         let data2 = Arc::new(TestData(Rectangle::from_size(Vec2::new(1.0, 1.0)), Vec2::new(7.0, 7.0)));
         let data2_ref = Arc::clone(&data2);
-        tree.insert(data2);
+        tree.insert(data2).unwrap();
 
 
         assert!(tree.children.is_none());
@@ -221,7 +377,7 @@ mod quadtree_tests {
 
         let data3 = Arc::new(TestData(Rectangle::from_size(Vec2::new(1.0, 1.0)), Vec2::new(3.0, 3.0)));
         let data3_ref = Arc::clone(&data3);
-        tree.insert(data3);
+        tree.insert(data3).unwrap();
 
         assert!(tree.children.is_some());
         assert!(tree.data.len() == 0);
@@ -233,20 +389,20 @@ mod quadtree_tests {
 
     #[test]
     fn test_query() {
-        let mut tree = QuadNode::new(Aabb2d{
+        let mut tree = QuadNode::with_config(Aabb2d{
             min: Vec2::new(0.0, 0.0),
             max: Vec2::new(10.0, 10.0),
-        });
+        }, two_per_node());
         let data1 = Arc::new(TestData(Rectangle::from_size(Vec2::new(1.0, 1.0)), Vec2::new(4.0, 2.0)));
         let data1_ref = Arc::clone(&data1);
-        tree.insert(data1);
+        tree.insert(data1).unwrap();
 
         let data2 = Arc::new(TestData(Rectangle::from_size(Vec2::new(1.0, 1.0)), Vec2::new(7.0, 7.0)));
-        tree.insert(data2);
+        tree.insert(data2).unwrap();
 
         let data3 = Arc::new(TestData(Rectangle::from_size(Vec2::new(1.0, 1.0)), Vec2::new(3.0, 3.0)));
         let data3_ref = Arc::clone(&data3);
-        tree.insert(data3);
+        tree.insert(data3).unwrap();
 
 
         let query_boundary = Aabb2d{
@@ -263,20 +419,20 @@ mod quadtree_tests {
 
     #[test]
     fn test_remove() {
-        let mut tree = QuadNode::new(Aabb2d{
+        let mut tree = QuadNode::with_config(Aabb2d{
             min: Vec2::new(0.0, 0.0),
             max: Vec2::new(10.0, 10.0),
-        });
+        }, two_per_node());
         let data1 = Arc::new(TestData(Rectangle::from_size(Vec2::new(1.0, 1.0)), Vec2::new(4.0, 2.0)));
         let data1_ref = Arc::clone(&data1);
-        tree.insert(data1);
+        tree.insert(data1).unwrap();
 
         let data2 = Arc::new(TestData(Rectangle::from_size(Vec2::new(1.0, 1.0)), Vec2::new(7.0, 7.0)));
-        tree.insert(data2);
+        tree.insert(data2).unwrap();
 
         let data3 = Arc::new(TestData(Rectangle::from_size(Vec2::new(1.0, 1.0)), Vec2::new(3.0, 3.0)));
         let data3_ref = Arc::clone(&data3);
-        tree.insert(data3);
+        tree.insert(data3).unwrap();
 
         let query_boundary = Aabb2d{
             min: Vec2::new(2.0, 2.0),
@@ -290,4 +446,186 @@ mod quadtree_tests {
 
     }
 
+    #[test]
+    fn test_insert_straddling_after_subdivide() {
+        let mut tree = QuadNode::with_config(Aabb2d{
+            min: Vec2::new(0.0, 0.0),
+            max: Vec2::new(10.0, 10.0),
+        }, two_per_node());
+        for position in [Vec2::new(2.0, 2.0), Vec2::new(3.0, 3.0), Vec2::new(7.0, 7.0)] {
+            tree.insert(Arc::new(TestData(Rectangle::from_size(Vec2::new(1.0, 1.0)), position))).unwrap();
+        }
+        assert!(tree.children.is_some());
+
+        let straddling = Arc::new(TestData(Rectangle::from_size(Vec2::new(2.0, 2.0)), Vec2::new(5.0, 5.0)));
+        let straddling_ref = Arc::clone(&straddling);
+        tree.insert(straddling).unwrap();
+        assert!(ptr::eq(tree.data[0].as_ref(), straddling_ref.as_ref()));
+
+        let results = tree.query(&Aabb2d{
+            min: Vec2::new(4.5, 4.5),
+            max: Vec2::new(5.5, 5.5),
+        });
+        assert!(results.len() == 1);
+
+        tree.remove(straddling_ref.as_ref());
+        assert!(tree.data.is_empty());
+    }
+
+    fn random_data(rng: &mut Rng, spread: f32) -> Arc<TestData> {
+        let size = rng.vec2(0.0, 4.0);
+        let position = rng.vec2(-spread, spread);
+        Arc::new(TestData(Rectangle::from_size(size), position))
+    }
+
+    fn sorted_ptrs(items: &[Arc<TestData>]) -> Vec<usize> {
+        let mut ptrs: Vec<usize> = items.iter().map(|d| Arc::as_ptr(d) as usize).collect();
+        ptrs.sort_unstable();
+        ptrs
+    }
+
+    /// 结构不变量：子节点数据都在子节点范围内，留在有子节点的节点上的数据不属于任何子节点，
+    /// 有子节点的子树数据多于 `max_items`（否则应已合并）。
+    fn check_invariants(node: &QuadNode<TestData>, is_root: bool) {
+        let config = node.config;
+        if !is_root {
+            for data in &node.data {
+                assert!(node.boundary.contains(&data.aabb_2d()));
+            }
+        }
+        if let Some(children) = &node.children {
+            assert!(node.len() > config.max_items);
+            for data in &node.data {
+                assert!(children.iter().all(|child| !child.boundary.contains(&data.aabb_2d())));
+            }
+            for child in children {
+                check_invariants(child, false);
+            }
+        }
+    }
+
+    #[test]
+    fn test_property_queries_match_linear_scan() {
+        let configs = [
+            two_per_node(),
+            QuadTreeConfig::default(),
+            QuadTreeConfig {
+                max_items: 1,
+                min_size: 0.5,
+                max_depth: 3,
+                ..default()
+            },
+        ];
+        for seed in 1..=40u64 {
+            let mut rng = Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+            let config = configs[seed as usize % configs.len()];
+            let mut tree = QuadNode::with_config(Aabb2d::new(Vec2::ZERO, Vec2::splat(20.0)), config);
+            let mut items: Vec<Arc<TestData>> = Vec::new();
+
+            for _ in 0..200 {
+                // 先插入为主，之后交替删除，覆盖细分与合并
+                if items.is_empty() || rng.next() < 0.6 {
+                    // 偶尔超出范围，触发根节点扩大
+                    let spread = if rng.next() < 0.05 { 60.0 } else { 20.0 };
+                    let data = random_data(&mut rng, spread);
+                    tree.insert(data.clone()).unwrap();
+                    items.push(data);
+                } else {
+                    let index = (rng.next() * items.len() as f32) as usize % items.len();
+                    let data = items.swap_remove(index);
+                    assert!(tree.remove(&data));
+                    assert!(!tree.remove(&data));
+                }
+
+                assert_eq!(tree.len(), items.len());
+                check_invariants(&tree, true);
+
+                let area = Aabb2d::new(rng.vec2(-40.0, 40.0), rng.vec2(0.0, 15.0));
+                let expected: Vec<Arc<TestData>> = items
+                    .iter()
+                    .filter(|d| area.intersects(&d.aabb_2d()))
+                    .cloned()
+                    .collect();
+                assert_eq!(sorted_ptrs(&tree.query(&area)), sorted_ptrs(&expected), "seed {seed}");
+            }
+
+            for data in items.drain(..) {
+                assert!(tree.remove(&data));
+            }
+            assert!(tree.is_empty());
+            assert!(tree.children.is_none(), "seed {seed}: empty tree should merge back to a leaf");
+        }
+    }
+
+    #[test]
+    fn test_respects_max_depth_and_min_size() {
+        let mut tree = QuadNode::with_config(Aabb2d::new(Vec2::ZERO, Vec2::splat(8.0)), QuadTreeConfig {
+            max_items: 1,
+            min_size: 2.0,
+            max_depth: 8,
+            ..default()
+        });
+        // 同一位置的小数据会一直细分：边长 16 → 8 → 4 → 2，再分就小于 min_size
+        for _ in 0..10 {
+            tree.insert(Arc::new(TestData(Rectangle::from_size(Vec2::splat(0.1)), Vec2::splat(1.0)))).unwrap();
+        }
+        assert_eq!(tree.depth(), 3);
+
+        let mut tree = QuadNode::with_config(Aabb2d::new(Vec2::ZERO, Vec2::splat(8.0)), QuadTreeConfig {
+            max_items: 1,
+            min_size: 0.01,
+            max_depth: 1,
+            ..default()
+        });
+        for _ in 0..10 {
+            tree.insert(Arc::new(TestData(Rectangle::from_size(Vec2::splat(0.1)), Vec2::splat(1.0)))).unwrap();
+        }
+        assert_eq!(tree.depth(), 1);
+        assert_eq!(tree.len(), 10);
+    }
+
+    #[test]
+    fn test_remove_merges_children() {
+        let mut tree = QuadNode::with_config(Aabb2d::new(Vec2::splat(5.0), Vec2::splat(5.0)), two_per_node());
+        let items: Vec<_> = [Vec2::new(2.0, 2.0), Vec2::new(7.0, 7.0), Vec2::new(7.0, 2.0)]
+            .into_iter()
+            .map(|p| Arc::new(TestData(Rectangle::from_size(Vec2::ONE), p)))
+            .collect();
+        for data in &items {
+            tree.insert(data.clone()).unwrap();
+        }
+        assert!(tree.children.is_some());
+
+        tree.remove(&items[0]);
+        assert!(tree.children.is_none());
+        assert_eq!(tree.data.len(), 2);
+    }
+
+    #[test]
+    fn test_out_of_bounds_grow_and_reject() {
+        let bounds = Aabb2d::new(Vec2::splat(5.0), Vec2::splat(5.0));
+        let far = Arc::new(TestData(Rectangle::from_size(Vec2::ONE), Vec2::new(-25.0, 14.0)));
+
+        let mut tree = QuadNode::with_config(bounds, two_per_node());
+        tree.insert(far.clone()).unwrap();
+        assert!(tree.get_bouding_box().contains(&far.aabb_2d()));
+        // 原根节点成为新根节点的子节点，尺寸按 2 的幂扩大
+        let size = tree.get_bouding_box().half_size() * 2.0;
+        assert_eq!(size, Vec2::new(40.0, 40.0));
+        assert_eq!(tree.query(&far.aabb_2d()).len(), 1);
+        assert!(tree.remove(&far));
+
+        let mut tree = QuadNode::with_config(bounds, QuadTreeConfig {
+            out_of_bounds: OutOfBounds::Reject,
+            ..two_per_node()
+        });
+        assert_eq!(
+            tree.insert(far.clone()),
+            Err(QuadTreeError::OutOfBounds { aabb: far.aabb_2d(), boundary: bounds })
+        );
+        assert!(tree.is_empty());
+
+        let nan = Arc::new(TestData(Rectangle::from_size(Vec2::ONE), Vec2::NAN));
+        assert!(QuadNode::new(bounds).insert(nan).is_err());
+    }
 }
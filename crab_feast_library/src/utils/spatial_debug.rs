@@ -0,0 +1,256 @@
+use std::sync::{Mutex, PoisonError};
+
+use bevy::math::bounding::{Aabb2d, BoundingVolume};
+use bevy::prelude::*;
+
+use super::spatial_plugin::update_spatial_index;
+use super::{QuadNode, SpatialEntry, SpatialIndex, SpatialPlane};
+
+const QUERY_COLOR: Color = Color::srgb(0.2, 0.8, 1.0);
+const HIT_COLOR: Color = Color::srgb(1.0, 0.85, 0.1);
+/// Hue step between quadtree levels, in degrees.
+const DEPTH_HUE_STEP: f32 = 40.0;
+/// Point footprints are drawn as crosses of this half size.
+const POINT_SIZE: f32 = 0.15;
+
+/// Draws the [`SpatialIndex`] quadtree on its plane in 3D, and the regions and hits of the
+/// [`SpatialQuery`](super::SpatialQuery) calls made during the frame.
+///
+/// Everything is drawn through the [`SpatialGizmos`] group, so the overlay is toggled at
+/// runtime through `GizmoConfigStore`: `enabled` switches all of it, the group's fields
+/// switch the tree and the queries separately.
+pub struct SpatialDebugPlugin;
+
+impl Plugin for SpatialDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_gizmo_group::<SpatialGizmos>()
+            .init_resource::<SpatialQueryLog>()
+            .add_systems(
+                PostUpdate,
+                (draw_spatial_index, draw_spatial_queries).after(update_spatial_index),
+            );
+    }
+}
+
+#[derive(Reflect, GizmoConfigGroup)]
+pub struct SpatialGizmos {
+    /// Quadtree nodes with their footprints. Hue follows depth; saturation and brightness
+    /// grow as a node fills up to `max_items`.
+    pub tree: bool,
+    /// Query regions, with the footprints they returned highlighted.
+    pub queries: bool,
+    /// Offset along the plane normal, to keep lines out of the ground.
+    pub elevation: f32,
+}
+
+impl Default for SpatialGizmos {
+    fn default() -> Self {
+        Self {
+            tree: true,
+            queries: true,
+            elevation: 0.05,
+        }
+    }
+}
+
+/// Shape of a recorded query, on the index plane.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum QueryRegion {
+    Circle {
+        center: Vec2,
+        radius: f32,
+    },
+    Aabb(Aabb2d),
+    Ray {
+        origin: Vec2,
+        end: Vec2,
+    },
+    /// Circle out to the farthest of the nearest hits.
+    Nearest {
+        point: Vec2,
+        radius: f32,
+    },
+}
+
+#[derive(Debug)]
+struct QueryRecord {
+    region: QueryRegion,
+    hits: Vec<Entity>,
+}
+
+/// Queries made through [`SpatialQuery`](super::SpatialQuery) since the overlay last drew.
+/// Only recorded while [`SpatialGizmos::queries`] is shown.
+#[derive(Resource, Default)]
+pub struct SpatialQueryLog {
+    recording: bool,
+    records: Mutex<Vec<QueryRecord>>,
+}
+
+impl SpatialQueryLog {
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    /// Number of queries waiting to be drawn.
+    pub fn len(&self) -> usize {
+        self.records
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(crate) fn record(&self, region: QueryRegion, hits: impl Iterator<Item = Entity>) {
+        self.records
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(QueryRecord {
+                region,
+                hits: hits.collect(),
+            });
+    }
+}
+
+/// Outline of `aabb` on the plane, or a cross for a point footprint.
+fn draw_aabb(
+    gizmos: &mut Gizmos<SpatialGizmos>,
+    plane: SpatialPlane,
+    height: f32,
+    aabb: &Aabb2d,
+    color: Color,
+) {
+    let isometry = Isometry3d::new(plane.unproject(aabb.center(), height), plane.rotation());
+    let size = aabb.max - aabb.min;
+    if size == Vec2::ZERO {
+        gizmos.cross(isometry, POINT_SIZE, color);
+    } else {
+        gizmos.rect(isometry, size, color);
+    }
+}
+
+fn draw_node(
+    gizmos: &mut Gizmos<SpatialGizmos>,
+    plane: SpatialPlane,
+    node: &QuadNode<SpatialEntry<Entity>>,
+    depth: u32,
+) {
+    let height = gizmos.config_ext.elevation;
+    let max_items = node.config().max_items.max(1);
+    let occupancy = (node.iter_data().count() as f32 / max_items as f32).min(1.0);
+    let color = Color::hsv(
+        (depth as f32 * DEPTH_HUE_STEP) % 360.0,
+        0.2 + 0.7 * occupancy,
+        0.5 + 0.5 * occupancy,
+    );
+    draw_aabb(gizmos, plane, height, node.get_bouding_box(), color);
+    for entry in node.iter_data() {
+        draw_aabb(gizmos, plane, height, &entry.aabb, color);
+    }
+    if let Some(children) = node.iter_children() {
+        for child in children {
+            draw_node(gizmos, plane, child, depth + 1);
+        }
+    }
+}
+
+fn draw_spatial_index(mut gizmos: Gizmos<SpatialGizmos>, index: Res<SpatialIndex>) {
+    if !gizmos.config.enabled || !gizmos.config_ext.tree {
+        return;
+    }
+    draw_node(&mut gizmos, index.plane(), index.tree().tree(), 0);
+}
+
+fn draw_spatial_queries(
+    mut gizmos: Gizmos<SpatialGizmos>,
+    index: Res<SpatialIndex>,
+    mut log: ResMut<SpatialQueryLog>,
+) {
+    log.recording = gizmos.config.enabled && gizmos.config_ext.queries;
+    let records = std::mem::take(
+        log.records
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner),
+    );
+    let plane = index.plane();
+    // Slightly above the tree so highlights stay visible over node outlines.
+    let height = gizmos.config_ext.elevation * 2.0;
+    let rotation = plane.rotation();
+    for QueryRecord { region, hits } in records {
+        match region {
+            QueryRegion::Circle { center, radius } => {
+                let isometry = Isometry3d::new(plane.unproject(center, height), rotation);
+                gizmos.circle(isometry, radius, QUERY_COLOR);
+            }
+            QueryRegion::Aabb(area) => draw_aabb(&mut gizmos, plane, height, &area, QUERY_COLOR),
+            QueryRegion::Ray { origin, end } => {
+                gizmos.arrow(
+                    plane.unproject(origin, height),
+                    plane.unproject(end, height),
+                    QUERY_COLOR,
+                );
+            }
+            QueryRegion::Nearest { point, radius } => {
+                let isometry = Isometry3d::new(plane.unproject(point, height), rotation);
+                gizmos.cross(isometry, POINT_SIZE, QUERY_COLOR);
+                gizmos.circle(isometry, radius, QUERY_COLOR);
+            }
+        }
+        for hit in hits {
+            if let Some(aabb) = index.tree().get(hit) {
+                draw_aabb(&mut gizmos, plane, height, &aabb, HIT_COLOR);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod spatial_debug_tests {
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::transform::TransformPlugin;
+
+    use super::super::{SpatialIndexPlugin, SpatialIndexed, SpatialQuery};
+    use super::*;
+
+    #[test]
+    fn test_queries_are_logged_only_while_recording() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            TransformPlugin,
+            SpatialIndexPlugin::default(),
+        ))
+        .init_resource::<SpatialQueryLog>();
+        let target = app
+            .world_mut()
+            .spawn((
+                Transform::from_xyz(1.0, 0.0, 1.0),
+                SpatialIndexed::default(),
+            ))
+            .id();
+        app.update();
+
+        let query = |query: SpatialQuery| {
+            query.within_radius(Vec3::ZERO, 2.0);
+            query.nearest(Vec3::ZERO, 1);
+        };
+        app.world_mut().run_system_once(query).unwrap();
+        assert!(app.world().resource::<SpatialQueryLog>().is_empty());
+
+        app.world_mut().resource_mut::<SpatialQueryLog>().recording = true;
+        app.world_mut().run_system_once(query).unwrap();
+        let log = app.world().resource::<SpatialQueryLog>();
+        let records = log.records.lock().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0].region,
+            QueryRegion::Circle {
+                center: Vec2::ZERO,
+                radius: 2.0
+            }
+        );
+        assert_eq!(records[1].hits, vec![target]);
+    }
+}
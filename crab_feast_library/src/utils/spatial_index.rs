@@ -0,0 +1,381 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::hash::Hash;
+use std::sync::Arc;
+
+use bevy::math::bounding::{Aabb2d, BoundingCircle, IntersectsVolume, RayCast2d};
+use bevy::prelude::*;
+
+use super::{QuadNode, QuadTreeData, QuadTreeError};
+
+/// One indexed key and the footprint it was inserted with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpatialEntry<K> {
+    pub key: K,
+    pub aabb: Aabb2d,
+}
+
+impl<K> QuadTreeData for SpatialEntry<K> {
+    fn aabb_2d(&self) -> Aabb2d {
+        self.aabb
+    }
+}
+
+/// A [`QuadNode`] of keyed footprints that can be moved and removed by key.
+///
+/// Each key owns the `Arc` stored in the tree, so removal and updates find the exact entry
+/// (the tree removes by pointer) without scanning.
+pub struct SpatialTree<K> {
+    tree: QuadNode<SpatialEntry<K>>,
+    entries: HashMap<K, Arc<SpatialEntry<K>>>,
+}
+
+impl<K: Copy + Eq + Hash> SpatialTree<K> {
+    pub fn new(bounds: Aabb2d) -> Self {
+        Self {
+            tree: QuadNode::new(bounds),
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: K) -> Option<Aabb2d> {
+        self.entries.get(&key).map(|entry| entry.aabb)
+    }
+
+    pub fn tree(&self) -> &QuadNode<SpatialEntry<K>> {
+        &self.tree
+    }
+
+    /// Inserts `key` or moves it to `aabb`; a no-op when the footprint did not change.
+    /// When the tree rejects the new footprint the key is no longer indexed.
+    pub fn upsert(&mut self, key: K, aabb: Aabb2d) -> Result<(), QuadTreeError> {
+        if let Some(entry) = self.entries.get(&key) {
+            if entry.aabb == aabb {
+                return Ok(());
+            }
+            self.tree.remove(entry);
+            self.entries.remove(&key);
+        }
+        let entry = Arc::new(SpatialEntry { key, aabb });
+        self.tree.insert(entry.clone())?;
+        self.entries.insert(key, entry);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, key: K) -> Option<Aabb2d> {
+        let entry = self.entries.remove(&key)?;
+        self.tree.remove(&entry);
+        Some(entry.aabb)
+    }
+
+    /// Keys whose footprint overlaps `area`.
+    pub fn in_aabb(&self, area: &Aabb2d) -> Vec<K> {
+        self.tree
+            .query(area)
+            .iter()
+            .map(|entry| entry.key)
+            .collect()
+    }
+
+    /// Keys whose footprint comes within `radius` of `center`.
+    pub fn within_radius(&self, center: Vec2, radius: f32) -> Vec<K> {
+        let circle = BoundingCircle::new(center, radius);
+        self.tree
+            .query(&Aabb2d::new(center, Vec2::splat(radius)))
+            .iter()
+            .filter(|entry| circle.intersects(&entry.aabb))
+            .map(|entry| entry.key)
+            .collect()
+    }
+
+    /// Keys hit by the ray within `max_distance`, nearest first, with the distance along the
+    /// ray to each footprint (`0.0` when the origin is inside it).
+    pub fn ray(&self, origin: Vec2, direction: Dir2, max_distance: f32) -> Vec<(K, f32)> {
+        let ray = RayCast2d::new(origin, direction, max_distance);
+        let mut hits = Vec::new();
+        let mut stack = vec![&self.tree];
+        while let Some(node) = stack.pop() {
+            hits.extend(node.iter_data().filter_map(|entry| {
+                ray.aabb_intersection_at(&entry.aabb)
+                    .map(|distance| (entry.key, distance))
+            }));
+            if let Some(children) = node.iter_children() {
+                stack.extend(
+                    children
+                        .filter(|child| ray.aabb_intersection_at(child.get_bouding_box()).is_some())
+                        .map(|child| child.as_ref()),
+                );
+            }
+        }
+        hits.sort_by(|a, b| a.1.total_cmp(&b.1));
+        hits
+    }
+
+    /// Up to `k` keys nearest to `point`, nearest first, with the distance from `point` to
+    /// each footprint. Best-first search: nodes are opened in order of their distance, so
+    /// only the part of the tree around `point` is visited.
+    pub fn nearest(&self, point: Vec2, k: usize) -> Vec<(K, f32)> {
+        let mut found = Vec::with_capacity(k);
+        let mut open = BinaryHeap::new();
+        open.push(Candidate {
+            distance: distance_to(self.tree.get_bouding_box(), point),
+            item: Item::Node(&self.tree),
+        });
+        while found.len() < k {
+            let Some(Candidate { distance, item }) = open.pop() else {
+                break;
+            };
+            match item {
+                Item::Entry(entry) => found.push((entry.key, distance)),
+                Item::Node(node) => {
+                    open.extend(node.iter_data().map(|entry| Candidate {
+                        distance: distance_to(&entry.aabb, point),
+                        item: Item::Entry(entry),
+                    }));
+                    if let Some(children) = node.iter_children() {
+                        open.extend(children.map(|child| Candidate {
+                            distance: distance_to(child.get_bouding_box(), point),
+                            item: Item::Node(child),
+                        }));
+                    }
+                }
+            }
+        }
+        found
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SpatialEntry<K>> + '_ {
+        self.entries.values().map(|entry| entry.as_ref())
+    }
+}
+
+fn distance_to(aabb: &Aabb2d, point: Vec2) -> f32 {
+    aabb.closest_point(point).distance(point)
+}
+
+enum Item<'a, K> {
+    Node(&'a QuadNode<SpatialEntry<K>>),
+    Entry(&'a SpatialEntry<K>),
+}
+
+struct Candidate<'a, K> {
+    distance: f32,
+    item: Item<'a, K>,
+}
+
+impl<K> PartialEq for Candidate<'_, K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<K> Eq for Candidate<'_, K> {}
+
+impl<K> Ord for Candidate<'_, K> {
+    // Reversed for a min-heap; at equal distance entries pop before nodes.
+    fn cmp(&self, other: &Self) -> Ordering {
+        let rank = |item: &Item<K>| matches!(item, Item::Entry(_)) as u8;
+        other
+            .distance
+            .total_cmp(&self.distance)
+            .then_with(|| rank(&self.item).cmp(&rank(&other.item)))
+    }
+}
+
+impl<K> PartialOrd for Candidate<'_, K> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod spatial_index_tests {
+    use std::time::Instant;
+
+    use bevy::math::bounding::BoundingVolume;
+
+    use super::super::test_rng::Rng;
+    use super::*;
+
+    /// How many times faster than a linear scan the index must answer the benchmark queries.
+    const MIN_SPEEDUP: u32 = 4;
+
+    fn bounds() -> Aabb2d {
+        Aabb2d {
+            min: Vec2::ZERO,
+            max: Vec2::splat(100.0),
+        }
+    }
+
+    fn random_tree(rng: &mut Rng, count: u32) -> (SpatialTree<u32>, Vec<SpatialEntry<u32>>) {
+        let mut tree = SpatialTree::new(bounds());
+        let mut entries = Vec::new();
+        for key in 0..count {
+            let aabb = Aabb2d::new(rng.vec2(0.0, 100.0), Vec2::splat(rng.range(0.0, 1.5)));
+            tree.upsert(key, aabb).unwrap();
+            entries.push(SpatialEntry { key, aabb });
+        }
+        (tree, entries)
+    }
+
+    fn sorted(mut keys: Vec<u32>) -> Vec<u32> {
+        keys.sort_unstable();
+        keys
+    }
+
+    fn brute_radius(entries: &[SpatialEntry<u32>], center: Vec2, radius: f32) -> Vec<u32> {
+        let circle = BoundingCircle::new(center, radius);
+        entries
+            .iter()
+            .filter(|e| circle.intersects(&e.aabb))
+            .map(|e| e.key)
+            .collect()
+    }
+
+    fn brute_nearest(entries: &[SpatialEntry<u32>], point: Vec2, k: usize) -> Vec<f32> {
+        let mut distances: Vec<f32> = entries
+            .iter()
+            .map(|e| distance_to(&e.aabb, point))
+            .collect();
+        distances.sort_by(f32::total_cmp);
+        distances.truncate(k);
+        distances
+    }
+
+    #[test]
+    fn test_upsert_moves_and_remove_forgets() {
+        let mut tree = SpatialTree::new(bounds());
+        tree.upsert(1, Aabb2d::new(Vec2::new(10.0, 10.0), Vec2::ONE))
+            .unwrap();
+        tree.upsert(2, Aabb2d::new(Vec2::new(90.0, 90.0), Vec2::ONE))
+            .unwrap();
+        tree.upsert(1, Aabb2d::new(Vec2::new(50.0, 50.0), Vec2::ONE))
+            .unwrap();
+        assert_eq!(tree.len(), 2);
+        assert_eq!(
+            tree.within_radius(Vec2::new(10.0, 10.0), 3.0),
+            Vec::<u32>::new()
+        );
+        assert_eq!(tree.within_radius(Vec2::new(50.0, 50.0), 3.0), vec![1]);
+
+        assert!(tree.remove(1).is_some());
+        assert!(tree.remove(1).is_none());
+        assert_eq!(tree.in_aabb(&bounds()), vec![2]);
+    }
+
+    #[test]
+    fn test_queries_match_brute_force() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        let (mut tree, mut entries) = random_tree(&mut rng, 500);
+
+        // Move half of the entries and drop a few to exercise incremental updates.
+        for entry in entries.iter_mut().step_by(2) {
+            entry.aabb = Aabb2d::new(rng.vec2(0.0, 100.0), entry.aabb.half_size());
+            tree.upsert(entry.key, entry.aabb).unwrap();
+        }
+        for key in (0..500).step_by(7) {
+            tree.remove(key);
+        }
+        entries.retain(|e| e.key % 7 != 0);
+        assert_eq!(tree.len(), entries.len());
+
+        for _ in 0..50 {
+            let center = rng.vec2(0.0, 100.0);
+            let radius = rng.range(0.0, 20.0);
+            assert_eq!(
+                sorted(tree.within_radius(center, radius)),
+                sorted(brute_radius(&entries, center, radius))
+            );
+
+            let area = Aabb2d::new(center, rng.vec2(0.0, 15.0));
+            let expected = entries
+                .iter()
+                .filter(|e| area.intersects(&e.aabb))
+                .map(|e| e.key)
+                .collect();
+            assert_eq!(sorted(tree.in_aabb(&area)), sorted(expected));
+
+            let k = 1 + (rng.next() * 10.0) as usize;
+            let nearest: Vec<f32> = tree.nearest(center, k).iter().map(|hit| hit.1).collect();
+            assert_eq!(nearest, brute_nearest(&entries, center, k));
+
+            let direction = Dir2::new(rng.vec2(0.0, 100.0) - Vec2::splat(50.0)).unwrap_or(Dir2::X);
+            let max_distance = rng.range(0.0, 80.0);
+            let ray = RayCast2d::new(center, direction, max_distance);
+            let mut expected: Vec<(u32, f32)> = entries
+                .iter()
+                .filter_map(|e| ray.aabb_intersection_at(&e.aabb).map(|d| (e.key, d)))
+                .collect();
+            expected.sort_by(|a, b| a.1.total_cmp(&b.1));
+            let hits = tree.ray(center, direction, max_distance);
+            let distances = |hits: &[(u32, f32)]| hits.iter().map(|h| h.1).collect::<Vec<_>>();
+            assert_eq!(distances(&hits), distances(&expected));
+        }
+    }
+
+    #[test]
+    fn test_nearest_returns_all_when_k_exceeds_len() {
+        let mut tree = SpatialTree::new(bounds());
+        tree.upsert('a', Aabb2d::new(Vec2::new(10.0, 10.0), Vec2::ZERO))
+            .unwrap();
+        tree.upsert('b', Aabb2d::new(Vec2::new(20.0, 10.0), Vec2::ZERO))
+            .unwrap();
+        let nearest = tree.nearest(Vec2::new(18.0, 10.0), 5);
+        assert_eq!(nearest, vec![('b', 2.0), ('a', 8.0)]);
+    }
+
+    /// The index must beat a linear scan by [`MIN_SPEEDUP`]; timing only means something in
+    /// release builds, so run with `cargo test --release -- --ignored`.
+    #[test]
+    #[ignore]
+    fn bench_against_brute_force() {
+        let mut rng = Rng(42);
+        let (tree, entries) = random_tree(&mut rng, 10_000);
+        let queries: Vec<(Vec2, f32)> = (0..2_000)
+            .map(|_| (rng.vec2(0.0, 100.0), rng.range(1.0, 5.0)))
+            .collect();
+
+        let start = Instant::now();
+        let indexed: usize = queries
+            .iter()
+            .map(|(c, r)| tree.within_radius(*c, *r).len())
+            .sum();
+        let indexed_time = start.elapsed();
+
+        let start = Instant::now();
+        let brute: usize = queries
+            .iter()
+            .map(|(c, r)| brute_radius(&entries, *c, *r).len())
+            .sum();
+        let brute_time = start.elapsed();
+        assert_eq!(indexed, brute);
+        assert!(
+            indexed_time * MIN_SPEEDUP < brute_time,
+            "radius: quadtree {indexed_time:?}, brute force {brute_time:?}"
+        );
+
+        let start = Instant::now();
+        let indexed: f32 = queries.iter().map(|(c, _)| tree.nearest(*c, 8)[7].1).sum();
+        let indexed_time = start.elapsed();
+
+        let start = Instant::now();
+        let brute: f32 = queries
+            .iter()
+            .map(|(c, _)| brute_nearest(&entries, *c, 8)[7])
+            .sum();
+        let brute_time = start.elapsed();
+        assert_eq!(indexed, brute);
+        assert!(
+            indexed_time * MIN_SPEEDUP < brute_time,
+            "k-nearest: quadtree {indexed_time:?}, brute force {brute_time:?}"
+        );
+    }
+}
@@ -0,0 +1,298 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::{
+    ecs::system::SystemParam, math::bounding::Aabb2d, prelude::*, transform::TransformSystems,
+};
+
+use super::spatial_debug::QueryRegion;
+use super::{SpatialQueryLog, SpatialTree};
+
+/// Keeps a [`SpatialIndex`] of every entity with [`SpatialIndexed`] and a [`GlobalTransform`].
+///
+/// The index is updated in `PostUpdate` after transform propagation, only for entities whose
+/// transform or footprint changed, so queries see positions as of the end of the last frame.
+pub struct SpatialIndexPlugin {
+    /// Initial area of the quadtree; the root grows to fit entities outside it.
+    pub bounds: Aabb2d,
+    pub plane: SpatialPlane,
+}
+
+impl Default for SpatialIndexPlugin {
+    fn default() -> Self {
+        Self {
+            bounds: Aabb2d::new(Vec2::ZERO, Vec2::splat(512.0)),
+            plane: SpatialPlane::default(),
+        }
+    }
+}
+
+impl Plugin for SpatialIndexPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SpatialIndex {
+            tree: SpatialTree::new(self.bounds),
+            plane: self.plane,
+        })
+        .add_systems(
+            PostUpdate,
+            update_spatial_index.after(TransformSystems::Propagate),
+        )
+        .add_observer(remove_from_spatial_index);
+    }
+}
+
+/// Unbounded rays are drawn this long by the debug overlay.
+const DRAWN_RAY_LENGTH: f32 = 100.0;
+
+/// World plane positions are projected onto before indexing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SpatialPlane {
+    /// Ground plane of a Y-up 3D world; `Vec2` is `(x, z)`.
+    #[default]
+    XZ,
+    /// Screen plane of a 2D world; `Vec2` is `(x, y)`.
+    XY,
+}
+
+impl SpatialPlane {
+    pub fn project(self, point: Vec3) -> Vec2 {
+        match self {
+            SpatialPlane::XZ => Vec2::new(point.x, point.z),
+            SpatialPlane::XY => point.truncate(),
+        }
+    }
+
+    /// Inverse of [`project`](Self::project), `height` along the plane normal.
+    pub fn unproject(self, point: Vec2, height: f32) -> Vec3 {
+        match self {
+            SpatialPlane::XZ => Vec3::new(point.x, height, point.y),
+            SpatialPlane::XY => point.extend(height),
+        }
+    }
+
+    /// Rotation from the XY plane onto this one, e.g. for gizmo rects and circles.
+    pub fn rotation(self) -> Quat {
+        match self {
+            SpatialPlane::XZ => Quat::from_rotation_x(FRAC_PI_2),
+            SpatialPlane::XY => Quat::IDENTITY,
+        }
+    }
+}
+
+/// Adds the entity to the [`SpatialIndex`], as a rectangle of `half_size` around its
+/// projected position (a point by default).
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct SpatialIndexed {
+    pub half_size: Vec2,
+}
+
+impl SpatialIndexed {
+    pub fn new(half_size: Vec2) -> Self {
+        Self { half_size }
+    }
+}
+
+#[derive(Resource)]
+pub struct SpatialIndex {
+    tree: SpatialTree<Entity>,
+    plane: SpatialPlane,
+}
+
+impl SpatialIndex {
+    pub fn tree(&self) -> &SpatialTree<Entity> {
+        &self.tree
+    }
+
+    pub fn plane(&self) -> SpatialPlane {
+        self.plane
+    }
+}
+
+pub(crate) fn update_spatial_index(
+    mut index: ResMut<SpatialIndex>,
+    moved: Query<
+        (Entity, &SpatialIndexed, &GlobalTransform),
+        Or<(Changed<GlobalTransform>, Changed<SpatialIndexed>)>,
+    >,
+) {
+    let index = &mut *index;
+    for (entity, indexed, transform) in &moved {
+        let center = index.plane.project(transform.translation());
+        if let Err(error) = index
+            .tree
+            .upsert(entity, Aabb2d::new(center, indexed.half_size))
+        {
+            warn!("{entity} dropped from the spatial index: {error}");
+        }
+    }
+}
+
+fn remove_from_spatial_index(trigger: On<Remove, SpatialIndexed>, mut index: ResMut<SpatialIndex>) {
+    index.tree.remove(trigger.event_target());
+}
+
+/// Read access to the [`SpatialIndex`] in world coordinates. Positions are projected onto
+/// the index plane, and distances are measured on it.
+///
+/// With [`SpatialDebugPlugin`](super::SpatialDebugPlugin) the queries are also drawn.
+#[derive(SystemParam)]
+pub struct SpatialQuery<'w> {
+    index: Res<'w, SpatialIndex>,
+    log: Option<Res<'w, SpatialQueryLog>>,
+}
+
+impl SpatialQuery<'_> {
+    fn project(&self, point: Vec3) -> Vec2 {
+        self.index.plane.project(point)
+    }
+
+    /// The debug log, when the overlay wants queries drawn.
+    fn log(&self) -> Option<&SpatialQueryLog> {
+        self.log.as_deref().filter(|log| log.is_recording())
+    }
+
+    /// Entities whose footprint comes within `radius` of `center`.
+    pub fn within_radius(&self, center: Vec3, radius: f32) -> Vec<Entity> {
+        let center = self.project(center);
+        let hits = self.index.tree.within_radius(center, radius);
+        if let Some(log) = self.log() {
+            log.record(QueryRegion::Circle { center, radius }, hits.iter().copied());
+        }
+        hits
+    }
+
+    /// Entities whose footprint overlaps the box spanned by the two corners.
+    pub fn in_aabb(&self, a: Vec3, b: Vec3) -> Vec<Entity> {
+        let (a, b) = (self.project(a), self.project(b));
+        let area = Aabb2d {
+            min: a.min(b),
+            max: a.max(b),
+        };
+        let hits = self.index.tree.in_aabb(&area);
+        if let Some(log) = self.log() {
+            log.record(QueryRegion::Aabb(area), hits.iter().copied());
+        }
+        hits
+    }
+
+    /// Entities along the ray, nearest first; empty when `direction` is perpendicular to
+    /// the plane.
+    pub fn ray(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> Vec<(Entity, f32)> {
+        let Ok(direction) = Dir2::new(self.project(direction)) else {
+            return Vec::new();
+        };
+        let origin = self.project(origin);
+        let hits = self.index.tree.ray(origin, direction, max_distance);
+        if let Some(log) = self.log() {
+            let end = origin + direction * max_distance.min(DRAWN_RAY_LENGTH);
+            log.record(
+                QueryRegion::Ray { origin, end },
+                hits.iter().map(|(entity, _)| *entity),
+            );
+        }
+        hits
+    }
+
+    /// The `k` entities nearest to `point`, nearest first.
+    pub fn nearest(&self, point: Vec3, k: usize) -> Vec<(Entity, f32)> {
+        let point = self.project(point);
+        let hits = self.index.tree.nearest(point, k);
+        if let Some(log) = self.log() {
+            let radius = hits.last().map_or(0.0, |(_, distance)| *distance);
+            log.record(
+                QueryRegion::Nearest { point, radius },
+                hits.iter().map(|(entity, _)| *entity),
+            );
+        }
+        hits
+    }
+}
+
+#[cfg(test)]
+mod spatial_plugin_tests {
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::transform::TransformPlugin;
+
+    use super::*;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            TransformPlugin,
+            SpatialIndexPlugin::default(),
+        ));
+        app
+    }
+
+    fn index(app: &App) -> &SpatialTree<Entity> {
+        app.world().resource::<SpatialIndex>().tree()
+    }
+
+    #[test]
+    fn test_index_follows_transforms() {
+        let mut app = app();
+        let near = app
+            .world_mut()
+            .spawn((
+                Transform::from_xyz(1.0, 5.0, 1.0),
+                SpatialIndexed::default(),
+            ))
+            .id();
+        let far = app
+            .world_mut()
+            .spawn((
+                Transform::from_xyz(40.0, 0.0, 40.0),
+                SpatialIndexed::new(Vec2::ONE),
+            ))
+            .id();
+        app.world_mut().spawn(Transform::from_xyz(0.0, 0.0, 0.0));
+        app.update();
+
+        assert_eq!(index(&app).len(), 2);
+        assert_eq!(index(&app).within_radius(Vec2::ZERO, 2.0), vec![near]);
+
+        app.world_mut()
+            .entity_mut(far)
+            .insert(Transform::from_xyz(0.0, 0.0, -2.0));
+        app.update();
+        let mut nearby = index(&app).within_radius(Vec2::ZERO, 2.0);
+        nearby.sort();
+        let mut expected = vec![near, far];
+        expected.sort();
+        assert_eq!(nearby, expected);
+
+        app.world_mut().despawn(near);
+        app.world_mut().entity_mut(far).remove::<SpatialIndexed>();
+        app.update();
+        assert!(index(&app).is_empty());
+    }
+
+    #[test]
+    fn test_spatial_query_projects_world_positions() {
+        let mut app = app();
+        let target = app
+            .world_mut()
+            .spawn((
+                Transform::from_xyz(10.0, 3.0, 0.0),
+                SpatialIndexed::default(),
+            ))
+            .id();
+        app.update();
+
+        let hits = app
+            .world_mut()
+            .run_system_once(|query: SpatialQuery| {
+                (
+                    query.ray(Vec3::ZERO, Vec3::new(1.0, 1.0, 0.0), 20.0),
+                    query.ray(Vec3::ZERO, Vec3::Y, 20.0),
+                    query.nearest(Vec3::new(0.0, 100.0, 0.0), 1),
+                    query.in_aabb(Vec3::new(12.0, 0.0, 1.0), Vec3::new(8.0, 0.0, -1.0)),
+                )
+            })
+            .unwrap();
+        assert_eq!(hits.0, vec![(target, 10.0)]);
+        assert!(hits.1.is_empty());
+        assert_eq!(hits.2, vec![(target, 10.0)]);
+        assert_eq!(hits.3, vec![target]);
+    }
+}
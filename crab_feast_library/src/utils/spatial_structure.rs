@@ -0,0 +1,211 @@
+use std::fmt;
+
+use bevy::math::bounding::{Aabb3d, BoundingSphere, RayCast3d};
+use bevy::prelude::*;
+
+/// A set of keyed 3D bounding boxes that can be searched by region and by ray.
+///
+/// Implemented by [`Octree`](super::Octree), which adapts to uneven distributions and large
+/// size differences, and [`HashGrid`](super::HashGrid), which is cheapest for many similar
+/// small objects spread evenly, so callers can pick a structure per use case and swap it
+/// without touching the queries.
+pub trait SpatialStructure<K: Copy> {
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The box `key` was last inserted with.
+    fn get(&self, key: K) -> Option<Aabb3d>;
+
+    /// Adds `key`, replacing its previous box if it was already present. On error the key
+    /// is no longer indexed.
+    fn insert(&mut self, key: K, aabb: Aabb3d) -> Result<(), SpatialError>;
+
+    fn remove(&mut self, key: K) -> Option<Aabb3d>;
+
+    /// Moves a key that is already indexed; [`SpatialError::NotFound`] otherwise.
+    fn update(&mut self, key: K, aabb: Aabb3d) -> Result<(), SpatialError> {
+        if self.get(key).is_none() {
+            return Err(SpatialError::NotFound);
+        }
+        self.insert(key, aabb)
+    }
+
+    /// Keys whose box overlaps `area`, in no particular order.
+    fn query_aabb(&self, area: &Aabb3d) -> Vec<K>;
+
+    /// Keys whose box overlaps `sphere`, in no particular order.
+    fn query_sphere(&self, sphere: &BoundingSphere) -> Vec<K>;
+
+    /// Keys hit by the ray, nearest first, with the distance along the ray to each box
+    /// (`0.0` when the origin is inside it).
+    fn raycast(&self, ray: &RayCast3d) -> Vec<(K, f32)>;
+
+    /// Draws the structure's cells and the indexed boxes with gizmos.
+    fn draw(&self, gizmos: &mut Gizmos);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SpatialError {
+    /// The box has a NaN or infinite corner.
+    NonFinite(Aabb3d),
+    /// The box is outside a structure that does not grow.
+    OutOfBounds { aabb: Aabb3d, boundary: Aabb3d },
+    /// The box spans more hash grid cells than a single entry may occupy.
+    TooLarge { cells: u64, limit: u64 },
+    /// [`SpatialStructure::update`] was called for a key that is not indexed.
+    NotFound,
+}
+
+impl fmt::Display for SpatialError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NonFinite(aabb) => write!(f, "{:?}..{:?} is not finite", aabb.min, aabb.max),
+            Self::OutOfBounds { aabb, boundary } => write!(
+                f,
+                "{:?}..{:?} is outside the bounds {:?}..{:?}",
+                aabb.min, aabb.max, boundary.min, boundary.max
+            ),
+            Self::TooLarge { cells, limit } => {
+                write!(
+                    f,
+                    "box spans {cells} grid cells, more than the limit of {limit}"
+                )
+            }
+            Self::NotFound => write!(f, "key is not indexed"),
+        }
+    }
+}
+
+impl std::error::Error for SpatialError {}
+
+pub(crate) fn is_finite(aabb: &Aabb3d) -> bool {
+    aabb.min.is_finite() && aabb.max.is_finite()
+}
+
+/// Gizmo transform of a unit cuboid scaled to `aabb`.
+pub(crate) fn cuboid(aabb: &Aabb3d) -> Transform {
+    Transform::from_translation(((aabb.min + aabb.max) * 0.5).into())
+        .with_scale((aabb.max - aabb.min).into())
+}
+
+#[cfg(test)]
+mod spatial_structure_tests {
+    use bevy::math::bounding::IntersectsVolume;
+    use bevy::math::Vec3A;
+
+    use super::super::test_rng::Rng;
+    use super::super::{HashGrid, Octree, QuadTreeConfig};
+    use super::*;
+
+    fn random_aabb(rng: &mut Rng, spread: f32) -> Aabb3d {
+        let half_size = rng.vec3(0.0, 3.0);
+        Aabb3d::new(rng.vec3(-spread, spread), half_size)
+    }
+
+    fn sorted(mut keys: Vec<u32>) -> Vec<u32> {
+        keys.sort_unstable();
+        keys
+    }
+
+    /// Inserts, moves and removes random boxes, then compares every query with a linear scan.
+    fn check_against_brute_force(mut structure: impl SpatialStructure<u32>, seed: u64) {
+        let mut rng = Rng(seed);
+        let mut boxes: Vec<Option<Aabb3d>> = Vec::new();
+        for key in 0..400 {
+            // A few boxes land outside the initial octree bounds.
+            let aabb = random_aabb(&mut rng, if key % 20 == 0 { 120.0 } else { 40.0 });
+            structure.insert(key, aabb).unwrap();
+            boxes.push(Some(aabb));
+        }
+        for key in (0..400).step_by(3) {
+            let aabb = random_aabb(&mut rng, 40.0);
+            structure.update(key, aabb).unwrap();
+            boxes[key as usize] = Some(aabb);
+        }
+        for key in (0..400).step_by(7) {
+            assert_eq!(structure.remove(key), boxes[key as usize].take());
+        }
+        assert_eq!(structure.remove(0), None);
+        assert_eq!(
+            structure.update(0, random_aabb(&mut rng, 1.0)),
+            Err(SpatialError::NotFound)
+        );
+        let live = || {
+            boxes
+                .iter()
+                .enumerate()
+                .filter_map(|(key, aabb)| aabb.map(|aabb| (key as u32, aabb)))
+        };
+        assert_eq!(structure.len(), live().count());
+
+        for _ in 0..50 {
+            let area = random_aabb(&mut rng, 50.0);
+            let expected = live()
+                .filter(|(_, aabb)| area.intersects(aabb))
+                .map(|(key, _)| key)
+                .collect();
+            assert_eq!(sorted(structure.query_aabb(&area)), sorted(expected));
+
+            let sphere = BoundingSphere::new(rng.vec3(-50.0, 50.0), rng.range(0.0, 15.0));
+            let expected = live()
+                .filter(|(_, aabb)| sphere.intersects(aabb))
+                .map(|(key, _)| key)
+                .collect();
+            assert_eq!(sorted(structure.query_sphere(&sphere)), sorted(expected));
+
+            let direction = Dir3::new(rng.vec3(-1.0, 1.0)).unwrap_or(Dir3::X);
+            let ray = RayCast3d::new(rng.vec3(-60.0, 60.0), direction, rng.range(10.0, 150.0));
+            let mut expected: Vec<(u32, f32)> = live()
+                .filter_map(|(key, aabb)| ray.aabb_intersection_at(&aabb).map(|t| (key, t)))
+                .collect();
+            expected.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+            let mut hits = structure.raycast(&ray);
+            assert!(hits.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+            hits.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+            assert_eq!(hits, expected);
+        }
+    }
+
+    #[test]
+    fn test_octree_matches_brute_force() {
+        let config = QuadTreeConfig {
+            max_items: 4,
+            min_size: 0.5,
+            ..default()
+        };
+        for seed in [1, 77, 4242] {
+            let bounds = Aabb3d::new(Vec3::ZERO, Vec3::splat(50.0));
+            check_against_brute_force(Octree::with_config(bounds, config), seed);
+        }
+    }
+
+    #[test]
+    fn test_hash_grid_matches_brute_force() {
+        for (seed, cell_size) in [(1, 4.0), (77, 10.0), (4242, 2.5)] {
+            check_against_brute_force(HashGrid::new(cell_size), seed);
+        }
+    }
+
+    #[test]
+    fn test_rejects_non_finite_boxes() {
+        let aabb = Aabb3d {
+            min: Vec3A::ZERO,
+            max: Vec3A::new(f32::NAN, 1.0, 1.0),
+        };
+        let mut octree = Octree::new(Aabb3d::new(Vec3::ZERO, Vec3::ONE));
+        let mut grid = HashGrid::new(1.0);
+        for structure in [&mut octree as &mut dyn SpatialStructure<u32>, &mut grid] {
+            structure
+                .insert(1, Aabb3d::new(Vec3::ZERO, Vec3::ONE))
+                .unwrap();
+            assert!(matches!(
+                structure.insert(1, aabb),
+                Err(SpatialError::NonFinite(_))
+            ));
+            assert!(structure.is_empty());
+        }
+    }
+}
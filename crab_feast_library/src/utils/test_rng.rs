@@ -0,0 +1,34 @@
+//! Small deterministic xorshift so randomized test layouts are reproducible without a rand
+//! dependency.
+
+use bevy::math::{Vec2, Vec3};
+
+pub(crate) struct Rng(pub(crate) u64);
+
+impl Rng {
+    /// Uniform in `[0, 1)`.
+    pub(crate) fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    pub(crate) fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next()
+    }
+
+    /// Every component in `[min, max)`.
+    pub(crate) fn vec2(&mut self, min: f32, max: f32) -> Vec2 {
+        Vec2::new(self.range(min, max), self.range(min, max))
+    }
+
+    /// Every component in `[min, max)`.
+    pub(crate) fn vec3(&mut self, min: f32, max: f32) -> Vec3 {
+        Vec3::new(
+            self.range(min, max),
+            self.range(min, max),
+            self.range(min, max),
+        )
+    }
+}
@@ -1,7 +1,15 @@
 use bevy::{prelude::*, winit::WinitSettings};
 use bevy_inspector_egui::{bevy_egui::EguiPlugin, quick::WorldInspectorPlugin};
+use crab_feast::{NetMode, NetOptions};
 
 fn main() {
+    // `--host` starts a server that also plays, `--connect` joins one; without either the
+    // menu asks.
+    let net = NetOptions::from_env_and_args().unwrap_or_else(|e| {
+        // Logging is not set up yet.
+        eprintln!("{e}\n\n{}", NetOptions::USAGE);
+        std::process::exit(2);
+    });
     let mut app = App::new();
     app.add_plugins(DefaultPlugins.set(WindowPlugin {
         primary_window: Some(Window {
@@ -15,12 +23,18 @@ fn main() {
     }))
     .insert_resource(WinitSettings {
         focused_mode: bevy::winit::UpdateMode::Continuous,
-        ..Default::default()
+        // A networked instance must keep ticking while another window has focus.
+        unfocused_mode: if net.mode == NetMode::None {
+            WinitSettings::default().unfocused_mode
+        } else {
+            bevy::winit::UpdateMode::Continuous
+        },
     })
     .add_plugins(EguiPlugin::default())
     .add_plugins(WorldInspectorPlugin::new());
 
     crab_feast::build_app(&mut app);
+    app.insert_resource(net.config).insert_state(net.mode);
 
     app.run();
 }